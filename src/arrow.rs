@@ -0,0 +1,129 @@
+//! Apache Arrow support, behind the `arrow` feature.
+//!
+//! Category columns are usually dictionary encoded, a column of keys
+//! into a small array of distinct values, which is what the pool is
+//! for a whole process. `dictionary_array` builds such a column from
+//! handles, finding each distinct string by its handle rather than by
+//! hashing its bytes, and `intern_column` goes the other way, interning
+//! each distinct value of a dictionary once however many rows refer to
+//! it, so reading the same categories back from Arrow or Parquet data
+//! allocates nothing once they are in the pool.
+
+use crate::Str;
+use arrow_array::{
+    cast::AsArray, types::Int32Type, Array, DictionaryArray, Int32Array, StringArray,
+};
+use arrow_schema::{ArrowError, DataType};
+use fxhash::FxHashMap;
+use std::{num::NonZeroUsize, sync::Arc};
+
+/// Build a dictionary encoded string column from `strs`, which may
+/// yield `Str`s or, for null rows, `Option<Str>`s. The dictionary holds
+/// each distinct string once, in the order of first appearance.
+pub fn dictionary_array<I>(strs: I) -> Result<DictionaryArray<Int32Type>, ArrowError>
+where
+    I: IntoIterator,
+    I::Item: Into<Option<Str>>,
+{
+    let mut index: FxHashMap<NonZeroUsize, i32> = FxHashMap::default();
+    let mut values: Vec<&'static str> = Vec::new();
+    let mut keys: Vec<Option<i32>> = Vec::new();
+    for t in strs {
+        let key = match t.into() {
+            None => None,
+            Some(t) => Some(match index.get(&t.as_raw()) {
+                Some(key) => *key,
+                None => {
+                    let key = i32::try_from(values.len())
+                        .map_err(|_| ArrowError::DictionaryKeyOverflowError)?;
+                    index.insert(t.as_raw(), key);
+                    values.push(t.as_static());
+                    key
+                }
+            }),
+        };
+        keys.push(key);
+    }
+    let values = StringArray::from_iter_values(values);
+    DictionaryArray::try_new(Int32Array::from(keys), Arc::new(values))
+}
+
+/// Intern every value of `array`, returning None for null rows.
+/// `array` may be a `Utf8`, `LargeUtf8` or `Utf8View` column, or a
+/// dictionary of one with any key type, whose values are each interned
+/// once. Strings that can't be interned fail with an
+/// `ArrowError::ExternalError` holding the `Error`.
+pub fn intern_column(array: &dyn Array) -> Result<Vec<Option<Str>>, ArrowError> {
+    match array.as_any_dictionary_opt() {
+        None => intern_strings(array),
+        Some(dict) => {
+            let values = intern_strings(dict.values().as_ref())?;
+            Ok(dict
+                .normalized_keys()
+                .into_iter()
+                .enumerate()
+                .map(|(i, key)| if array.is_null(i) { None } else { values[key] })
+                .collect())
+        }
+    }
+}
+
+fn intern_strings(array: &dyn Array) -> Result<Vec<Option<Str>>, ArrowError> {
+    fn intern<'a>(
+        strs: impl Iterator<Item = Option<&'a str>>,
+    ) -> Result<Vec<Option<Str>>, ArrowError> {
+        strs.map(|s| s.map(Str::try_from).transpose())
+            .collect::<crate::Result<_>>()
+            .map_err(|e| ArrowError::ExternalError(Box::new(e)))
+    }
+    match array.data_type() {
+        DataType::Utf8 => intern(array.as_string::<i32>().iter()),
+        DataType::LargeUtf8 => intern(array.as_string::<i64>().iter()),
+        DataType::Utf8View => intern(array.as_string_view().iter()),
+        t => Err(ArrowError::InvalidArgumentError(format!(
+            "{t} is not a string column"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow_array::{types::UInt8Type, Int64Array, StringViewArray};
+
+    #[test]
+    fn test_arrow() {
+        let [a, b] = ["arrow a", "arrow b"].map(|s| Str::try_from(s).unwrap());
+        let dict = dictionary_array([Some(a), Some(b), None, Some(a)]).unwrap();
+        assert_eq!(
+            dict.keys(),
+            &Int32Array::from(vec![Some(0), Some(1), None, Some(0)])
+        );
+        let values = dict.values().as_string::<i32>();
+        assert_eq!(
+            values.iter().collect::<Vec<_>>(),
+            [Some("arrow a"), Some("arrow b")]
+        );
+        assert_eq!(dictionary_array([b, b]).unwrap().values().len(), 1);
+        let got = intern_column(&dict).unwrap();
+        assert_eq!(got, [Some(a), Some(b), None, Some(a)]);
+        // dictionaries read from elsewhere, with other key types
+        let other: DictionaryArray<UInt8Type> = vec![Some("arrow b"), None, Some("arrow c")]
+            .into_iter()
+            .collect();
+        let got = intern_column(&other).unwrap();
+        assert_eq!(got[..2], [Some(b), None]);
+        assert_eq!(got[2].as_deref(), Some("arrow c"));
+        let view = StringViewArray::from(vec![Some("arrow a"), None]);
+        assert_eq!(intern_column(&view).unwrap(), [Some(a), None]);
+        assert!(matches!(
+            intern_column(&Int64Array::from(vec![1])),
+            Err(ArrowError::InvalidArgumentError(_))
+        ));
+        let long = StringArray::from(vec!["x".repeat(Str::MAX_LEN + 1)]);
+        assert!(matches!(
+            intern_column(&long),
+            Err(ArrowError::ExternalError(_))
+        ));
+    }
+}
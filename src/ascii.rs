@@ -0,0 +1,111 @@
+//! Interned strings that are guaranteed to be ASCII
+
+use crate::{stackbuf::StackBuf, Error, Result, Str};
+use std::{fmt, ops::Deref};
+
+/// A `Str` that is guaranteed to contain only ASCII, checked when it is
+/// interned.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsciiStr(Str);
+
+impl AsciiStr {
+    /// The interned string
+    pub fn get(self) -> Str {
+        self.0
+    }
+
+    /// The string, which is known to be ASCII and so can be treated
+    /// as bytes one for one
+    pub fn as_ascii_str(self) -> &'static str {
+        self.0.as_static()
+    }
+
+    pub fn as_bytes(self) -> &'static [u8] {
+        self.0.as_static().as_bytes()
+    }
+
+    /// Compare with `other`, ignoring ASCII case
+    pub fn eq_ignore_ascii_case(self, other: &str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+
+    /// Intern the lowercase form of the string
+    pub fn to_ascii_lowercase(self) -> Result<AsciiStr> {
+        self.map(|b| b.to_ascii_lowercase())
+    }
+
+    /// Intern the uppercase form of the string
+    pub fn to_ascii_uppercase(self) -> Result<AsciiStr> {
+        self.map(|b| b.to_ascii_uppercase())
+    }
+
+    fn map(self, f: impl Fn(u8) -> u8) -> Result<AsciiStr> {
+        let bytes = self.as_bytes();
+        if bytes.iter().all(|b| f(*b) == *b) {
+            return Ok(self);
+        }
+        let mut buf = StackBuf::new();
+        for b in bytes {
+            buf.push(f(*b) as char);
+        }
+        Ok(AsciiStr(buf.intern()?))
+    }
+}
+
+impl TryFrom<&str> for AsciiStr {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        if !s.is_ascii() {
+            return Err(Error::Invalid("string is not ascii"));
+        }
+        Ok(AsciiStr(Str::try_from(s)?))
+    }
+}
+
+impl TryFrom<Str> for AsciiStr {
+    type Error = Error;
+
+    fn try_from(s: Str) -> Result<Self> {
+        if !s.is_ascii() {
+            return Err(Error::Invalid("string is not ascii"));
+        }
+        Ok(AsciiStr(s))
+    }
+}
+
+impl From<AsciiStr> for Str {
+    fn from(s: AsciiStr) -> Str {
+        s.0
+    }
+}
+
+impl Deref for AsciiStr {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.0.as_static()
+    }
+}
+
+impl fmt::Debug for AsciiStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ascii() {
+        let s = AsciiStr::try_from("Content-Type").unwrap();
+        assert!(AsciiStr::try_from("café").is_err());
+        assert!(s.eq_ignore_ascii_case("content-type"));
+        let l = s.to_ascii_lowercase().unwrap();
+        assert_eq!(&*l, "content-type");
+        assert_eq!(l.to_ascii_lowercase().unwrap(), l);
+        assert_eq!(&*s.to_ascii_uppercase().unwrap(), "CONTENT-TYPE");
+    }
+}
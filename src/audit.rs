@@ -0,0 +1,85 @@
+//! Recording every string added to the pool, behind the `audit` feature
+
+use crate::{Str, ROOT};
+use std::time::SystemTime;
+
+/// A string that was added to the pool, passed to the `AuditSink`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The new string
+    pub string: Str,
+    /// When it was inserted
+    pub time: SystemTime,
+    /// The tag of the innermost enclosing `tagged` call on the thread
+    /// that interned it, if any
+    pub tag: Option<&'static str>,
+}
+
+/// Where audit records go. Closures taking an `&AuditRecord` are sinks.
+pub trait AuditSink: Send {
+    /// Record a string that was just added to the pool. This is called
+    /// with the pool lock held, so it must not intern anything, and
+    /// should hand the record off quickly, to a buffered file or a
+    /// channel, rather than block on I/O.
+    fn record(&mut self, record: &AuditRecord);
+}
+
+impl<F: FnMut(&AuditRecord) + Send> AuditSink for F {
+    fn record(&mut self, record: &AuditRecord) {
+        self(record)
+    }
+}
+
+/// Send every string added to the pool from now on to `sink`, with
+/// the time and tag it was added with, replacing the sink set before,
+/// or stop recording with None. Records are made while the pool lock
+/// is held, so every insert is recorded, in order, and hits on strings
+/// that are already interned, which add nothing, aren't. The built in
+/// common strings are never recorded, and with `shared-pool`, strings
+/// interned while another copy's pool is shared are recorded by that
+/// copy's sink, if it has one.
+pub fn set_audit_sink(sink: Option<Box<dyn AuditSink>>) {
+    ROOT.lock().audit = sink;
+}
+
+/// Record `t`, which was just inserted, if there is a sink
+pub(crate) fn record(sink: &mut Option<Box<dyn AuditSink>>, t: Str) {
+    if let Some(sink) = sink {
+        sink.record(&AuditRecord {
+            string: t,
+            time: SystemTime::now(),
+            tag: crate::callsite::current_tag(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tagged;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_audit() {
+        let (tx, rx) = mpsc::channel();
+        let start = SystemTime::now();
+        set_audit_sink(Some(Box::new(move |r: &AuditRecord| {
+            let _ = tx.send(*r);
+        })));
+        let a = Str::try_from("audited a").unwrap();
+        Str::try_from("audited a").unwrap();
+        let b = tagged("audit test", || Str::try_from("audited b").unwrap());
+        Str::try_from("a").unwrap();
+        set_audit_sink(None);
+        Str::try_from("audited c").unwrap();
+        // other tests may be interning at the same time
+        let got: Vec<AuditRecord> = rx
+            .try_iter()
+            .filter(|r| r.string.starts_with("audited"))
+            .collect();
+        assert_eq!(got.len(), 2);
+        assert_eq!((got[0].string, got[0].tag), (a, None));
+        assert_eq!((got[1].string, got[1].tag), (b, Some("audit test")));
+        assert!(got.iter().all(|r| r.time >= start));
+    }
+}
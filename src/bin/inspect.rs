@@ -0,0 +1,105 @@
+//! Examine pool snapshots written by `write_snapshot`
+//!
+//! ```text
+//! hcstatic-str-inspect stats <snapshot>
+//! hcstatic-str-inspect prefix <snapshot> <prefix>
+//! hcstatic-str-inspect grep <snapshot> <regex>
+//! hcstatic-str-inspect diff <old> <new>
+//! ```
+
+use hcstatic_str::{snapshot_strings, Result};
+use regex::Regex;
+use std::{collections::HashSet, env, fs, process};
+
+const USAGE: &str = "usage:
+    hcstatic-str-inspect stats <snapshot>
+    hcstatic-str-inspect prefix <snapshot> <prefix>
+    hcstatic-str-inspect grep <snapshot> <regex>
+    hcstatic-str-inspect diff <old> <new>";
+
+fn load(path: &str) -> Result<Vec<String>> {
+    let bytes = fs::read(path)?;
+    #[cfg(feature = "zstd")]
+    let bytes = hcstatic_str::decompress_snapshot(&bytes)?.into_owned();
+    let strs = snapshot_strings(&bytes)?
+        .map(|s| s.map(String::from))
+        .collect();
+    strs
+}
+
+fn stats(strs: &[String]) {
+    let bytes: usize = strs.iter().map(|s| s.len()).sum();
+    let unique = strs.iter().collect::<HashSet<_>>().len();
+    println!("strings: {}", strs.len());
+    println!("unique:  {unique}");
+    println!("bytes:   {bytes}");
+    if let Some(max) = strs.iter().map(|s| s.len()).max() {
+        println!("mean:    {:.1}", bytes as f64 / strs.len() as f64);
+        println!("longest: {max}");
+    }
+    let mut hist = [0usize; 9];
+    for s in strs {
+        hist[(usize::BITS - s.len().leading_zeros()) as usize] += 1;
+    }
+    println!("lengths:");
+    for (i, n) in hist.iter().enumerate().filter(|(_, n)| **n > 0) {
+        let (lo, hi) = if i == 0 {
+            (0, 0)
+        } else {
+            (1 << (i - 1), (1 << i) - 1)
+        };
+        println!("  {lo:>3}..={hi:<3} {n}");
+    }
+}
+
+fn diff(old: &[String], new: &[String]) {
+    let in_old: HashSet<&String> = old.iter().collect();
+    let in_new: HashSet<&String> = new.iter().collect();
+    for s in old.iter().filter(|s| !in_new.contains(s)) {
+        println!("-{s:?}");
+    }
+    for s in new.iter().filter(|s| !in_old.contains(s)) {
+        println!("+{s:?}");
+    }
+}
+
+fn run(args: &[String]) -> Result<bool> {
+    match args {
+        [cmd, path] if cmd == "stats" => stats(&load(path)?),
+        [cmd, path, prefix] if cmd == "prefix" => load(path)?
+            .iter()
+            .filter(|s| s.starts_with(prefix.as_str()))
+            .for_each(|s| println!("{s:?}")),
+        [cmd, path, re] if cmd == "grep" => {
+            let re = match Regex::new(re) {
+                Ok(re) => re,
+                Err(e) => {
+                    eprintln!("invalid regex: {e}");
+                    return Ok(false);
+                }
+            };
+            load(path)?
+                .iter()
+                .filter(|s| re.is_match(s))
+                .for_each(|s| println!("{s:?}"))
+        }
+        [cmd, old, new] if cmd == "diff" => diff(&load(old)?, &load(new)?),
+        _ => {
+            eprintln!("{USAGE}");
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => (),
+        Ok(false) => process::exit(2),
+        Err(e) => {
+            eprintln!("error: {e}");
+            process::exit(1)
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Interning large batches from async code, behind the `tokio` feature
+
+use crate::{Result, Str, ROOT};
+use std::panic;
+use tokio::task;
+
+/// How many strings are interned each time the lock is taken
+const BATCH: usize = 4096;
+
+/// Intern every string in `strs` on tokio's blocking thread pool,
+/// returning their handles in the same order, so loading a large
+/// dictionary doesn't stall the runtime's worker threads on the pool
+/// lock or on growing the pool. The lock is taken once per few
+/// thousand strings and released in between, so other threads can
+/// intern while a batch is loading.
+///
+/// If a string can't be interned the error is returned, and the
+/// strings interned before it remain in the pool.
+pub async fn intern_many_blocking<S>(strs: Vec<S>) -> Result<Vec<Str>>
+where
+    S: AsRef<str> + Send + 'static,
+{
+    let res = task::spawn_blocking(move || {
+        let mut out = Vec::with_capacity(strs.len());
+        for batch in strs.chunks(BATCH) {
+            let mut root = ROOT.lock();
+            root.all.try_reserve(batch.len())?;
+            for s in batch {
+                out.push(root.intern(s.as_ref())?);
+            }
+        }
+        Ok(out)
+    })
+    .await;
+    match res {
+        Ok(res) => res,
+        Err(e) => panic::resume_unwind(e.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_intern_many_blocking() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let strs: Vec<String> = (0..10_000)
+            .map(|i| format!("blocking {}", i % 5000))
+            .collect();
+        let ts = rt.block_on(intern_many_blocking(strs.clone())).unwrap();
+        assert_eq!(ts.len(), strs.len());
+        for (s, t) in strs.iter().zip(&ts) {
+            assert_eq!(*t, Str::try_from(s.as_str()).unwrap());
+        }
+        let long = vec!["blocking ok".to_string(), "x".repeat(Str::MAX_LEN + 1)];
+        assert!(matches!(
+            rt.block_on(intern_many_blocking(long)),
+            Err(Error::TooLong { .. })
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+//! Bounding the memory a caller can add to the pool
+
+#[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+use crate::shared;
+use crate::{common, frozen, index, stats, Error, Result, Root, Str, MAX_LEN, ROOT, TRAILER_LEN};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The bytes an entry for a string of `len` bytes takes, which is what
+/// inserting it is charged
+pub(crate) fn entry_len(len: usize) -> usize {
+    let header = if len == MAX_LEN { 2 } else { 1 };
+    header + len + TRAILER_LEN
+}
+
+/// Intern `s`, charging for its entry if it isn't interned yet. Under
+/// the pool lock, `check` is passed the cost of the entry, and can
+/// refuse the insert by returning an error, and `commit` is passed it
+/// once the string has been inserted. Strings that are found are found
+/// without the lock, as with `Str::try_from`, and cost nothing.
+#[cfg_attr(feature = "track-callers", track_caller)]
+pub(crate) fn intern_charged(
+    s: &str,
+    check: impl FnOnce(&mut Root, usize) -> Result<()>,
+    commit: impl FnOnce(&mut Root, usize),
+) -> Result<Str> {
+    #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+    if let Some(pool) = shared::host() {
+        return pool.intern(s);
+    }
+    if s.len() > MAX_LEN {
+        return Err(Error::TooLong { len: s.len() });
+    }
+    if let Some(t) = common::get(s) {
+        stats::hit(t);
+        return Ok(t);
+    }
+    if let Some(res) = frozen::get(s) {
+        let t = res?;
+        stats::hit(t);
+        return Ok(t);
+    }
+    let hash = index::hash(s);
+    if let Some(t) = index::get_unlocked(hash, s) {
+        stats::hit(t);
+        return Ok(t);
+    }
+    let mut root = ROOT.lock();
+    if let Some(t) = root.all.get(hash, s) {
+        stats::hit(t);
+        return Ok(t);
+    }
+    let cost = entry_len(s.len());
+    check(&mut root, cost)?;
+    let t = root.intern_hashed(hash, s)?;
+    commit(&mut root, cost);
+    Ok(t)
+}
+
+/// An allowance of pool memory for one request or job, debited for
+/// the entry of every string interned through it that wasn't in the
+/// pool yet. Strings that are already interned cost nothing. Once it
+/// runs out, interning a new string fails with
+/// `Error::BudgetExhausted`, so the permanent memory a request can
+/// commit is bounded however much untrusted input it sees. A budget
+/// can be shared between threads.
+#[derive(Debug)]
+pub struct Budget {
+    remaining: AtomicUsize,
+}
+
+impl Budget {
+    /// A budget of `bytes` of entries
+    pub const fn new(bytes: usize) -> Self {
+        Budget {
+            remaining: AtomicUsize::new(bytes),
+        }
+    }
+
+    /// The bytes left to spend
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(Ordering::Relaxed)
+    }
+
+    /// Intern `s`, debiting the budget if it isn't interned yet
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern(&self, s: &str) -> Result<Str> {
+        // debits happen under the pool lock, so nothing else changes
+        // the remainder between the check and the debit
+        intern_charged(
+            s,
+            |_, cost| {
+                if self.remaining() < cost {
+                    return Err(Error::BudgetExhausted);
+                }
+                Ok(())
+            },
+            |_, cost| {
+                self.remaining.fetch_sub(cost, Ordering::Relaxed);
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        let budget = Budget::new(3 * entry_len("budget 0".len()));
+        let strs: Vec<Str> = (0..3)
+            .map(|i| budget.intern(&format!("budget {i}")).unwrap())
+            .collect();
+        assert_eq!(budget.remaining(), 0);
+        assert!(matches!(
+            budget.intern("budget 3"),
+            Err(Error::BudgetExhausted)
+        ));
+        // hits are free
+        assert_eq!(budget.intern("budget 1").unwrap(), strs[1]);
+        assert_eq!(budget.intern("a").unwrap(), Str::try_from("a").unwrap());
+        let long = "b".repeat(MAX_LEN + 1);
+        assert!(matches!(budget.intern(&long), Err(Error::TooLong { .. })));
+        let big = Budget::new(usize::MAX);
+        big.intern("budget 3").unwrap();
+        assert_eq!(big.remaining(), usize::MAX - entry_len(8));
+        assert_eq!(
+            budget.intern("budget 3").unwrap(),
+            Str::try_from("budget 3").unwrap()
+        );
+    }
+}
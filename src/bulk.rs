@@ -0,0 +1,305 @@
+//! Interning many strings at once
+
+use crate::{Result, Str, ROOT};
+use fxhash::FxHashSet;
+use std::{
+    collections::{HashSet, VecDeque},
+    io::BufRead,
+    iter,
+    num::NonZeroUsize,
+};
+
+fn trim_newline(s: &str) -> &str {
+    let s = s.strip_suffix('\n').unwrap_or(s);
+    s.strip_suffix('\r').unwrap_or(s)
+}
+
+/// Intern every line read from `reader`, returning the number of
+/// lines interned. Line endings are stripped and empty lines are
+/// skipped. Lines are read and interned in batches, so the lock is
+/// only taken once per batch, and the index is grown to fit each
+/// batch before it is interned.
+///
+/// If reading fails or a line can't be interned, the error is
+/// returned and the lines before it remain in the pool.
+pub fn load_dictionary(mut reader: impl BufRead) -> Result<usize> {
+    const BATCH: usize = 4096;
+    let mut buf = String::new();
+    let mut lines: Vec<(usize, usize)> = Vec::with_capacity(BATCH);
+    let mut n = 0;
+    loop {
+        buf.clear();
+        lines.clear();
+        while lines.len() < BATCH {
+            let start = buf.len();
+            if reader.read_line(&mut buf)? == 0 {
+                break;
+            }
+            let line = trim_newline(&buf[start..]);
+            if !line.is_empty() {
+                lines.push((start, start + line.len()));
+            }
+        }
+        if lines.is_empty() {
+            break Ok(n);
+        }
+        let mut root = ROOT.lock();
+        root.all.try_reserve(lines.len())?;
+        for (start, end) in &lines {
+            root.intern(&buf[*start..*end])?;
+            n += 1;
+        }
+    }
+}
+
+/// Like `load_dictionary`, but for a dictionary in static memory,
+/// for example one embedded with `include_str!`. Lines long enough
+/// are referenced in place as with `Str::from_static` instead of
+/// being copied.
+pub fn load_static_dictionary(dict: &'static str) -> Result<usize> {
+    let mut root = ROOT.lock();
+    root.all.try_reserve(dict.lines().count())?;
+    let mut n = 0;
+    for line in dict.lines().filter(|l| !l.is_empty()) {
+        root.intern_static(line)?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Intern every string in `strs` under a single lock, returning the
+/// number of strings interned, for the tables `export_as_rust`
+/// generates. Strings long enough are referenced in place as with
+/// `Str::from_static` instead of being copied.
+pub fn prefill(strs: &'static [&'static str]) -> Result<usize> {
+    let mut root = ROOT.lock();
+    root.all.try_reserve(strs.len())?;
+    for s in strs {
+        root.intern_static(s)?;
+    }
+    Ok(strs.len())
+}
+
+/// Iterate over the lines of `reader`, interning each one. Line
+/// endings are stripped. A single buffer is reused for every line,
+/// so no `String` is allocated per line.
+pub fn intern_lines(mut reader: impl BufRead) -> impl Iterator<Item = Result<Str>> {
+    let mut buf = String::new();
+    iter::from_fn(move || {
+        buf.clear();
+        match reader.read_line(&mut buf) {
+            Err(e) => Some(Err(e.into())),
+            Ok(0) => None,
+            Ok(_) => Some(Str::try_from(trim_newline(&buf))),
+        }
+    })
+}
+
+/// Iterate over the tokens of `reader`, interning each one. Tokens
+/// are the non empty runs of chars between chars for which `delim`
+/// returns true; line endings always end a token. Input is read a
+/// line at a time into a single reused buffer, so no `String` is
+/// allocated per token. A token longer than `Str::MAX_LEN` yields
+/// `TooLong`, and iteration continues with the next one.
+pub fn intern_tokens(
+    mut reader: impl BufRead,
+    delim: impl Fn(char) -> bool,
+) -> impl Iterator<Item = Result<Str>> {
+    let mut buf = String::new();
+    let mut pos = 0;
+    iter::from_fn(move || loop {
+        let rest = &buf[pos..];
+        match rest.find(|c| !delim(c) && c != '\n' && c != '\r') {
+            None => {
+                buf.clear();
+                pos = 0;
+                match reader.read_line(&mut buf) {
+                    Err(e) => return Some(Err(e.into())),
+                    Ok(0) => return None,
+                    Ok(_) => (),
+                }
+            }
+            Some(start) => {
+                let tok = &rest[start..];
+                let len = tok
+                    .find(|c| delim(c) || c == '\n' || c == '\r')
+                    .unwrap_or(tok.len());
+                pos += start + len;
+                return Some(Str::try_from(&tok[..len]));
+            }
+        }
+    })
+}
+
+/// Adds `interned` to every iterator of strings
+pub trait InternExt<'a>: Iterator<Item = &'a str> + Sized {
+    /// Intern each string, yielding the results in order. Strings are
+    /// read ahead from the iterator and interned `Interned::BATCH` at a
+    /// time, so the lock is taken once per batch instead of once per
+    /// string. The lock isn't held while results are being yielded, so
+    /// the caller can intern other strings as it goes.
+    fn interned(self) -> Interned<'a, Self> {
+        Interned {
+            iter: self,
+            batch: Vec::new(),
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a str>> InternExt<'a> for I {}
+
+/// The iterator returned by `InternExt::interned`
+pub struct Interned<'a, I> {
+    iter: I,
+    batch: Vec<&'a str>,
+    ready: VecDeque<Result<Str>>,
+}
+
+impl<I> Interned<'_, I> {
+    /// The number of strings interned under each lock acquisition
+    pub const BATCH: usize = 256;
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Iterator for Interned<'a, I> {
+    type Item = Result<Str>;
+
+    fn next(&mut self) -> Option<Result<Str>> {
+        if self.ready.is_empty() {
+            self.batch.extend(self.iter.by_ref().take(Self::BATCH));
+            if !self.batch.is_empty() {
+                let mut root = ROOT.lock();
+                self.ready
+                    .extend(self.batch.drain(..).map(|s| root.intern(s)));
+            }
+        }
+        self.ready.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        let n = self.ready.len();
+        (lo.saturating_add(n), hi.and_then(|hi| hi.checked_add(n)))
+    }
+}
+
+/// Statistics about a collection interned by `dedup_strings`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// The number of distinct strings in the collection
+    pub unique: usize,
+    /// The number of strings that were repeats of an earlier string
+    pub duplicates: usize,
+    /// The total length of the repeated strings, which no longer
+    /// need their own copy
+    pub bytes_saved: usize,
+}
+
+/// Intern every string in `strings` in one pass under a single lock,
+/// returning the handles in the same order along with statistics
+/// about how much the collection was deduplicated.
+pub fn dedup_strings(strings: Vec<String>) -> Result<(Vec<Str>, DedupStats)> {
+    let mut seen: FxHashSet<NonZeroUsize> = HashSet::default();
+    let mut stats = DedupStats::default();
+    let mut res = Vec::with_capacity(strings.len());
+    let mut root = ROOT.lock();
+    for s in &strings {
+        let t = root.intern(s)?;
+        if seen.insert(t.as_raw()) {
+            stats.unique += 1;
+        } else {
+            stats.duplicates += 1;
+            stats.bytes_saved += s.len();
+        }
+        res.push(t);
+    }
+    Ok((res, stats))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dictionary() {
+        let words: Vec<String> = (0..10000).map(|i| format!("word{i}")).collect();
+        let dict = words.join("\r\n") + "\n\n";
+        assert_eq!(load_dictionary(dict.as_bytes()).unwrap(), words.len());
+        for w in &words {
+            let t = Str::try_from(w.as_str()).unwrap();
+            assert_eq!(&*t, w);
+        }
+        let n = load_static_dictionary("foo\nbar\n\nbaz\n").unwrap();
+        assert_eq!(n, 3);
+        static STRS: &[&str] = &["prefill a", "", "prefilled from a static table"];
+        assert_eq!(prefill(STRS).unwrap(), 3);
+        assert_eq!(&*Str::try_from("prefill a").unwrap(), "prefill a");
+    }
+
+    #[test]
+    fn test_dedup() {
+        let strings = ["dedup a", "dedup bb", "dedup a", "dedup a"];
+        let (res, stats) = dedup_strings(strings.iter().map(|s| s.to_string()).collect()).unwrap();
+        assert_eq!(res.len(), 4);
+        assert_eq!(res[0].0, res[2].0);
+        assert_eq!(
+            stats,
+            DedupStats {
+                unique: 2,
+                duplicates: 2,
+                bytes_saved: 14
+            }
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let text = "lines one\r\nlines two\n\nlines one";
+        let res: Vec<Str> = intern_lines(text.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(res.len(), 4);
+        assert_eq!(&*res[1], "lines two");
+        assert_eq!(&*res[2], "");
+        assert_eq!(res[0].0, res[3].0);
+    }
+
+    #[test]
+    fn test_tokens() {
+        let text = "tokens  one,two\r\n\n, tokens\nthree";
+        let res: Vec<Str> = intern_tokens(text.as_bytes(), |c| c == ' ' || c == ',')
+            .collect::<Result<_>>()
+            .unwrap();
+        let res: Vec<&str> = res.iter().map(|t| t.as_static()).collect();
+        assert_eq!(res, ["tokens", "one", "two", "tokens", "three"]);
+        let long = "x".repeat(Str::MAX_LEN + 1) + " tokens";
+        let mut it = intern_tokens(long.as_bytes(), char::is_whitespace);
+        assert!(matches!(it.next(), Some(Err(crate::Error::TooLong { .. }))));
+        assert_eq!(&*it.next().unwrap().unwrap(), "tokens");
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_interned() {
+        let strs: Vec<String> = (0..1000).map(|i| format!("interned {i}")).collect();
+        let long = "x".repeat(Str::MAX_LEN + 1);
+        let mut it = strs
+            .iter()
+            .map(String::as_str)
+            .chain([long.as_str(), "interned after"])
+            .interned();
+        assert_eq!(it.size_hint(), (1002, Some(1002)));
+        assert_eq!(&*it.next().unwrap().unwrap(), "interned 0");
+        assert_eq!(it.size_hint(), (1001, Some(1001)));
+        // the lock isn't held between items
+        Str::try_from("interned meanwhile").unwrap();
+        let rest: Vec<_> = it.collect();
+        assert_eq!(rest.len(), 1001);
+        assert!(rest[..999]
+            .iter()
+            .zip(&strs[1..])
+            .all(|(t, s)| &**t.as_ref().unwrap() == s));
+        assert!(matches!(rest[999], Err(crate::Error::TooLong { .. })));
+        assert_eq!(&**rest[1000].as_ref().unwrap(), "interned after");
+    }
+}
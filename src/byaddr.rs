@@ -0,0 +1,69 @@
+//! Interned strings ordered by handle
+
+use crate::Str;
+use std::{cmp::Ordering, fmt, ops::Deref};
+
+/// A `Str` that is ordered by its handle instead of its bytes. The
+/// order is arbitrary, but total and stable for the life of the
+/// process, so it makes a cheap key for a `BTreeMap` or `BTreeSet`
+/// when the order of the keys doesn't matter. Equality is the same as
+/// for `Str`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StrByAddr(pub Str);
+
+impl PartialOrd for StrByAddr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StrByAddr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.as_raw().cmp(&other.0.as_raw())
+    }
+}
+
+impl From<Str> for StrByAddr {
+    fn from(s: Str) -> Self {
+        StrByAddr(s)
+    }
+}
+
+impl From<StrByAddr> for Str {
+    fn from(s: StrByAddr) -> Self {
+        s.0
+    }
+}
+
+impl Deref for StrByAddr {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.0.as_static()
+    }
+}
+
+impl fmt::Debug for StrByAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_by_addr() {
+        let strs: Vec<Str> = ["by addr b", "by addr a", "by addr c", "by addr a"]
+            .into_iter()
+            .map(|s| Str::try_from(s).unwrap())
+            .collect();
+        let set: BTreeSet<StrByAddr> = strs.iter().copied().map(StrByAddr).collect();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&StrByAddr(strs[1])));
+        let raw: Vec<_> = set.iter().map(|s| s.0.as_raw()).collect();
+        assert!(raw.windows(2).all(|w| w[0] < w[1]));
+    }
+}
@@ -0,0 +1,143 @@
+//! Attributing new strings to the code that interned them.
+//!
+//! With the `track-callers` feature every string inserted into the pool
+//! is charged to the source location that interned it, or to the tag of
+//! the innermost enclosing `tagged` call, so a code path that fills the
+//! pool with unique strings can be found from a report instead of a
+//! heap profile. Locations are passed through `#[track_caller]`, which
+//! costs a hidden argument on every interning call, so this is meant
+//! for debug builds. Strings interned through helpers that aren't
+//! `#[track_caller]`, such as the decoders, are charged to the helper,
+//! wrap the caller in `tagged` to group them by code path instead.
+//! With the `audit` feature, tags are also passed to the audit sink.
+
+#[cfg(feature = "track-callers")]
+use crate::sync::{Lazy, Mutex};
+use std::cell::Cell;
+#[cfg(feature = "track-callers")]
+use std::{cmp::Reverse, collections::HashMap, fmt, panic::Location};
+
+/// What a string was charged to
+#[cfg(feature = "track-callers")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Site {
+    /// The location of the interning call
+    Location(&'static Location<'static>),
+    /// The tag of the enclosing `tagged` call
+    Tag(&'static str),
+}
+
+#[cfg(feature = "track-callers")]
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Site::Location(l) => write!(f, "{l}"),
+            Site::Tag(t) => f.write_str(t),
+        }
+    }
+}
+
+/// The strings a site has added to the pool
+#[cfg(feature = "track-callers")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiteUsage {
+    /// Where the strings were interned
+    pub site: Site,
+    /// The number of strings inserted
+    pub strings: usize,
+    /// The number of bytes of string data inserted
+    pub bytes: usize,
+}
+
+#[cfg(feature = "track-callers")]
+pub(crate) static SITES: Lazy<Mutex<HashMap<Site, (usize, usize)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+thread_local! {
+    static TAG: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// The tag of the innermost enclosing `tagged` call on this thread
+#[cfg(feature = "audit")]
+pub(crate) fn current_tag() -> Option<&'static str> {
+    TAG.with(Cell::get)
+}
+
+/// Charge a newly inserted string of `len` bytes to the current tag,
+/// or to the caller
+#[cfg(feature = "track-callers")]
+#[track_caller]
+pub(crate) fn record(len: usize) {
+    let site = match TAG.with(Cell::get) {
+        Some(tag) => Site::Tag(tag),
+        None => Site::Location(Location::caller()),
+    };
+    let mut sites = SITES.lock();
+    let (strings, bytes) = sites.entry(site).or_default();
+    *strings += 1;
+    *bytes += len;
+}
+
+/// Run `f`, charging every string it interns on this thread to `tag`
+/// instead of to the location that interned it, and with `audit`,
+/// recording them with `tag`. Calls nest, the innermost tag wins.
+pub fn tagged<R>(tag: &'static str, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<&'static str>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            TAG.with(|t| t.set(self.0));
+        }
+    }
+
+    let _restore = Restore(TAG.with(|t| t.replace(Some(tag))));
+    f()
+}
+
+/// Report how many strings and bytes each site has added to the pool,
+/// largest first. Hits on strings that were already interned aren't
+/// counted, nor are the built in common strings.
+#[cfg(feature = "track-callers")]
+pub fn callsite_report() -> Vec<SiteUsage> {
+    let mut report: Vec<SiteUsage> = SITES
+        .lock()
+        .iter()
+        .map(|(site, (strings, bytes))| SiteUsage {
+            site: *site,
+            strings: *strings,
+            bytes: *bytes,
+        })
+        .collect();
+    report.sort_by_key(|u| Reverse((u.bytes, u.strings)));
+    report
+}
+
+#[cfg(all(test, feature = "track-callers"))]
+mod test {
+    use super::*;
+    use crate::Str;
+
+    #[test]
+    fn test_callsite() {
+        let line = line!() + 1;
+        Str::try_from("callsite a").unwrap();
+        Str::try_from("callsite a").unwrap();
+        tagged("callsite test", || {
+            Str::try_from("callsite b").unwrap();
+            Str::try_from("callsite cc").unwrap();
+        });
+        let report = callsite_report();
+        let here = report
+            .iter()
+            .find(
+                |u| matches!(u.site, Site::Location(l) if l.file() == file!() && l.line() == line),
+            )
+            .unwrap();
+        assert_eq!((here.strings, here.bytes), (1, 10));
+        let tag = report
+            .iter()
+            .find(|u| u.site == Site::Tag("callsite test"))
+            .unwrap();
+        assert_eq!((tag.strings, tag.bytes), (2, 21));
+    }
+}
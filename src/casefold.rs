@@ -0,0 +1,131 @@
+//! Case insensitive interned keys
+
+use crate::{stackbuf::StackBuf, Error, Result, Str};
+use caseless::Caseless;
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// A case insensitive interned key that remembers how it was spelled.
+///
+/// The string is case folded with the full default case folding of
+/// Unicode's CaseFolding.txt before it is interned, so `ß` folds to
+/// `ss` and final `ς` to `σ`, and equality, ordering, and hashing only
+/// consider the folded form, so `CaseFoldStr`s that differ only in
+/// case are the same key. The string isn't normalized, so precomposed
+/// and decomposed accents still differ. The original spelling is
+/// interned as well and is what `Display` shows.
+#[derive(Clone, Copy)]
+pub struct CaseFoldStr {
+    folded: Str,
+    original: Str,
+}
+
+impl CaseFoldStr {
+    /// The case folded form, the canonical key
+    pub fn folded(self) -> Str {
+        self.folded
+    }
+
+    /// The spelling this key was created from
+    pub fn original(self) -> Str {
+        self.original
+    }
+}
+
+fn fold(s: &str) -> Result<Str> {
+    if s.chars().default_case_fold().eq(s.chars()) {
+        return Str::try_from(s);
+    }
+    let mut buf = StackBuf::new();
+    for c in s.chars().default_case_fold() {
+        buf.push(c);
+    }
+    buf.intern()
+}
+
+impl TryFrom<&str> for CaseFoldStr {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        let original = Str::try_from(s)?;
+        Ok(CaseFoldStr {
+            folded: fold(s)?,
+            original,
+        })
+    }
+}
+
+impl TryFrom<Str> for CaseFoldStr {
+    type Error = Error;
+
+    fn try_from(original: Str) -> Result<Self> {
+        Ok(CaseFoldStr {
+            folded: fold(&original)?,
+            original,
+        })
+    }
+}
+
+impl PartialEq for CaseFoldStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.folded == other.folded
+    }
+}
+
+impl Eq for CaseFoldStr {}
+
+impl PartialOrd for CaseFoldStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CaseFoldStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.folded.cmp(&other.folded)
+    }
+}
+
+impl Hash for CaseFoldStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.folded.hash(state)
+    }
+}
+
+impl fmt::Display for CaseFoldStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl fmt::Debug for CaseFoldStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.original, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_casefold() {
+        let a = CaseFoldStr::try_from("Straße ÉTÉ").unwrap();
+        let b = CaseFoldStr::try_from("strasse été").unwrap();
+        let c = CaseFoldStr::try_from("STRAßE été").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a, c);
+        assert_eq!(&*a.folded(), "strasse été");
+        assert_eq!(b.folded(), b.original());
+        let fold = |s| CaseFoldStr::try_from(s).unwrap();
+        assert_eq!(fold("ΣΟΦΟΣ"), fold("σοφος"));
+        assert_eq!(fold("σοφος"), fold("σοφοσ"));
+        assert_eq!(fold("ﬁle"), fold("FILE"));
+        assert_ne!(fold("ve\u{301}lo"), fold("vélo"));
+        assert_eq!(a.to_string(), "Straße ÉTÉ");
+        assert_eq!(c.to_string(), "STRAßE été");
+    }
+}
@@ -0,0 +1,240 @@
+//! A table of strings that are interned so often that they are built
+//! into static memory at compile time and found without hashing or
+//! locking: the empty string, every single ASCII character, "true",
+//! "false", and the decimal integers up to 999.
+//!
+//! The table uses the same entry layout as a chunk, and the pool
+//! never stores a second copy of these strings, so they hashcons the
+//! same as everything else.
+
+use crate::Str;
+#[cfg(not(feature = "forbid-unsafe"))]
+use std::ptr::NonNull;
+
+const EMPTY: usize = 0;
+const CHARS: usize = EMPTY + 1;
+const TRUE: usize = CHARS + 128 * 2;
+const FALSE: usize = TRUE + 5;
+const TWO_DIGITS: usize = FALSE + 6;
+const THREE_DIGITS: usize = TWO_DIGITS + 90 * 3;
+const LEN: usize = THREE_DIGITS + 900 * 4;
+
+const fn build() -> [u8; LEN] {
+    const fn entry(t: &mut [u8; LEN], pos: usize, s: &[u8]) {
+        t[pos] = s.len() as u8;
+        let mut i = 0;
+        while i < s.len() {
+            t[pos + 1 + i] = s[i];
+            i += 1;
+        }
+    }
+    let mut t = [0u8; LEN];
+    entry(&mut t, EMPTY, b"");
+    let mut c = 0;
+    while c < 128 {
+        entry(&mut t, CHARS + c * 2, &[c as u8]);
+        c += 1;
+    }
+    entry(&mut t, TRUE, b"true");
+    entry(&mut t, FALSE, b"false");
+    let mut n = 10;
+    while n < 100 {
+        let s = [b'0' + (n / 10) as u8, b'0' + (n % 10) as u8];
+        entry(&mut t, TWO_DIGITS + (n - 10) * 3, &s);
+        n += 1;
+    }
+    while n < 1000 {
+        let s = [
+            b'0' + (n / 100) as u8,
+            b'0' + (n / 10 % 10) as u8,
+            b'0' + (n % 10) as u8,
+        ];
+        entry(&mut t, THREE_DIGITS + (n - 100) * 4, &s);
+        n += 1;
+    }
+    t
+}
+
+static TABLE: [u8; LEN] = build();
+
+fn digit(b: u8) -> Option<usize> {
+    b.is_ascii_digit().then(|| (b - b'0') as usize)
+}
+
+fn offset(s: &[u8]) -> Option<usize> {
+    match s {
+        [] => Some(EMPTY),
+        [c] if c.is_ascii() => Some(CHARS + *c as usize * 2),
+        b"true" => Some(TRUE),
+        b"false" => Some(FALSE),
+        [b'1'..=b'9', d1] => Some(TWO_DIGITS + (digit(s[0])? * 10 + digit(*d1)? - 10) * 3),
+        [b'1'..=b'9', d1, d2] => {
+            let n = digit(s[0])? * 100 + digit(*d1)? * 10 + digit(*d2)?;
+            Some(THREE_DIGITS + (n - 100) * 4)
+        }
+        _ => None,
+    }
+}
+
+/// Look up `s` in the table of common strings
+#[cfg(not(feature = "forbid-unsafe"))]
+pub(crate) fn get(s: &str) -> Option<Str> {
+    let pos = offset(s.as_bytes())?;
+    // SAFETY: pos is the start of an entry in TABLE, which is
+    // immutable and lives forever
+    Some(unsafe {
+        Str::from_entry(
+            NonNull::new_unchecked(TABLE.as_ptr().add(pos) as *mut u8),
+            s.len(),
+            false,
+        )
+    })
+}
+
+/// The common string whose entry is at `addr`, if that is the start
+/// of an entry in the table
+#[cfg(not(feature = "forbid-unsafe"))]
+pub(crate) fn at(addr: usize) -> Option<Str> {
+    let t = crate::plain_entry(&TABLE, addr.wrapping_sub(TABLE.as_ptr() as usize))?;
+    get(&t).filter(|u| u.as_raw() == t.as_raw())
+}
+
+/// Every common string, in id order
+pub(crate) fn strs() -> impl Iterator<Item = &'static str> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        (pos < LEN).then(|| {
+            let len = TABLE[pos] as usize;
+            let s = std::str::from_utf8(&TABLE[pos + 1..pos + 1 + len]).unwrap();
+            pos += 1 + len;
+            s
+        })
+    })
+}
+
+/// The number of common strings, which take the first ids
+pub(crate) const COUNT: u32 = 1 + 128 + 2 + 90 + 900;
+
+/// The id of the common string whose entry is at `pos`, its position
+/// in the table
+#[cfg(all(
+    any(feature = "ids", feature = "name-hash"),
+    not(feature = "forbid-unsafe")
+))]
+fn id_at(pos: usize) -> u32 {
+    (match pos {
+        EMPTY => 0,
+        _ if pos < TRUE => 1 + (pos - CHARS) / 2,
+        TRUE => 129,
+        FALSE => 130,
+        _ if pos < THREE_DIGITS => 131 + (pos - TWO_DIGITS) / 3,
+        _ => 221 + (pos - THREE_DIGITS) / 4,
+    }) as u32
+}
+
+/// The common string with id `id`
+#[cfg(all(
+    any(feature = "ids", feature = "name-hash"),
+    not(feature = "forbid-unsafe")
+))]
+pub(crate) fn by_id(id: u32) -> Option<Str> {
+    let id = id as usize;
+    let (pos, len) = match id {
+        0 => (EMPTY, 0),
+        1..=128 => (CHARS + (id - 1) * 2, 1),
+        129 => (TRUE, 4),
+        130 => (FALSE, 5),
+        131..=220 => (TWO_DIGITS + (id - 131) * 3, 2),
+        221..=1120 => (THREE_DIGITS + (id - 221) * 4, 3),
+        _ => return None,
+    };
+    // SAFETY: pos is the start of an entry in TABLE for a string of
+    // len bytes
+    Some(unsafe {
+        Str::from_entry(
+            NonNull::new_unchecked(TABLE.as_ptr().add(pos) as *mut u8),
+            len,
+            false,
+        )
+    })
+}
+
+/// Every common string
+#[cfg(all(feature = "name-hash", not(feature = "forbid-unsafe")))]
+pub(crate) fn all() -> impl Iterator<Item = Str> {
+    (0..COUNT).map(|id| by_id(id).unwrap())
+}
+
+/// The id of `t` if it is one of the common strings
+#[cfg(all(
+    any(feature = "ids", feature = "name-hash"),
+    not(feature = "forbid-unsafe")
+))]
+pub(crate) fn id(t: Str) -> Option<u32> {
+    let pos = (t.entry() as usize).wrapping_sub(TABLE.as_ptr() as usize);
+    (pos < LEN).then(|| id_at(pos))
+}
+
+/// With `forbid-unsafe` handles can't point into the table, so each
+/// entry is read into a leaked `&str` the first time the table is
+/// used, along with its offset
+#[cfg(feature = "forbid-unsafe")]
+static STRS: crate::sync::Lazy<Vec<(usize, &'static str)>> = crate::sync::Lazy::new(|| {
+    let mut strs = Vec::new();
+    let mut pos = 0;
+    while pos < LEN {
+        let len = TABLE[pos] as usize;
+        let s = std::str::from_utf8(&TABLE[pos + 1..pos + 1 + len]).unwrap();
+        strs.push((pos, s));
+        pos += 1 + len;
+    }
+    strs
+});
+
+#[cfg(feature = "forbid-unsafe")]
+pub(crate) fn get(s: &str) -> Option<Str> {
+    let pos = offset(s.as_bytes())?;
+    let i = STRS.binary_search_by_key(&pos, |(p, _)| *p).ok()?;
+    Some(Str(&STRS[i].1))
+}
+
+/// The id of `t` if it is one of the common strings, which is its
+/// position in the table
+#[cfg(all(feature = "ids", feature = "forbid-unsafe"))]
+pub(crate) fn id(t: Str) -> Option<u32> {
+    let addr = |i: usize| &STRS[i].1 as *const &str as usize;
+    let i = t.addr().wrapping_sub(addr(0)) / std::mem::size_of::<(usize, &str)>();
+    (i < STRS.len() && t.addr() == addr(i)).then_some(i as u32)
+}
+
+/// The common string with id `id`
+#[cfg(all(feature = "ids", feature = "forbid-unsafe"))]
+pub(crate) fn by_id(id: u32) -> Option<Str> {
+    STRS.get(id as usize).map(|(_, s)| Str(s))
+}
+
+/// Every common string
+#[cfg(feature = "forbid-unsafe")]
+pub(crate) fn all() -> impl Iterator<Item = Str> {
+    STRS.iter().map(|(_, s)| Str(s))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_common() {
+        let mut all = vec![String::new(), "true".into(), "false".into()];
+        all.extend((0..128u8).map(|c| (c as char).to_string()));
+        all.extend((0..1000).map(|n: u32| n.to_string()));
+        for s in &all {
+            let t = get(s).unwrap();
+            assert_eq!(&*t, s);
+            assert_eq!(Str::try_from(s.as_str()).unwrap().as_raw(), t.as_raw());
+        }
+        for s in ["01", "1000", "tru", "é", "0x"] {
+            assert!(get(s).is_none());
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Configuring the pool at run time, from a `Config` or the
+//! environment, so a deployed binary can be tuned without rebuilding
+//! it.
+//!
+//! Most settings can be changed at any time, but the hash seed and the
+//! shard count of the `dashmap` index are fixed when the pool is first
+//! used, so a `Config` that sets them has to be applied before
+//! anything is interned, typically at the start of `main`.
+
+use crate::{
+    set_entry_alignment, set_limit_policy, set_max_chunk_size, set_memory_limit, set_recycle_slack,
+    Error, LimitPolicy, Result, MAX_ALIGN,
+};
+use std::{env, sync::OnceLock};
+
+/// How the index hasher is seeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashSeed {
+    /// Always the same seed, so runs are reproducible
+    Fixed,
+    /// A seed drawn from per process randomness, as the `random-seed`
+    /// feature does, so which strings collide can't be predicted
+    Random,
+}
+
+/// Settings for the pool. Every field left as None keeps its current
+/// value, which is the default unless it has been set before. Build one
+/// from `Config::default()` or `Config::from_env`, and apply it with
+/// `Config::apply`.
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct Config {
+    /// As `set_memory_limit`, from `HCSTATIC_STR_MEMORY_LIMIT`
+    pub memory_limit: Option<usize>,
+    /// As `set_max_chunk_size`, from `HCSTATIC_STR_CHUNK_SIZE`
+    pub max_chunk_size: Option<usize>,
+    /// As `set_entry_alignment`, from `HCSTATIC_STR_ALIGN`
+    pub entry_alignment: Option<usize>,
+    /// As `set_limit_policy`, from `HCSTATIC_STR_LIMIT_POLICY`, which
+    /// may be `error` or `panic`
+    pub limit_policy: Option<LimitPolicy>,
+    /// As `set_recycle_slack`, from `HCSTATIC_STR_RECYCLE_SLACK`, which
+    /// may be `on` or `off`
+    pub recycle_slack: Option<bool>,
+    /// How to seed the index hasher, from `HCSTATIC_STR_HASH_SEED`,
+    /// which may be `fixed` or `random`. The `deterministic` feature
+    /// overrides it. Only applies before the pool is first used.
+    pub hash_seed: Option<HashSeed>,
+    /// The number of shards of the `dashmap` index, a power of two,
+    /// from `HCSTATIC_STR_SHARDS`. It is ignored by the other indexes.
+    /// Only applies before the pool is first used.
+    pub shards: Option<usize>,
+}
+
+/// The settings fixed when the pool is first used
+#[derive(Clone, Copy)]
+pub(crate) struct Boot {
+    pub(crate) random_seed: bool,
+    #[cfg_attr(
+        not(all(feature = "dashmap", not(feature = "arc-swap"))),
+        allow(dead_code)
+    )]
+    pub(crate) shards: Option<usize>,
+}
+
+static BOOT: OnceLock<Boot> = OnceLock::new();
+
+/// The settings fixed when the pool was first used, fixing them now if
+/// it hasn't been
+pub(crate) fn boot() -> Boot {
+    *BOOT.get_or_init(|| Boot {
+        random_seed: cfg!(feature = "random-seed"),
+        shards: None,
+    })
+}
+
+/// Parse a byte count, which may have a `K`, `M` or `G` suffix for
+/// KiB, MiB or GiB
+fn parse_size(s: &str) -> Option<usize> {
+    let s = s.trim();
+    let (digits, shift) = match s.as_bytes().last()? {
+        b'k' | b'K' => (&s[..s.len() - 1], 10),
+        b'm' | b'M' => (&s[..s.len() - 1], 20),
+        b'g' | b'G' => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    let n: usize = digits.parse().ok()?;
+    n.checked_mul(1 << shift)
+}
+
+/// Read the variable `name`, parsing it with `parse`, and failing with
+/// `why` if it is set but can't be parsed
+fn var<T>(name: &str, why: &'static str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>> {
+    match env::var(name) {
+        Ok(v) => parse(&v).map(Some).ok_or(Error::Config(why)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => Err(Error::Config(why)),
+    }
+}
+
+impl Config {
+    /// Read the settings from the environment variables named on each
+    /// field. Variables that aren't set leave their field as None.
+    /// Sizes are in bytes, and may have a `K`, `M` or `G` suffix.
+    pub fn from_env() -> Result<Config> {
+        Ok(Config {
+            memory_limit: var(
+                "HCSTATIC_STR_MEMORY_LIMIT",
+                "HCSTATIC_STR_MEMORY_LIMIT is not a size",
+                parse_size,
+            )?,
+            max_chunk_size: var(
+                "HCSTATIC_STR_CHUNK_SIZE",
+                "HCSTATIC_STR_CHUNK_SIZE is not a size",
+                parse_size,
+            )?,
+            entry_alignment: var(
+                "HCSTATIC_STR_ALIGN",
+                "HCSTATIC_STR_ALIGN is not a number",
+                |v| v.trim().parse().ok(),
+            )?,
+            limit_policy: var(
+                "HCSTATIC_STR_LIMIT_POLICY",
+                "HCSTATIC_STR_LIMIT_POLICY is not error or panic",
+                |v| match v.trim() {
+                    "error" => Some(LimitPolicy::Error),
+                    "panic" => Some(LimitPolicy::Panic),
+                    _ => None,
+                },
+            )?,
+            recycle_slack: var(
+                "HCSTATIC_STR_RECYCLE_SLACK",
+                "HCSTATIC_STR_RECYCLE_SLACK is not on or off",
+                |v| match v.trim() {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => None,
+                },
+            )?,
+            hash_seed: var(
+                "HCSTATIC_STR_HASH_SEED",
+                "HCSTATIC_STR_HASH_SEED is not fixed or random",
+                |v| match v.trim() {
+                    "fixed" => Some(HashSeed::Fixed),
+                    "random" => Some(HashSeed::Random),
+                    _ => None,
+                },
+            )?,
+            shards: var(
+                "HCSTATIC_STR_SHARDS",
+                "HCSTATIC_STR_SHARDS is not a number",
+                |v| v.trim().parse().ok(),
+            )?,
+        })
+    }
+
+    /// Apply the settings that are set. Nothing is changed if any of
+    /// them is out of range, or if the hash seed or shard count is set
+    /// and the pool has already been used.
+    pub fn apply(&self) -> Result<()> {
+        if self
+            .entry_alignment
+            .is_some_and(|a| !a.is_power_of_two() || a > MAX_ALIGN)
+        {
+            return Err(Error::Config(
+                "the entry alignment must be a power of two of at most 64",
+            ));
+        }
+        if self.shards.is_some_and(|n| n < 2 || !n.is_power_of_two()) {
+            return Err(Error::Config(
+                "the shard count must be a power of two greater than 1",
+            ));
+        }
+        if self.hash_seed.is_some() || self.shards.is_some() {
+            let boot = Boot {
+                random_seed: match self.hash_seed {
+                    Some(seed) => seed == HashSeed::Random,
+                    None => cfg!(feature = "random-seed"),
+                },
+                shards: self.shards,
+            };
+            BOOT.set(boot).map_err(|_| {
+                Error::Config("the hash seed and shards must be set before the pool is used")
+            })?;
+        }
+        if let Some(bytes) = self.memory_limit {
+            set_memory_limit(bytes);
+        }
+        if let Some(bytes) = self.max_chunk_size {
+            set_max_chunk_size(bytes);
+        }
+        if let Some(align) = self.entry_alignment {
+            set_entry_alignment(align);
+        }
+        if let Some(policy) = self.limit_policy {
+            set_limit_policy(policy);
+        }
+        if let Some(on) = self.recycle_slack {
+            set_recycle_slack(on);
+        }
+        Ok(())
+    }
+}
+
+/// Read the settings from the environment with `Config::from_env` and
+/// apply them, for the start of `main`
+pub fn configure_from_env() -> Result<()> {
+    Config::from_env()?.apply()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("64K"), Some(64 << 10));
+        assert_eq!(parse_size(" 2m "), Some(2 << 20));
+        assert_eq!(parse_size("1G"), Some(1 << 30));
+        assert_eq!(parse_size("M"), None);
+        assert_eq!(parse_size("-1"), None);
+        // nothing in this test binary sets the variables
+        let c = Config::from_env().unwrap();
+        assert!(c.memory_limit.is_none() && c.hash_seed.is_none());
+        assert!(Config::default().apply().is_ok());
+        let c = Config {
+            entry_alignment: Some(3),
+            ..Config::default()
+        };
+        assert!(matches!(c.apply(), Err(Error::Config(_))));
+        let c = Config {
+            shards: Some(6),
+            ..Config::default()
+        };
+        assert!(matches!(c.apply(), Err(Error::Config(_))));
+        crate::Str::try_from("config").unwrap();
+        let c = Config {
+            hash_seed: Some(HashSeed::Fixed),
+            ..Config::default()
+        };
+        assert!(matches!(c.apply(), Err(Error::Config(_))));
+    }
+}
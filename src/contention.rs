@@ -0,0 +1,173 @@
+//! Measuring how long callers wait for the pool lock, behind the
+//! `lock-contention` feature, to tell whether a workload would gain
+//! from the `dashmap` or `arc-swap` index.
+//!
+//! Every lock of the pool first tries to take it without waiting,
+//! which costs an atomic add to count it. Only when another thread
+//! holds it is the wait timed, so the clock is only read by calls that
+//! are waiting anyway. Lookups the index answers without the lock
+//! aren't counted.
+
+use crate::{
+    stats::{load, Counter},
+    sync::{Mutex, MutexGuard},
+};
+use std::{sync::atomic::Ordering, time::Duration, time::Instant};
+
+/// The number of histogram buckets. Bucket `i` counts waits of under
+/// `2^i` microseconds that don't fit an earlier bucket, and the last
+/// counts everything longer, about 4 seconds and up.
+const BUCKETS: usize = 23;
+
+static ACQUIRED: Counter = Counter::new(0);
+static CONTENDED: Counter = Counter::new(0);
+static WAITED_NS: Counter = Counter::new(0);
+static HISTOGRAM: [Counter; BUCKETS] = [const { Counter::new(0) }; BUCKETS];
+
+/// A mutex that counts and times how long locking it waits
+pub(crate) struct Timed<T>(Mutex<T>);
+
+impl<T> Timed<T> {
+    pub(crate) fn new(v: T) -> Self {
+        Timed(Mutex::new(v))
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        if let Some(g) = self.0.try_lock() {
+            ACQUIRED.fetch_add(1, Ordering::Relaxed);
+            return g;
+        }
+        let start = Instant::now();
+        let g = self.0.lock();
+        waited(start.elapsed());
+        g
+    }
+
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let g = self.0.try_lock()?;
+        ACQUIRED.fetch_add(1, Ordering::Relaxed);
+        Some(g)
+    }
+}
+
+fn waited(d: Duration) {
+    let micros = d.as_micros().min(u64::MAX as u128) as u64;
+    let bucket = ((u64::BITS - micros.leading_zeros()) as usize).min(BUCKETS - 1);
+    ACQUIRED.fetch_add(1, Ordering::Relaxed);
+    CONTENDED.fetch_add(1, Ordering::Relaxed);
+    WAITED_NS.fetch_add(d.as_nanos() as _, Ordering::Relaxed);
+    HISTOGRAM[bucket].fetch_add(1, Ordering::Relaxed);
+}
+
+/// How long locking the pool has waited, from `lock_waits`. Targets
+/// without 64 bit atomics count with word sized counters, which wrap
+/// sooner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockWaits {
+    /// The number of times the pool was locked
+    pub acquisitions: u64,
+    /// How many of those had to wait for another thread to unlock it
+    pub contended: u64,
+    /// The total time spent waiting
+    pub waited: Duration,
+    /// The number of waits in each bucket, with the bucket's exclusive
+    /// upper bound, which doubles from 1 microsecond. The last bound is
+    /// `Duration::MAX`.
+    pub histogram: Vec<(Duration, u64)>,
+}
+
+impl LockWaits {
+    /// The fraction of acquisitions that had to wait, or 0 if there
+    /// were none
+    pub fn contended_ratio(&self) -> f64 {
+        if self.acquisitions == 0 {
+            0.
+        } else {
+            self.contended as f64 / self.acquisitions as f64
+        }
+    }
+
+    /// The upper bound of the bucket holding the wait at quantile `q`,
+    /// between 0 and 1, of the contended acquisitions, or zero if
+    /// there were none. `quantile(0.99)` is the p99 wait, rounded up
+    /// to a power of two microseconds.
+    pub fn quantile(&self, q: f64) -> Duration {
+        let rank = (q.clamp(0., 1.) * self.contended as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bound, n) in &self.histogram {
+            seen += n;
+            if seen >= rank.max(1) {
+                return *bound;
+            }
+        }
+        Duration::ZERO
+    }
+}
+
+/// Report how often locking the pool has waited for another thread,
+/// and for how long, since the process started. Sample it before and
+/// after a load test, or scrape it with the rest of the metrics, to
+/// see whether contention is worth turning on a lock free index for.
+pub fn lock_waits() -> LockWaits {
+    LockWaits {
+        acquisitions: load(&ACQUIRED),
+        contended: load(&CONTENDED),
+        waited: Duration::from_nanos(load(&WAITED_NS)),
+        histogram: HISTOGRAM
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                let bound = match i {
+                    _ if i == BUCKETS - 1 => Duration::MAX,
+                    _ => Duration::from_micros(1 << i),
+                };
+                (bound, load(n))
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lock_waits() {
+        let before = lock_waits();
+        crate::Str::try_from("lock waits").unwrap();
+        let m = Timed::new(());
+        let g = m.lock();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                tx.send(()).unwrap();
+                drop(m.lock())
+            });
+            rx.recv().unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            drop(g);
+        });
+        let after = lock_waits();
+        assert!(after.acquisitions >= before.acquisitions + 3);
+        assert!(after.contended > before.contended);
+        assert!(after.waited >= before.waited + Duration::from_millis(10));
+        assert_eq!(after.histogram.len(), BUCKETS);
+        // the wait of at least 10ms lands in a bucket of over 8ms
+        let long: u64 = after.histogram[14..].iter().map(|(_, n)| n).sum();
+        let long_before: u64 = before.histogram[14..].iter().map(|(_, n)| n).sum();
+        assert!(long > long_before);
+        assert!(after.quantile(1.) > Duration::from_millis(8));
+        assert!(after.quantile(0.) <= after.quantile(1.));
+        assert!(after.contended_ratio() > 0.);
+        let none = LockWaits {
+            acquisitions: 0,
+            contended: 0,
+            waited: Duration::ZERO,
+            histogram: vec![(Duration::MAX, 0)],
+        };
+        assert_eq!(
+            (none.quantile(0.5), none.contended_ratio()),
+            (Duration::ZERO, 0.)
+        );
+    }
+}
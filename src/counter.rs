@@ -0,0 +1,134 @@
+//! Counting occurrences of interned strings
+
+use crate::Str;
+use fxhash::FxHashMap;
+use std::{cmp::Reverse, collections::HashMap, fmt, num::NonZeroUsize};
+
+/// A multiset of interned strings, counting how many times each was
+/// added. Strings are keyed by handle, so counting one hashes a word
+/// instead of the bytes of the string, which is what makes counting
+/// interned tokens cheaper than counting `&str`s.
+#[derive(Clone, Default)]
+pub struct StrCounter {
+    counts: FxHashMap<NonZeroUsize, (Str, u64)>,
+}
+
+impl StrCounter {
+    pub fn new() -> Self {
+        StrCounter {
+            counts: HashMap::default(),
+        }
+    }
+
+    /// Count one more occurrence of `t`
+    pub fn increment(&mut self, t: Str) {
+        self.add(t, 1)
+    }
+
+    /// Count `n` more occurrences of `t`
+    pub fn add(&mut self, t: Str, n: u64) {
+        self.counts.entry(t.as_raw()).or_insert((t, 0)).1 += n;
+    }
+
+    /// The number of times `t` was counted
+    pub fn get(&self, t: Str) -> u64 {
+        self.counts.get(&t.as_raw()).map_or(0, |(_, n)| *n)
+    }
+
+    /// Forget `t`, returning its count
+    pub fn remove(&mut self, t: Str) -> u64 {
+        self.counts.remove(&t.as_raw()).map_or(0, |(_, n)| n)
+    }
+
+    /// The number of distinct strings counted
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The sum of every count
+    pub fn total(&self) -> u64 {
+        self.counts.values().map(|(_, n)| n).sum()
+    }
+
+    /// Every string counted with its count, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (Str, u64)> + '_ {
+        self.counts.values().copied()
+    }
+
+    /// The `n` most often counted strings with their counts, most
+    /// frequent first, with ties broken by the string. Only the top `n`
+    /// are sorted.
+    pub fn top_k(&self, n: usize) -> Vec<(Str, u64)> {
+        let mut all: Vec<(Str, u64)> = self.iter().collect();
+        let by_count = |(s0, n0): &(Str, u64), (s1, n1): &(Str, u64)| {
+            (Reverse(n0), s0).cmp(&(Reverse(n1), s1))
+        };
+        if n < all.len() {
+            all.select_nth_unstable_by(n, by_count);
+            all.truncate(n);
+        }
+        all.sort_unstable_by(by_count);
+        all
+    }
+
+    /// Add the counts of `other` to this counter, as when combining the
+    /// counts from each shard of a job
+    pub fn merge(&mut self, other: &StrCounter) {
+        for (t, n) in other.iter() {
+            self.add(t, n);
+        }
+    }
+}
+
+impl Extend<Str> for StrCounter {
+    fn extend<I: IntoIterator<Item = Str>>(&mut self, iter: I) {
+        for t in iter {
+            self.increment(t);
+        }
+    }
+}
+
+impl FromIterator<Str> for StrCounter {
+    fn from_iter<I: IntoIterator<Item = Str>>(iter: I) -> Self {
+        let mut counter = StrCounter::new();
+        counter.extend(iter);
+        counter
+    }
+}
+
+impl fmt::Debug for StrCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.top_k(usize::MAX)).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_str_counter() {
+        let [a, b, c] = ["counter a", "counter b", "counter c"].map(|s| Str::try_from(s).unwrap());
+        let mut counter: StrCounter = [a, b, a, c, a, b].into_iter().collect();
+        assert_eq!((counter.get(a), counter.get(b), counter.get(c)), (3, 2, 1));
+        assert_eq!((counter.len(), counter.total()), (3, 6));
+        assert_eq!(counter.top_k(2), [(a, 3), (b, 2)]);
+        assert_eq!(counter.top_k(10), [(a, 3), (b, 2), (c, 1)]);
+        assert!(counter.top_k(0).is_empty());
+        let mut other = StrCounter::new();
+        other.add(c, 4);
+        other.increment(b);
+        counter.merge(&other);
+        assert_eq!(counter.top_k(3), [(c, 5), (a, 3), (b, 3)]);
+        assert_eq!(counter.remove(c), 5);
+        assert_eq!((counter.get(c), counter.remove(c)), (0, 0));
+        assert_eq!(
+            format!("{counter:?}"),
+            r#"{"counter a": 3, "counter b": 3}"#
+        );
+    }
+}
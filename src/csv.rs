@@ -0,0 +1,126 @@
+//! CSV support, behind the `csv` feature.
+//!
+//! Category columns in CSV files, a country, a status, a host name,
+//! repeat a handful of values over millions of rows. `InternColumns`
+//! interns the fields of the columns it is given as each record is
+//! read, so they are stored once and compared by handle, while the
+//! other columns are left to the caller. A reader reuses one record,
+//! `read_record` or `read_byte_record`, and one output vector, so
+//! interning values that are already in the pool allocates nothing per
+//! row. Records deserialized with serde into structs with `Str` fields
+//! intern those fields straight out of the record too.
+
+use crate::{Result, Str};
+use ::csv::{ByteRecord, StringRecord};
+use std::str;
+
+/// The columns of a CSV file to intern, by index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternColumns {
+    columns: Vec<usize>,
+}
+
+impl InternColumns {
+    /// Intern the columns at `columns`, in that order
+    pub fn new(columns: impl IntoIterator<Item = usize>) -> Self {
+        InternColumns {
+            columns: columns.into_iter().collect(),
+        }
+    }
+
+    /// Intern the columns named `names` in `headers`, in the order of
+    /// `names`, or None if one of them isn't in `headers`
+    pub fn by_name(headers: &StringRecord, names: &[&str]) -> Option<Self> {
+        let columns = names
+            .iter()
+            .map(|name| headers.iter().position(|h| h == *name))
+            .collect::<Option<Vec<_>>>()?;
+        Some(InternColumns { columns })
+    }
+
+    /// The indexes of the columns
+    pub fn columns(&self) -> &[usize] {
+        &self.columns
+    }
+
+    /// Intern the fields of `record` in the columns, replacing the
+    /// contents of `out` with them, one per column. A column the
+    /// record is too short to have, as with flexible readers, gives
+    /// None.
+    pub fn intern_into(&self, record: &StringRecord, out: &mut Vec<Option<Str>>) -> Result<()> {
+        out.clear();
+        for i in &self.columns {
+            out.push(record.get(*i).map(Str::try_from).transpose()?);
+        }
+        Ok(())
+    }
+
+    /// Like `intern_into`, but for a `ByteRecord`, whose fields are
+    /// only checked to be UTF-8 in the interned columns, so reading the
+    /// rest of the record needn't validate it at all
+    pub fn intern_bytes_into(&self, record: &ByteRecord, out: &mut Vec<Option<Str>>) -> Result<()> {
+        out.clear();
+        for i in &self.columns {
+            let field = record.get(*i).map(str::from_utf8).transpose()?;
+            out.push(field.map(Str::try_from).transpose()?);
+        }
+        Ok(())
+    }
+
+    /// Intern the fields of `record` in the columns into a new vector
+    pub fn intern(&self, record: &StringRecord) -> Result<Vec<Option<Str>>> {
+        let mut out = Vec::with_capacity(self.columns.len());
+        self.intern_into(record, &mut out)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    const DATA: &str = "id,country,status\n1,csv fr,csv ok\n2,csv de,csv ok\n3,csv fr\n";
+
+    #[test]
+    fn test_intern_columns() {
+        let mut rdr = ::csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(DATA.as_bytes());
+        let cols = InternColumns::by_name(rdr.headers().unwrap(), &["status", "country"]).unwrap();
+        assert_eq!(cols.columns(), [2, 1]);
+        assert!(InternColumns::by_name(rdr.headers().unwrap(), &["missing"]).is_none());
+        let mut record = StringRecord::new();
+        let mut out = Vec::new();
+        let mut rows = Vec::new();
+        while rdr.read_record(&mut record).unwrap() {
+            cols.intern_into(&record, &mut out).unwrap();
+            rows.push(out.clone());
+        }
+        let t = |s| Some(Str::try_from(s).unwrap());
+        assert_eq!(
+            rows,
+            [
+                vec![t("csv ok"), t("csv fr")],
+                vec![t("csv ok"), t("csv de")],
+                vec![None, t("csv fr")],
+            ]
+        );
+        assert_eq!(rows[0][0].unwrap().as_raw(), rows[1][0].unwrap().as_raw());
+        let mut rdr = ::csv::Reader::from_reader(DATA.as_bytes());
+        let mut record = ByteRecord::new();
+        rdr.read_byte_record(&mut record).unwrap();
+        cols.intern_bytes_into(&record, &mut out).unwrap();
+        assert_eq!(out, [t("csv ok"), t("csv fr")]);
+        let bad = ByteRecord::from(vec![&b"1"[..], b"\xff", b"ok"]);
+        assert!(matches!(
+            InternColumns::new([1]).intern_bytes_into(&bad, &mut out),
+            Err(Error::Utf8(_))
+        ));
+        let long = StringRecord::from(vec!["x".repeat(Str::MAX_LEN + 1)]);
+        assert!(matches!(
+            InternColumns::new([0]).intern(&long),
+            Err(Error::TooLong { .. })
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+//! Interning strings decoded from other representations
+
+use crate::{stackbuf::StackBuf, Error, Result, Str, MAX_LEN};
+use std::str;
+
+impl Str {
+    /// Intern `bytes`, replacing invalid UTF-8 sequences with U+FFFD
+    /// the way `String::from_utf8_lossy` does. The result is built on
+    /// the stack, so nothing is allocated even when there are
+    /// replacements. It fails with `TooLong` if the result is over the
+    /// limit.
+    pub fn from_utf8_lossy(bytes: &[u8]) -> Result<Str> {
+        let mut buf = StackBuf::new();
+        for chunk in bytes.utf8_chunks() {
+            buf.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                buf.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+        buf.intern()
+    }
+
+    /// Convert UTF-16 to UTF-8 on the stack and intern it. Unpaired
+    /// surrogates are an `Error::Decode`, and a result over the limit
+    /// is `TooLong`.
+    pub fn from_utf16(units: &[u16]) -> Result<Str> {
+        let mut buf = StackBuf::new();
+        for c in char::decode_utf16(units.iter().copied()) {
+            buf.push(c.map_err(|_| Error::Decode("unpaired surrogate"))?);
+        }
+        buf.intern()
+    }
+
+    /// Percent-decode `s`, as found in a URL path segment, and intern
+    /// the result, so `%2Ffoo` and `/foo` are the same string. A `%`
+    /// not followed by two hex digits is kept as it is, and `+` is not
+    /// a space. It is decoded on the stack, so nothing is allocated. If
+    /// the decoded bytes are not UTF-8 it fails with `Error::Utf8`, and
+    /// if they are over the limit with `TooLong`.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_percent_decoded(s: &str) -> Result<Str> {
+        if !s.contains('%') {
+            return Str::try_from(s);
+        }
+        fn hex(b: u8) -> Option<u8> {
+            (b as char).to_digit(16).map(|d| d as u8)
+        }
+        let mut buf = [0; MAX_LEN];
+        let mut len = 0;
+        let mut bytes = s.as_bytes();
+        while let [b, rest @ ..] = bytes {
+            let (b, rest) = match rest {
+                [h, l, tail @ ..] if *b == b'%' => match (hex(*h), hex(*l)) {
+                    (Some(h), Some(l)) => (h << 4 | l, tail),
+                    _ => (*b, rest),
+                },
+                _ => (*b, rest),
+            };
+            if let Some(slot) = buf.get_mut(len) {
+                *slot = b;
+            }
+            len += 1;
+            bytes = rest;
+        }
+        if len > MAX_LEN {
+            return Err(Error::TooLong { len });
+        }
+        Str::try_from(str::from_utf8(&buf[..len])?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_utf8_lossy() {
+        let bytes = b"lossy \xff\xfe input \xe2\x82";
+        let t = Str::from_utf8_lossy(bytes).unwrap();
+        assert_eq!(&*t, String::from_utf8_lossy(bytes));
+        assert_eq!(&*Str::from_utf8_lossy(b"valid").unwrap(), "valid");
+    }
+
+    #[test]
+    fn test_percent_decoded() {
+        let t = Str::intern_percent_decoded("%2Ffoo").unwrap();
+        assert_eq!(t, Str::try_from("/foo").unwrap());
+        assert_eq!(
+            &*Str::intern_percent_decoded("caf%C3%a9+x").unwrap(),
+            "café+x"
+        );
+        assert_eq!(&*Str::intern_percent_decoded("100%").unwrap(), "100%");
+        assert_eq!(&*Str::intern_percent_decoded("%zz%4").unwrap(), "%zz%4");
+        assert!(matches!(
+            Str::intern_percent_decoded("%ff"),
+            Err(Error::Utf8(_))
+        ));
+        assert!(matches!(
+            Str::intern_percent_decoded(&"%41".repeat(300)),
+            Err(Error::TooLong { len: 300 })
+        ));
+    }
+
+    #[test]
+    fn test_from_utf16() {
+        let units: Vec<u16> = "utf16 𝄞 ok".encode_utf16().collect();
+        assert_eq!(&*Str::from_utf16(&units).unwrap(), "utf16 𝄞 ok");
+        assert!(matches!(
+            Str::from_utf16(&[0x61, 0xd834]),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            Str::from_utf16(&[0x61; 300]),
+            Err(Error::TooLong { len: 300 })
+        ));
+    }
+}
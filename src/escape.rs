@@ -0,0 +1,64 @@
+//! Interning escaped forms of strings
+
+use crate::{stackbuf::StackBuf, Result, Str};
+use std::fmt::Write;
+
+impl Str {
+    /// Escape `s` for use inside a JSON string literal (without the
+    /// surrounding quotes) and intern the result. The escaped form is
+    /// built on the stack, so nothing is allocated.
+    pub fn intern_json_escaped(s: &str) -> Result<Str> {
+        let mut buf = StackBuf::new();
+        for c in s.chars() {
+            match c {
+                '"' => buf.push_str("\\\""),
+                '\\' => buf.push_str("\\\\"),
+                '\n' => buf.push_str("\\n"),
+                '\r' => buf.push_str("\\r"),
+                '\t' => buf.push_str("\\t"),
+                '\u{8}' => buf.push_str("\\b"),
+                '\u{c}' => buf.push_str("\\f"),
+                c if c < ' ' => {
+                    let _ = write!(buf, "\\u{:04x}", c as u32);
+                }
+                c => buf.push(c),
+            }
+        }
+        buf.intern()
+    }
+
+    /// Escape `s` for use in HTML text or a quoted attribute value and
+    /// intern the result. The escaped form is built on the stack, so
+    /// nothing is allocated.
+    pub fn intern_html_escaped(s: &str) -> Result<Str> {
+        let mut buf = StackBuf::new();
+        for c in s.chars() {
+            match c {
+                '&' => buf.push_str("&amp;"),
+                '<' => buf.push_str("&lt;"),
+                '>' => buf.push_str("&gt;"),
+                '"' => buf.push_str("&quot;"),
+                '\'' => buf.push_str("&#39;"),
+                c => buf.push(c),
+            }
+        }
+        buf.intern()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Str};
+
+    #[test]
+    fn test_escape() {
+        let s = Str::intern_json_escaped("say \"hi\"\n\u{1}\\").unwrap();
+        assert_eq!(&*s, "say \\\"hi\\\"\\n\\u0001\\\\");
+        let s = Str::intern_html_escaped("<a href='x'>&</a>").unwrap();
+        assert_eq!(&*s, "&lt;a href=&#39;x&#39;&gt;&amp;&lt;/a&gt;");
+        assert!(matches!(
+            Str::intern_html_escaped(&"&".repeat(100)),
+            Err(Error::TooLong { len: 500 })
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+//! Turning the pool into Rust source
+
+use crate::{Result, Str, ROOT};
+use std::{collections::HashSet, io};
+
+/// The longest constant name generated, before a suffix making it
+/// unique
+const MAX_NAME: usize = 40;
+
+/// A constant name for `s`, its letters and digits upper cased with
+/// runs of anything else replaced by an underscore
+fn const_name(s: &str) -> String {
+    let mut name = String::new();
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+        if name.len() == MAX_NAME {
+            break;
+        }
+    }
+    let name = name.trim_end_matches('_');
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name.to_string(),
+        _ => format!("S_{name}"),
+    }
+}
+
+/// Write a Rust module holding every string in the pool to `w`,
+/// returning the number of strings written. Each string becomes a
+/// `pub const` named after it, and `ALL` lists them in the order they
+/// were interned, and the module's `prefill` function interns them
+/// all in place with `hcstatic_str::prefill`, so the strings a
+/// profiling run interned can be compiled into the next release and
+/// preloaded at startup, without reading a snapshot. The built in
+/// common strings are not included. The pool is only locked while the
+/// strings are gathered, not while they are written.
+pub fn export_as_rust(mut w: impl io::Write) -> Result<usize> {
+    let strs: Vec<Str> = ROOT.lock().strings().collect();
+    let mut names: HashSet<String> = HashSet::from(["ALL".to_string()]);
+    let mut all = Vec::with_capacity(strs.len());
+    writeln!(w, "// Generated by hcstatic_str::export_as_rust")?;
+    writeln!(w)?;
+    for t in &strs {
+        let base = const_name(t);
+        let mut name = base.clone();
+        let mut n = 1;
+        while names.contains(&name) {
+            n += 1;
+            name = format!("{base}_{n}");
+        }
+        // the Debug form of a str is a valid string literal
+        writeln!(w, "pub const {name}: &str = {:?};", &**t)?;
+        names.insert(name.clone());
+        all.push(name);
+    }
+    writeln!(w)?;
+    writeln!(w, "pub static ALL: &[&str] = &[")?;
+    for name in &all {
+        writeln!(w, "    {name},")?;
+    }
+    writeln!(w, "];")?;
+    writeln!(w)?;
+    writeln!(w, "/// Intern every string in `ALL`")?;
+    writeln!(w, "pub fn prefill() -> hcstatic_str::Result<usize> {{")?;
+    writeln!(w, "    hcstatic_str::prefill(ALL)")?;
+    writeln!(w, "}}")?;
+    Ok(strs.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_const_name() {
+        assert_eq!(const_name("content-type"), "CONTENT_TYPE");
+        assert_eq!(const_name("  x.y  "), "X_Y");
+        assert_eq!(const_name("404 not found"), "S_404_NOT_FOUND");
+        assert_eq!(const_name("ünïcode"), "N_CODE");
+        assert_eq!(const_name("$$"), "S_");
+        assert_eq!(const_name(&"ab".repeat(100)).len(), MAX_NAME);
+    }
+
+    #[test]
+    fn test_export_as_rust() {
+        for s in [
+            "export quote \"\\",
+            "export\nnewline",
+            "export-a",
+            "export a",
+            "all",
+        ] {
+            Str::try_from(s).unwrap();
+        }
+        let mut out = Vec::new();
+        let n = export_as_rust(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(n >= 4);
+        assert!(out.contains("pub const EXPORT_QUOTE: &str = \"export quote \\\"\\\\\";\n"));
+        assert!(out.contains("pub const EXPORT_NEWLINE: &str = \"export\\nnewline\";\n"));
+        let a = out
+            .find("pub const EXPORT_A: &str = \"export-a\";\n")
+            .unwrap();
+        let b = out
+            .find("pub const EXPORT_A_2: &str = \"export a\";\n")
+            .unwrap();
+        assert!(a < b);
+        assert!(!out.contains("pub const ALL:"));
+        assert!(out.contains("    EXPORT_A,\n") && out.contains("    EXPORT_A_2,\n"));
+        assert!(out.ends_with("    hcstatic_str::prefill(ALL)\n}\n"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Two word handles that keep a pointer to their bytes
+
+use crate::Str;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+#[cfg(not(feature = "forbid-unsafe"))]
+use std::{ptr::NonNull, slice, str};
+
+/// A `Str` together with the address of its bytes, for code that
+/// scans millions of handles in a tight loop. Reading a `Str` finds
+/// its bytes from its entry, which for strings made with
+/// `Str::from_static`, and on targets that don't pack the length into
+/// the handle, means reading the entry header or record first.
+/// `FatStr` pays one extra word to skip that: deref and slicing go
+/// straight to the bytes, and `len` comes from the handle on 64 bit
+/// x86 and ARM. Converting to and from `Str` is free, and comparisons,
+/// hashing and ordering are the same as for `Str`.
+///
+/// With `forbid-unsafe` it holds the handle and the `&'static str`,
+/// so it is three words.
+#[derive(Clone, Copy)]
+#[cfg(not(feature = "forbid-unsafe"))]
+pub struct FatStr {
+    ptr: NonNull<u8>,
+    t: Str,
+}
+
+#[derive(Clone, Copy)]
+#[cfg(feature = "forbid-unsafe")]
+pub struct FatStr {
+    s: &'static str,
+    t: Str,
+}
+
+// The bytes a FatStr points to are immutable and live forever, like
+// the entry of its Str.
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl Send for FatStr {}
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl Sync for FatStr {}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+impl FatStr {
+    /// The length of the string in bytes
+    pub fn len(&self) -> usize {
+        self.t.len()
+    }
+
+    /// The string with its full `'static` lifetime, as
+    /// `Str::as_static`
+    pub fn as_static(self) -> &'static str {
+        // SAFETY: ptr and len are those of the bytes of t, which are
+        // valid UTF-8 and live forever
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(self.ptr.as_ptr(), self.len())) }
+    }
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+impl From<Str> for FatStr {
+    fn from(t: Str) -> Self {
+        let ptr = NonNull::new(t.as_static().as_ptr() as *mut u8).unwrap();
+        FatStr { ptr, t }
+    }
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl FatStr {
+    /// The length of the string in bytes
+    pub fn len(&self) -> usize {
+        self.s.len()
+    }
+
+    /// The string with its full `'static` lifetime, as
+    /// `Str::as_static`
+    pub fn as_static(self) -> &'static str {
+        self.s
+    }
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl From<Str> for FatStr {
+    fn from(t: Str) -> Self {
+        FatStr {
+            s: t.as_static(),
+            t,
+        }
+    }
+}
+
+impl FatStr {
+    /// The interned string
+    pub fn get(self) -> Str {
+        self.t
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<FatStr> for Str {
+    fn from(t: FatStr) -> Str {
+        t.t
+    }
+}
+
+impl Deref for FatStr {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.as_static()
+    }
+}
+
+impl Borrow<str> for FatStr {
+    fn borrow(&self) -> &'static str {
+        self.as_static()
+    }
+}
+
+impl AsRef<str> for FatStr {
+    fn as_ref(&self) -> &'static str {
+        self.as_static()
+    }
+}
+
+impl fmt::Debug for FatStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl PartialEq for FatStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.t == other.t
+    }
+}
+
+impl Eq for FatStr {}
+
+impl Hash for FatStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl PartialOrd for FatStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FatStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{collections::HashSet, mem};
+
+    #[test]
+    fn test_fat() {
+        let words = if cfg!(feature = "forbid-unsafe") {
+            3
+        } else {
+            2
+        };
+        assert_eq!(mem::size_of::<FatStr>(), words * mem::size_of::<usize>());
+        let t = Str::try_from("fat str").unwrap();
+        let long = Str::try_from("f".repeat(Str::MAX_LEN).as_str()).unwrap();
+        let s = Str::from_static("a fat static string long enough for a record").unwrap();
+        for t in [t, long, s, Str::try_from("").unwrap()] {
+            let f = FatStr::from(t);
+            assert_eq!((&*f, f.len()), (&*t, t.len()));
+            assert_eq!(f.as_static().as_ptr(), t.as_static().as_ptr());
+            assert_eq!(Str::from(f).as_raw(), t.as_raw());
+        }
+        let f = FatStr::from(t);
+        assert_eq!(&f[4..], "str");
+        assert_eq!(format!("{f:?}"), "\"fat str\"");
+        let set: HashSet<FatStr> = [t, t, s].into_iter().map(FatStr::from).collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains("fat str"));
+        assert!(FatStr::from(s) < f);
+    }
+}
@@ -0,0 +1,107 @@
+//! Keeping the pool usable across `fork`.
+//!
+//! `fork` copies only the calling thread into the child, so any lock
+//! another thread held at that moment stays held in the child forever,
+//! and the child deadlocks the first time it interns. `prepare_fork`
+//! takes every lock the crate has, so no other thread can be holding
+//! one when the process forks, and the guard it returns releases them
+//! again. The crate doesn't register a `pthread_atfork` handler, since
+//! that needs unsafe code and would make every `fork` in the process,
+//! including the ones behind `std::process::Command`, wait for the
+//! pool.
+
+use crate::{
+    search::SORTED,
+    segpath::{Nodes, NODES},
+    sync::MutexGuard,
+    Generation, Root, Str, ROOT,
+};
+
+/// Every lock of the crate, held from `prepare_fork` until the guard is
+/// dropped
+#[must_use = "the locks are released as soon as the guard is dropped"]
+pub struct ForkGuard {
+    #[cfg(feature = "test-util")]
+    _isolated: std::sync::MutexGuard<'static, ()>,
+    #[cfg(feature = "substring-index")]
+    _trigrams: MutexGuard<'static, crate::trigram::Trigrams>,
+    #[cfg(feature = "fuzzy")]
+    _bk_tree: MutexGuard<'static, crate::fuzzy::BkTree>,
+    _sorted: MutexGuard<'static, (Vec<Str>, Generation)>,
+    _paths: MutexGuard<'static, Nodes>,
+    _root: MutexGuard<'static, Root>,
+    #[cfg(feature = "frequency")]
+    _counts: MutexGuard<'static, fxhash::FxHashMap<std::num::NonZeroUsize, (Str, u64)>>,
+    #[cfg(feature = "track-callers")]
+    _sites: MutexGuard<'static, std::collections::HashMap<crate::Site, (usize, usize)>>,
+    #[cfg(all(feature = "forbid-unsafe", feature = "ids"))]
+    _ids: MutexGuard<'static, fxhash::FxHashMap<usize, u32>>,
+    #[cfg(feature = "counters")]
+    _watch: MutexGuard<'static, Option<crate::stats::Watch>>,
+    #[cfg(feature = "counters")]
+    _samples: MutexGuard<'static, std::collections::VecDeque<crate::stats::Sample>>,
+    #[cfg(feature = "unicode")]
+    _char_counts: MutexGuard<'static, fxhash::FxHashMap<usize, crate::unicode::Counts>>,
+}
+
+/// Take every lock of the crate, waiting for other threads to release
+/// them, so the process can fork with the pool in a consistent state.
+/// Call this right before `fork`, and drop the guard right after it in
+/// both the parent and the child, which can then intern as usual.
+/// Interning on the forking thread while the guard is held deadlocks.
+/// With the `dashmap` feature, threads finding strings in the index
+/// without the pool lock take the locks of its shards, which this
+/// can't take, so a fork racing with such a lookup can still leave a
+/// shard locked in the child. With the `test-util` feature it waits
+/// for a running `with_isolated_pool` to finish, so it can't be called
+/// inside one.
+pub fn prepare_fork() -> ForkGuard {
+    // isolated tests hold their lock while they do everything else,
+    // the indexes over the pool lock it while they hold their own lock,
+    // and interning locks the counters, ids and hit ratio watch while
+    // it holds the pool lock, so they are taken in that order
+    ForkGuard {
+        #[cfg(feature = "test-util")]
+        _isolated: crate::test_util::ISOLATED
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner),
+        #[cfg(feature = "substring-index")]
+        _trigrams: crate::trigram::INDEX.lock(),
+        #[cfg(feature = "fuzzy")]
+        _bk_tree: crate::fuzzy::TREE.lock(),
+        _sorted: SORTED.lock(),
+        _paths: NODES.lock(),
+        _root: ROOT.lock(),
+        #[cfg(feature = "frequency")]
+        _counts: crate::frequency::COUNTS.lock(),
+        #[cfg(feature = "track-callers")]
+        _sites: crate::callsite::SITES.lock(),
+        #[cfg(all(feature = "forbid-unsafe", feature = "ids"))]
+        _ids: crate::leaked::IDS.lock(),
+        #[cfg(feature = "counters")]
+        _watch: crate::stats::WATCH.lock(),
+        #[cfg(feature = "counters")]
+        _samples: crate::stats::SAMPLES.lock(),
+        #[cfg(feature = "unicode")]
+        _char_counts: crate::unicode::COUNTS.lock(),
+    }
+}
+
+// with single-threaded the other thread panics instead of blocking
+#[cfg(all(
+    test,
+    not(all(feature = "single-threaded", not(feature = "forbid-unsafe")))
+))]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_prepare_fork() {
+        let guard = prepare_fork();
+        let blocked = thread::spawn(|| Str::try_intern_nonblocking("prepare fork")).join();
+        assert_eq!(blocked.unwrap(), None);
+        drop(guard);
+        assert_eq!(&*Str::try_from("prepare fork").unwrap(), "prepare fork");
+    }
+}
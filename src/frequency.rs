@@ -0,0 +1,67 @@
+//! Counting how often each string is interned.
+//!
+//! With the `frequency` feature every interning call that returns a
+//! string, whether it was already in the pool or not, bumps a count
+//! for that string. The counts are kept in a map behind their own
+//! lock, so this costs a lock and a hash lookup on every call, even
+//! ones that would otherwise not lock at all. It is meant for finding
+//! out which strings are worth building into a dictionary or
+//! preloading, not for production builds.
+
+use crate::sync::{Lazy, Mutex};
+use crate::Str;
+use fxhash::FxHashMap;
+use std::{cmp::Reverse, collections::HashMap, num::NonZeroUsize};
+
+/// The count of each string, with the string, by its handle
+pub(crate) static COUNTS: Lazy<Mutex<FxHashMap<NonZeroUsize, (Str, u64)>>> =
+    Lazy::new(|| Mutex::new(HashMap::default()));
+
+/// Count a request for `t`
+pub(crate) fn count(t: Str) {
+    COUNTS.lock().entry(t.as_raw()).or_insert((t, 0)).1 += 1;
+}
+
+/// The number of times `t` has been interned
+pub fn request_count(t: Str) -> u64 {
+    COUNTS.lock().get(&t.as_raw()).map_or(0, |(_, n)| *n)
+}
+
+/// The `n` most often interned strings with their counts, most
+/// frequent first, with ties broken by the string.
+pub fn top_k(n: usize) -> Vec<(Str, u64)> {
+    let mut all: Vec<(Str, u64)> = COUNTS.lock().values().copied().collect();
+    all.sort_unstable_by(|(s0, n0), (s1, n1)| (Reverse(n0), s0).cmp(&(Reverse(n1), s1)));
+    all.truncate(n);
+    all
+}
+
+/// Sort `strs` by how often they were interned, most often first,
+/// keeping the order of strings interned equally often
+pub(crate) fn rank(strs: &mut [Str]) {
+    let counts = COUNTS.lock();
+    strs.sort_by_cached_key(|t| Reverse(counts.get(&t.as_raw()).map_or(0, |(_, n)| *n)));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frequency() {
+        let a = Str::try_from("frequency a").unwrap();
+        for _ in 0..2 {
+            Str::try_from("frequency a").unwrap();
+        }
+        let b = Str::try_from("frequency b").unwrap();
+        assert_eq!(request_count(a), 3);
+        assert_eq!(request_count(b), 1);
+        // other tests intern concurrently, so only check the order of
+        // these two
+        let top = top_k(usize::MAX);
+        let pos = |t| top.iter().position(|(s, _)| *s == t).unwrap();
+        assert!(pos(a) < pos(b));
+        assert!(top.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(top_k(1).len(), 1);
+    }
+}
@@ -0,0 +1,114 @@
+//! The read only deployment mode
+//!
+//! Once the pool is frozen its contents are moved into a perfect hash
+//! table built with the hash and displace method. Each string's hash
+//! picks a bucket, and each bucket stores the displacement that sends
+//! all of its strings to distinct slots, so a lookup is one hash, two
+//! array reads, and one comparison, without any lock. Nothing can be
+//! added to a frozen pool.
+
+use crate::{Error, Result, Str, ROOT};
+use std::{
+    hash::{DefaultHasher, Hasher},
+    sync::OnceLock,
+};
+
+static FROZEN: OnceLock<Table> = OnceLock::new();
+
+struct Table {
+    /// the displacement of each bucket
+    disp: Vec<u32>,
+    slots: Vec<Option<Str>>,
+}
+
+/// The pool hash is fast, but similar strings often collide in it,
+/// and strings with equal hashes can't be told apart by a perfect
+/// hash. The frozen table uses SipHash with fixed keys instead.
+fn hash(s: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    h.write(s.as_bytes());
+    h.finish()
+}
+
+fn mix(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+fn bucket(hash: u64, len: usize) -> usize {
+    (hash % len as u64) as usize
+}
+
+fn slot(hash: u64, disp: u32, len: usize) -> usize {
+    (mix(hash ^ (disp as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)) % len as u64) as usize
+}
+
+impl Table {
+    fn build(strs: &[Str]) -> Table {
+        // a little slack makes the last buckets much quicker to place
+        let len = strs.len() + strs.len() / 8 + 1;
+        let mut buckets: Vec<Vec<(u64, Str)>> = vec![Vec::new(); strs.len().div_ceil(4).max(1)];
+        for s in strs {
+            let hash = hash(s);
+            let n = buckets.len();
+            buckets[bucket(hash, n)].push((hash, *s));
+        }
+        let mut order: Vec<usize> = (0..buckets.len()).collect();
+        order.sort_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+        let mut disp = vec![0; buckets.len()];
+        let mut slots = vec![None; len];
+        let mut taken = Vec::new();
+        for b in order {
+            'disp: for d in 0.. {
+                taken.clear();
+                for (hash, _) in &buckets[b] {
+                    let i = slot(*hash, d, len);
+                    if slots[i].is_some() || taken.contains(&i) {
+                        continue 'disp;
+                    }
+                    taken.push(i);
+                }
+                for (i, (_, s)) in taken.iter().zip(&buckets[b]) {
+                    slots[*i] = Some(*s);
+                }
+                disp[b] = d;
+                break;
+            }
+        }
+        Table { disp, slots }
+    }
+
+    fn get(&self, s: &str) -> Option<Str> {
+        let hash = hash(s);
+        let d = self.disp[bucket(hash, self.disp.len())];
+        self.slots[slot(hash, d, self.slots.len())].filter(|t| **t == *s)
+    }
+}
+
+/// Freeze the pool. Every string interned so far, for example by
+/// `preload_embedded`, stays available and is found without taking
+/// any lock, while interning anything else fails at once with
+/// `Error::Frozen`, so the memory used by the pool can't grow from
+/// then on. Freezing again does nothing.
+pub fn freeze() {
+    let mut root = ROOT.lock();
+    if FROZEN.get().is_none() {
+        let strs: Vec<Str> = root.strings().collect();
+        let _ = FROZEN.set(Table::build(&strs));
+        root.frozen = true;
+    }
+}
+
+/// Whether the pool has been frozen
+pub fn is_frozen() -> bool {
+    FROZEN.get().is_some()
+}
+
+/// Look `s` up in the frozen pool. None if the pool isn't frozen,
+/// otherwise the result of the lookup.
+pub(crate) fn get(s: &str) -> Option<Result<Str>> {
+    FROZEN.get().map(|t| t.get(s).ok_or(Error::Frozen))
+}
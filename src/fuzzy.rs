@@ -0,0 +1,155 @@
+//! Edit distance lookup over the pool, behind the `fuzzy` feature.
+//!
+//! The interned strings are kept in a BK-tree, in which each child of
+//! a node sits under its edit distance to the node. The triangle
+//! inequality then bounds the distance of everything under a child, so
+//! a query only descends into the children whose distance is within
+//! `max_distance` of the distance of the query to the node. Like the
+//! substring index, the tree is built the first time
+//! `closest_matches` is called and brought up to date with the strings
+//! interned since on every later call.
+
+use crate::{
+    generation::{new_since, Generation},
+    sync::{Lazy, Mutex},
+    Str,
+};
+
+struct Node {
+    t: Str,
+    /// the positions in nodes of the children, with their distance to
+    /// this node
+    children: Vec<(u8, u32)>,
+}
+
+#[derive(Default)]
+pub(crate) struct BkTree {
+    /// the generation the tree is up to date with
+    seen: Generation,
+    /// the root is the first node
+    nodes: Vec<Node>,
+}
+
+pub(crate) static TREE: Lazy<Mutex<BkTree>> = Lazy::new(|| Mutex::new(BkTree::default()));
+
+/// The Levenshtein distance between a and b, in chars
+fn distance(a: &[char], b: &str) -> usize {
+    let mut row: Vec<usize> = (0..=a.len()).collect();
+    for (j, cb) in b.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = j + 1;
+        for (i, ca) in a.iter().enumerate() {
+            let d = (diag + (*ca != cb) as usize)
+                .min(row[i] + 1)
+                .min(row[i + 1] + 1);
+            diag = row[i + 1];
+            row[i + 1] = d;
+        }
+    }
+    row[a.len()]
+}
+
+impl BkTree {
+    fn update(&mut self) {
+        let (strs, generation) = new_since(self.seen);
+        for t in strs {
+            self.insert(t);
+        }
+        self.seen = generation;
+    }
+
+    fn insert(&mut self, t: Str) {
+        let id = u32::try_from(self.nodes.len()).expect("too many strings to index");
+        self.nodes.push(Node {
+            t,
+            children: Vec::new(),
+        });
+        if id == 0 {
+            return;
+        }
+        let chars: Vec<char> = t.chars().collect();
+        let mut node = 0;
+        loop {
+            // strings are at most MAX_LEN bytes, so distances fit in a u8
+            let d = distance(&chars, &self.nodes[node].t) as u8;
+            let children = &mut self.nodes[node].children;
+            match children.iter().find(|(cd, _)| *cd == d) {
+                Some((_, child)) => node = *child as usize,
+                None => {
+                    children.push((d, id));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Up to `limit` interned strings within `max_distance` edits of
+/// `query`, closest first, each with its distance, for "did you mean"
+/// suggestions. An edit inserts, deletes or replaces one char. Strings
+/// at the same distance are ordered by their contents. The built in
+/// common strings aren't searched.
+pub fn closest_matches(query: &str, max_distance: usize, limit: usize) -> Vec<(Str, usize)> {
+    let mut tree = TREE.lock();
+    tree.update();
+    let mut found = Vec::new();
+    if tree.nodes.is_empty() {
+        return found;
+    }
+    let query: Vec<char> = query.chars().collect();
+    let mut stack = vec![0];
+    while let Some(node) = stack.pop() {
+        let Node { t, children } = &tree.nodes[node];
+        let d = distance(&query, t);
+        if d <= max_distance {
+            found.push((*t, d));
+        }
+        stack.extend(
+            children
+                .iter()
+                .filter(|(cd, _)| (*cd as usize).abs_diff(d) <= max_distance)
+                .map(|(_, child)| *child as usize),
+        );
+    }
+    found.sort_unstable_by(|(t, d), (u, e)| d.cmp(e).then_with(|| t.as_static().cmp(u)));
+    found.truncate(limit);
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_closest_matches() {
+        assert_eq!(distance(&['k', 'i', 't', 't', 'e', 'n'], "sitting"), 3);
+        assert_eq!(distance(&[], "héllo"), 5);
+        for s in [
+            "fuzzy-commit",
+            "fuzzy-config",
+            "fuzzy-checkout",
+            "fuzzy-clone",
+        ] {
+            Str::try_from(s).unwrap();
+        }
+        let closest = |q, max, limit| {
+            closest_matches(q, max, limit)
+                .into_iter()
+                .map(|(t, d)| (t.as_static(), d))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(closest("fuzzy-comit", 2, 5), [("fuzzy-commit", 1)]);
+        assert_eq!(
+            closest("fuzzy-cone", 3, 2),
+            [("fuzzy-clone", 1), ("fuzzy-config", 3)]
+        );
+        assert!(closest("fuzzy-comit", 2, 0).is_empty());
+        // interned after the tree was built
+        Str::try_from("fuzzy-cherry-pick").unwrap();
+        assert_eq!(
+            closest("fuzzy-chery-pick", 1, 5),
+            [("fuzzy-cherry-pick", 1)]
+        );
+        assert!(closest("fuzzy-rebase", 2, 5).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! Finding the strings interned since some earlier point
+
+use crate::{Root, Str, ROOT};
+#[cfg(target_has_atomic = "64")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The generation as of the last insert, the end of the last chunk
+/// and then the end of the tail being filled. Targets without 64 bit
+/// atomics compute the generation under the pool lock instead.
+#[cfg(target_has_atomic = "64")]
+static CURRENT: AtomicU64 = AtomicU64::new(0);
+#[cfg(target_has_atomic = "64")]
+static CURRENT_TAIL: AtomicU64 = AtomicU64::new(0);
+
+/// A point in the history of the pool. Strings are only ever added to
+/// the pool, so a generation says which of them had been interned at
+/// that point. Later generations compare greater. The default
+/// generation is the empty pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Generation {
+    last: u64,
+    tail: u64,
+}
+
+impl Generation {
+    /// The current generation of `root`. This is the position of the
+    /// end of the written part of the last chunk, and of the tail of a
+    /// retired chunk `set_recycle_slack` is filling, both of which only
+    /// move forward.
+    pub(crate) fn of(root: &Root) -> Generation {
+        let last = match root.chunks.last() {
+            None => 0,
+            Some(c) => (((root.chunks.len() - 1) as u64) << 32) | c.written as u64,
+        };
+        let written = match root.tails.get(root.tail) {
+            None => 0,
+            Some((i, _)) => root.chunks[*i].written as u64,
+        };
+        Generation {
+            last,
+            tail: ((root.tail as u64) << 32) | written,
+        }
+    }
+
+    /// Record the generation of `root` after an insert
+    pub(crate) fn publish(_root: &Root) {
+        #[cfg(target_has_atomic = "64")]
+        {
+            let g = Generation::of(_root);
+            CURRENT_TAIL.store(g.tail, Ordering::Release);
+            CURRENT.store(g.last, Ordering::Release);
+        }
+    }
+
+    fn chunk(self) -> usize {
+        (self.last >> 32) as usize
+    }
+
+    fn offset(self) -> usize {
+        (self.last & u32::MAX as u64) as usize
+    }
+
+    fn tail(self) -> usize {
+        (self.tail >> 32) as usize
+    }
+
+    fn tail_offset(self) -> usize {
+        (self.tail & u32::MAX as u64) as usize
+    }
+}
+
+/// The current generation of the pool. This is a single atomic load,
+/// and it changes on every insertion, so it is a cheap way to tell
+/// whether anything derived from the contents of the pool is stale.
+/// On targets without 64 bit atomics it takes the pool lock.
+pub fn generation() -> Generation {
+    // the two halves may come from different inserts, but each is one
+    // the pool had, so new_since never misses a string
+    #[cfg(target_has_atomic = "64")]
+    return Generation {
+        last: CURRENT.load(Ordering::Acquire),
+        tail: CURRENT_TAIL.load(Ordering::Acquire),
+    };
+    #[cfg(not(target_has_atomic = "64"))]
+    return Generation::of(&ROOT.lock());
+}
+
+/// Return the strings interned since `generation`, in the order they
+/// were interned, along with the current generation to pass to the
+/// next call. Replicating the pool to another process can then send
+/// only what is new each time. The built in common strings are never
+/// returned, as they are always in the pool. Strings `set_recycle_slack`
+/// wrote to the tails of retired chunks are returned first.
+pub fn new_since(generation: Generation) -> (Vec<Str>, Generation) {
+    let root = ROOT.lock();
+    let mut strs = Vec::new();
+    // the chunks from the last one of the generation on are walked to
+    // their end below, tails and all
+    for (k, (i, start)) in root.tails.iter().enumerate().skip(generation.tail()) {
+        if *i >= generation.chunk() {
+            break;
+        }
+        let from = if k == generation.tail() {
+            generation.tail_offset().max(*start)
+        } else {
+            *start
+        };
+        strs.extend(root.chunks[*i].entries(from));
+    }
+    for (i, c) in root.chunks.iter().enumerate().skip(generation.chunk()) {
+        let from = if i == generation.chunk() {
+            generation.offset()
+        } else {
+            0
+        };
+        strs.extend(c.entries(from));
+    }
+    (strs, Generation::of(&root))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_new_since() {
+        let (_, g0) = new_since(Generation::default());
+        let a = Str::try_from("generation a").unwrap();
+        let b = Str::try_from("generation b").unwrap();
+        let (strs, g1) = new_since(g0);
+        assert!(g1 > g0);
+        // other tests may be interning at the same time
+        let pos = |t| strs.iter().position(|s| *s == t).unwrap();
+        assert!(pos(a) < pos(b));
+        let (strs, _) = new_since(g1);
+        assert!(!strs.contains(&a));
+        let (all, _) = new_since(Generation::default());
+        assert!(all.contains(&a) && all.contains(&b));
+    }
+
+    #[test]
+    fn test_generation() {
+        let g0 = generation();
+        Str::try_from("generation counter").unwrap();
+        let g1 = generation();
+        assert!(g1 > g0);
+        Str::try_from("generation counter").unwrap();
+        assert!(generation() >= g1);
+        assert!(new_since(g0).0.iter().any(|s| &**s == "generation counter"));
+    }
+}
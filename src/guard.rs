@@ -0,0 +1,85 @@
+//! Screening strings before they are added to the pool
+
+use crate::{Error, Result, Root, Str, ROOT};
+
+/// What the intern guard decided about a string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Add the string to the pool
+    Allow,
+    /// Refuse it, interning fails with `Error::Rejected`
+    Reject,
+    /// Intern this string in its place, for example the string with
+    /// the secret it contains masked out. The replacement isn't
+    /// screened again.
+    Replace(String),
+}
+
+/// The intern guard, see `set_intern_guard`
+pub type Guard = Box<dyn Fn(&str) -> Verdict + Send>;
+
+/// Screen every string before it is added to the pool with `guard`,
+/// replacing the guard set before, or stop screening with None.
+/// Anything interned stays in memory, and in core dumps, until the
+/// process exits, so a guard that rejects or masks out strings that
+/// look like API keys or bearer tokens keeps them out of the pool even
+/// when they reach an interning call by mistake.
+///
+/// The guard is only asked about strings that aren't in the pool yet,
+/// so strings that were interned before it was set are still found,
+/// and it is called with the pool lock held, so it must not intern
+/// anything. With `shared-pool`, strings interned while another copy's
+/// pool is shared are screened by that copy's guard.
+pub fn set_intern_guard(guard: Option<Guard>) {
+    ROOT.lock().guard = guard;
+}
+
+impl Root {
+    /// Ask the guard about `s`, which isn't in the pool, returning the
+    /// replacement to intern instead if there is one
+    pub(crate) fn screen(&self, s: &str) -> Result<Option<String>> {
+        match self.guard.as_ref().map(|guard| guard(s)) {
+            None | Some(Verdict::Allow) => Ok(None),
+            Some(Verdict::Reject) => Err(Error::Rejected),
+            Some(Verdict::Replace(r)) => Ok(Some(r)),
+        }
+    }
+
+    /// Intern `r`, the replacement the guard chose, without screening
+    /// it again
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) fn intern_replacement(&mut self, r: &str) -> Result<Str> {
+        let guard = self.guard.take();
+        let res = self.intern(r);
+        self.guard = guard;
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guard() {
+        let before = Str::try_from("Bearer guard before").unwrap();
+        set_intern_guard(Some(Box::new(|s: &str| {
+            if s.starts_with("sk-guard") {
+                Verdict::Reject
+            } else if let Some(token) = s.strip_prefix("Bearer guard") {
+                Verdict::Replace(format!("Bearer guard {}", "*".repeat(token.len())))
+            } else {
+                Verdict::Allow
+            }
+        })));
+        let rejected = Str::try_from("sk-guard 1234");
+        let masked = Str::try_from("Bearer guard abc").unwrap();
+        let found = Str::try_from("Bearer guard before").unwrap();
+        set_intern_guard(None);
+        assert!(matches!(rejected, Err(Error::Rejected)));
+        assert_eq!(&*masked, "Bearer guard ****");
+        assert_eq!(masked, Str::try_from("Bearer guard ****").unwrap());
+        assert_eq!(found, before);
+        assert!(Str::try_from("sk-guard 1234").is_ok());
+    }
+}
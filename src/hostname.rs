@@ -0,0 +1,94 @@
+//! Interned hostnames, behind the `idna` feature
+
+use crate::{Error, Result, Str};
+use idna::AsciiDenyList;
+use std::{fmt, ops::Deref};
+
+/// A hostname interned in its ASCII form, after IDNA processing:
+/// Unicode labels are mapped as UTS 46 specifies, which lowercases
+/// them, and encoded as punycode, so `Bücher.example`,
+/// `bücher.example` and `xn--bcher-kva.example` are the same
+/// `Hostname`. Names already in lower case ASCII, which is most of
+/// them, are interned without allocating.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hostname(Str);
+
+impl Hostname {
+    /// The interned ASCII form
+    pub fn get(self) -> Str {
+        self.0
+    }
+
+    /// The Unicode form, for display
+    pub fn to_unicode(self) -> String {
+        idna::domain_to_unicode(&self.0).0
+    }
+}
+
+impl TryFrom<&str> for Hostname {
+    type Error = Error;
+
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn try_from(s: &str) -> Result<Self> {
+        match idna::domain_to_ascii_cow(s.as_bytes(), AsciiDenyList::URL) {
+            Ok(ascii) if !ascii.is_empty() => Ok(Hostname(Str::try_from(&*ascii)?)),
+            _ => Err(Error::Invalid("not a valid hostname")),
+        }
+    }
+}
+
+impl TryFrom<Str> for Hostname {
+    type Error = Error;
+
+    fn try_from(s: Str) -> Result<Self> {
+        Hostname::try_from(s.as_static())
+    }
+}
+
+impl From<Hostname> for Str {
+    fn from(h: Hostname) -> Str {
+        h.0
+    }
+}
+
+impl Deref for Hostname {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.0.as_static()
+    }
+}
+
+impl fmt::Debug for Hostname {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Hostname {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hostname() {
+        let h = Hostname::try_from("Bücher.Example").unwrap();
+        assert_eq!(&*h, "xn--bcher-kva.example");
+        assert_eq!(h, Hostname::try_from("xn--bcher-kva.example").unwrap());
+        assert_eq!(h, Hostname::try_from("BÜCHER.example").unwrap());
+        assert_eq!(h.to_unicode(), "bücher.example");
+        let h = Hostname::try_from("WWW.Example.COM").unwrap();
+        assert_eq!(h.get(), Str::try_from("www.example.com").unwrap());
+        for bad in ["", "a b.com", "host:80", "xn--a.com"] {
+            assert!(
+                matches!(Hostname::try_from(bad), Err(Error::Invalid(_))),
+                "{bad}"
+            );
+        }
+    }
+}
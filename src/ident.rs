@@ -0,0 +1,151 @@
+//! Interning with validation
+
+use crate::{Error, Result, Str};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Deref,
+};
+
+/// A rule a string must follow to be interned as an `IdentStr`
+pub trait Validator {
+    /// Return an error describing why `s` is not valid, if it isn't
+    fn validate(s: &str) -> Result<(), &'static str>;
+}
+
+/// The default identifier rule, `[A-Za-z_][A-Za-z0-9_]*`
+#[derive(Debug, Clone, Copy)]
+pub struct Identifier;
+
+impl Validator for Identifier {
+    fn validate(s: &str) -> Result<(), &'static str> {
+        let mut bytes = s.bytes();
+        match bytes.next() {
+            None => Err("identifier is empty"),
+            Some(b) if !(b.is_ascii_alphabetic() || b == b'_') => {
+                Err("identifier must start with a letter or _")
+            }
+            Some(_) if bytes.all(|b| b.is_ascii_alphanumeric() || b == b'_') => Ok(()),
+            Some(_) => Err("identifier may only contain letters, digits, and _"),
+        }
+    }
+}
+
+/// A `Str` that passed the validator `V` when it was interned. Strings
+/// are validated before they are interned, so strings that fail never
+/// end up in the pool.
+pub struct IdentStr<V: Validator = Identifier>(Str, PhantomData<fn() -> V>);
+
+impl<V: Validator> IdentStr<V> {
+    /// The interned string
+    pub fn get(self) -> Str {
+        self.0
+    }
+}
+
+impl<V: Validator> TryFrom<&str> for IdentStr<V> {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        V::validate(s).map_err(Error::Invalid)?;
+        Ok(IdentStr(Str::try_from(s)?, PhantomData))
+    }
+}
+
+impl<V: Validator> TryFrom<Str> for IdentStr<V> {
+    type Error = Error;
+
+    fn try_from(s: Str) -> Result<Self> {
+        V::validate(&s).map_err(Error::Invalid)?;
+        Ok(IdentStr(s, PhantomData))
+    }
+}
+
+impl<V: Validator> From<IdentStr<V>> for Str {
+    fn from(s: IdentStr<V>) -> Str {
+        s.0
+    }
+}
+
+impl<V: Validator> Deref for IdentStr<V> {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.0.as_static()
+    }
+}
+
+// implemented by hand so V doesn't need to implement them
+
+impl<V: Validator> Clone for IdentStr<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V: Validator> Copy for IdentStr<V> {}
+
+impl<V: Validator> PartialEq for IdentStr<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<V: Validator> Eq for IdentStr<V> {}
+
+impl<V: Validator> PartialOrd for IdentStr<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V: Validator> Ord for IdentStr<V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<V: Validator> Hash for IdentStr<V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<V: Validator> fmt::Debug for IdentStr<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Lower;
+
+    impl Validator for Lower {
+        fn validate(s: &str) -> Result<(), &'static str> {
+            if s.bytes().all(|b| b.is_ascii_lowercase()) {
+                Ok(())
+            } else {
+                Err("not lowercase")
+            }
+        }
+    }
+
+    #[test]
+    fn test_ident() {
+        let i = IdentStr::<Identifier>::try_from("_foo9").unwrap();
+        assert_eq!(&*i, "_foo9");
+        for bad in ["", "9foo", "foo-bar", "fé"] {
+            assert!(matches!(
+                IdentStr::<Identifier>::try_from(bad),
+                Err(Error::Invalid(_))
+            ));
+        }
+        assert!(IdentStr::<Lower>::try_from("abc").is_ok());
+        assert!(IdentStr::<Lower>::try_from("aBc").is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! The index used to find strings that are already in the pool.
+//!
+//! By default the index is a hash set protected by the pool lock. With
+//! the `dashmap` feature it is a concurrent set living outside the
+//! lock instead, so strings that are already interned are found without
+//! taking the pool lock at all, and the lock only serializes inserting
+//! new strings into the chunks. All inserts still happen under the pool
+//! lock, so looking a string up again under the lock before inserting
+//! it is enough to keep the pool free of duplicates.
+//!
+//! With the `arc-swap` feature the index is an immutable snapshot that
+//! anyone can read without a lock, plus a small delta of recent inserts
+//! protected by the pool lock. The delta is merged into a new snapshot
+//! once it has grown to a fraction of the snapshot, so readers never
+//! lock and writers pay an amortized copy for each merge. Strings still
+//! in the delta are only found under the lock. If both features are
+//! enabled `arc-swap` is used.
+//!
+//! With the `profiling` feature the functions that grow the index, and
+//! the one that allocates chunks, are never inlined, so heap profilers
+//! such as dhat and heaptrack attribute the pool's allocations to
+//! `hcstatic_str` frames rather than to whatever called `try_from`.
+
+use crate::sync::Lazy;
+use crate::{config, Str};
+use fxhash::FxHasher;
+use hashbrown::TryReserveError;
+use std::hash::{BuildHasher, Hasher};
+
+/// The hasher of the pool index. This is FxHash, optionally seeded
+/// with per process randomness by the `random-seed` feature, or a
+/// `Config`, so that the layout of the index, and which strings
+/// collide in it, can't be predicted from one run to the next. The
+/// `deterministic` feature overrides both, for test and simulation
+/// builds that must be reproducible.
+#[derive(Clone)]
+pub(crate) struct PoolHasher {
+    seed: u64,
+}
+
+impl Default for PoolHasher {
+    fn default() -> Self {
+        let seed = if config::boot().random_seed && !cfg!(feature = "deterministic") {
+            std::collections::hash_map::RandomState::new().hash_one(0u64)
+        } else {
+            0
+        };
+        PoolHasher { seed }
+    }
+}
+
+impl BuildHasher for PoolHasher {
+    type Hasher = FxHasher;
+
+    fn build_hasher(&self) -> FxHasher {
+        let mut h = FxHasher::default();
+        if self.seed != 0 {
+            h.write_u64(self.seed);
+        }
+        h
+    }
+}
+
+/// The hasher every lookup uses. It lives outside the pool lock so
+/// callers can hash a string before taking the lock.
+static HASHER: Lazy<PoolHasher> = Lazy::new(PoolHasher::default);
+
+/// Compare the bytes of an entry with a candidate 8 bytes at a time.
+/// Interned strings are often close to the 255 byte limit, and every
+/// probe that finds the string has to compare all of it, so this is
+/// noticeably faster than a byte wise loop while staying portable. The
+/// compiler turns the word compares into vector compares where it can.
+#[inline]
+pub(crate) fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let (ca, ra) = (a.chunks_exact(8), a.chunks_exact(8).remainder());
+    let rb = b.chunks_exact(8).remainder();
+    let word = |c: &[u8]| u64::from_ne_bytes(c.try_into().unwrap());
+    ca.zip(b.chunks_exact(8))
+        .fold(0, |acc, (x, y)| acc | (word(x) ^ word(y)))
+        == 0
+        && ra == rb
+}
+
+/// Hash `s` the way the index does
+pub(crate) fn hash(s: &str) -> u64 {
+    HASHER.hash_one(s)
+}
+
+#[cfg(not(any(feature = "dashmap", feature = "arc-swap")))]
+mod imp {
+    use super::*;
+    use hashbrown::HashTable;
+
+    pub(crate) struct Index(HashTable<Str>);
+
+    impl Index {
+        pub(crate) fn new() -> Self {
+            Index(HashTable::new())
+        }
+
+        /// Find `s`, whose hash must be `hash(s)`
+        pub(crate) fn get(&self, hash: u64, s: &str) -> Option<Str> {
+            self.0
+                .find(hash, |t| bytes_eq(t.as_bytes(), s.as_bytes()))
+                .copied()
+        }
+
+        /// Insert `s`, which must not be in the index, with its hash
+        #[cfg_attr(feature = "profiling", inline(never))]
+        pub(crate) fn insert(&mut self, hash: u64, s: Str) {
+            self.0.insert_unique(hash, s, |t| super::hash(t));
+        }
+
+        #[cfg_attr(feature = "profiling", inline(never))]
+        pub(crate) fn try_reserve(&mut self, n: usize) -> Result<(), TryReserveError> {
+            self.0.try_reserve(n, |t| super::hash(t))
+        }
+
+        pub(crate) fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        /// The bytes allocated by the table
+        pub(crate) fn allocation_size(&self) -> usize {
+            self.0.allocation_size()
+        }
+    }
+
+    /// Look up `s` without the pool lock, which isn't possible with
+    /// this index
+    pub(crate) fn get_unlocked(_hash: u64, _s: &str) -> Option<Str> {
+        None
+    }
+}
+
+#[cfg(all(feature = "dashmap", not(feature = "arc-swap")))]
+mod imp {
+    use super::*;
+    use dashmap::DashMap;
+
+    /// A map rather than a `DashSet`, which can't be given a shard
+    /// count
+    static SET: Lazy<DashMap<Str, (), PoolHasher>> = Lazy::new(|| match config::boot().shards {
+        Some(n) => DashMap::with_hasher_and_shard_amount(HASHER.clone(), n),
+        None => DashMap::with_hasher(HASHER.clone()),
+    });
+
+    /// A handle to the global concurrent set. Only the pool lock holder
+    /// has one, so only it can insert.
+    pub(crate) struct Index(&'static DashMap<Str, (), PoolHasher>);
+
+    impl Index {
+        pub(crate) fn new() -> Self {
+            Index(&SET)
+        }
+
+        /// dashmap can't take a precomputed hash, so `_hash` is unused
+        pub(crate) fn get(&self, _hash: u64, s: &str) -> Option<Str> {
+            self.0.get(s).map(|r| *r.key())
+        }
+
+        #[cfg_attr(feature = "profiling", inline(never))]
+        pub(crate) fn insert(&mut self, _hash: u64, s: Str) {
+            self.0.insert(s, ());
+        }
+
+        /// dashmap can't reserve fallibly, its shards grow as needed
+        #[cfg_attr(feature = "profiling", inline(never))]
+        pub(crate) fn try_reserve(&mut self, _n: usize) -> Result<(), TryReserveError> {
+            Ok(())
+        }
+
+        pub(crate) fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        /// An estimate of the bytes allocated by the shards, from their
+        /// capacity, as dashmap doesn't report it
+        pub(crate) fn allocation_size(&self) -> usize {
+            let buckets = self.0.capacity() * 8 / 7;
+            buckets * (std::mem::size_of::<Str>() + 1)
+        }
+    }
+
+    /// Look up `s` without the pool lock
+    pub(crate) fn get_unlocked(_hash: u64, s: &str) -> Option<Str> {
+        SET.get(s).map(|r| *r.key())
+    }
+}
+
+#[cfg(feature = "arc-swap")]
+mod imp {
+    use super::*;
+    use arc_swap::ArcSwap;
+    use hashbrown::HashTable;
+    use std::sync::Arc;
+
+    static SNAPSHOT: Lazy<ArcSwap<HashTable<Str>>> =
+        Lazy::new(|| ArcSwap::from_pointee(HashTable::new()));
+
+    /// The smallest delta worth merging
+    const MIN_DELTA: usize = 1024;
+
+    fn find(t: &HashTable<Str>, hash: u64, s: &str) -> Option<Str> {
+        t.find(hash, |t| bytes_eq(t.as_bytes(), s.as_bytes()))
+            .copied()
+    }
+
+    /// The strings inserted since the last merge. Only the pool lock
+    /// holder has the delta, so only it can insert or merge.
+    pub(crate) struct Index {
+        delta: HashTable<Str>,
+    }
+
+    impl Index {
+        pub(crate) fn new() -> Self {
+            Index {
+                delta: HashTable::new(),
+            }
+        }
+
+        pub(crate) fn get(&self, hash: u64, s: &str) -> Option<Str> {
+            find(&SNAPSHOT.load(), hash, s).or_else(|| find(&self.delta, hash, s))
+        }
+
+        #[cfg_attr(feature = "profiling", inline(never))]
+        pub(crate) fn insert(&mut self, hash: u64, s: Str) {
+            self.delta.insert_unique(hash, s, |t| super::hash(t));
+            if self.delta.len() >= MIN_DELTA.max(SNAPSHOT.load().len() / 4) {
+                self.merge();
+            }
+        }
+
+        /// Publish a new snapshot holding the old one and the delta. If
+        /// it can't be allocated the delta is kept, and merging is
+        /// tried again on the next insert.
+        #[cfg_attr(feature = "profiling", inline(never))]
+        fn merge(&mut self) {
+            let old = SNAPSHOT.load_full();
+            let mut new: HashTable<Str> = HashTable::new();
+            if new
+                .try_reserve(old.len() + self.delta.len(), |t| super::hash(t))
+                .is_err()
+            {
+                return;
+            }
+            for t in old.iter().chain(self.delta.iter()) {
+                new.insert_unique(super::hash(t), *t, |t| super::hash(t));
+            }
+            SNAPSHOT.store(Arc::new(new));
+            self.delta.clear();
+        }
+
+        #[cfg_attr(feature = "profiling", inline(never))]
+        pub(crate) fn try_reserve(&mut self, n: usize) -> Result<(), TryReserveError> {
+            self.delta.try_reserve(n, |t| super::hash(t))
+        }
+
+        pub(crate) fn len(&self) -> usize {
+            SNAPSHOT.load().len() + self.delta.len()
+        }
+
+        /// The bytes allocated by the current snapshot and the delta.
+        /// Readers may still hold older snapshots, which aren't
+        /// counted.
+        pub(crate) fn allocation_size(&self) -> usize {
+            SNAPSHOT.load().allocation_size() + self.delta.allocation_size()
+        }
+    }
+
+    /// Look up `s` in the snapshot without any lock
+    pub(crate) fn get_unlocked(hash: u64, s: &str) -> Option<Str> {
+        find(&SNAPSHOT.load(), hash, s)
+    }
+}
+
+pub(crate) use imp::{get_unlocked, Index};
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_bytes_eq() {
+        let a: Vec<u8> = (0..=254).collect();
+        for len in 0..a.len() {
+            let s = &a[..len];
+            assert!(super::bytes_eq(s, &a[..len]));
+            assert!(!super::bytes_eq(s, &a[..len + 1]));
+            for i in 0..len {
+                let mut b = s.to_vec();
+                b[i] ^= 1;
+                assert!(!super::bytes_eq(s, &b));
+            }
+        }
+    }
+
+    #[cfg(feature = "arc-swap")]
+    #[test]
+    fn test_snapshot() {
+        use super::*;
+        let s = "snapshot 0";
+        let t = Str::try_from(s).unwrap();
+        // keep inserting until a merge publishes the first string
+        let merged = (1..1_000_000).any(|i| {
+            Str::try_from(format!("snapshot {i}").as_str()).unwrap();
+            get_unlocked(hash(s), s).is_some()
+        });
+        assert!(merged);
+        assert_eq!(get_unlocked(hash(s), s), Some(t));
+    }
+}
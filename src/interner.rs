@@ -0,0 +1,214 @@
+//! A common interface to string interners
+
+use crate::{common, frozen, index, Error, Result, Str, ROOT};
+
+/// An interner, so libraries can be written against any interner and
+/// users can swap one for another. This crate implements it for the
+/// global pool, `Global`, and for lasso's `Rodeo` with the `lasso`
+/// feature.
+pub trait Interner {
+    /// The handle the interner returns for a string
+    type Key: Copy + Eq;
+    type Error;
+
+    /// Intern `s`, returning the key of the existing copy if there is
+    /// one
+    fn intern(&mut self, s: &str) -> Result<Self::Key, Self::Error>;
+
+    /// Find the key of `s` if it is already interned, without
+    /// interning it
+    fn get(&self, s: &str) -> Option<Self::Key>;
+
+    /// The string a key was returned for
+    fn resolve(&self, key: Self::Key) -> &str;
+
+    /// Intern every string in `batch`, returning the first error after
+    /// trying all of them. Interners that lock can override this to
+    /// lock once for the whole batch.
+    fn intern_batch<S: AsRef<str>>(&mut self, batch: &[S]) -> Result<(), Self::Error>
+    where
+        Self: Sized,
+    {
+        let mut res = Ok(());
+        for s in batch {
+            if let Err(e) = self.intern(s.as_ref()) {
+                res = res.and(Err(e));
+            }
+        }
+        res
+    }
+}
+
+/// The global pool as an `Interner`. Keys are `Str`, and it is free
+/// to create as many as needed, they all refer to the same pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Global;
+
+impl Interner for Global {
+    type Key = Str;
+    type Error = Error;
+
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn intern(&mut self, s: &str) -> Result<Str> {
+        Str::try_from(s)
+    }
+
+    fn get(&self, s: &str) -> Option<Str> {
+        if let Some(t) = common::get(s) {
+            return Some(t);
+        }
+        if let Some(res) = frozen::get(s) {
+            return res.ok();
+        }
+        let hash = index::hash(s);
+        index::get_unlocked(hash, s).or_else(|| ROOT.lock().all.get(hash, s))
+    }
+
+    fn resolve(&self, key: Str) -> &str {
+        key.as_static()
+    }
+
+    fn intern_batch<S: AsRef<str>>(&mut self, batch: &[S]) -> Result<()> {
+        let mut root = ROOT.lock();
+        let mut res = Ok(());
+        for s in batch {
+            if let Err(e) = root.intern(s.as_ref()) {
+                res = res.and(Err(e));
+            }
+        }
+        res
+    }
+}
+
+/// A handle to an interner, the global pool by default, that strings
+/// can be added to with `Extend`, so builder style code can write
+/// `pool.extend(lines)`. Strings are interned in batches, and the
+/// global pool is locked once per batch rather than once per string.
+/// `Extend` can't fail, so a string that can't be interned is skipped,
+/// and the first error is kept until `take_error`.
+#[derive(Debug, Default)]
+pub struct Pool<I: Interner = Global> {
+    interner: I,
+    error: Option<I::Error>,
+}
+
+impl Pool {
+    /// A handle to the global pool
+    pub fn global() -> Self {
+        Pool::new(Global)
+    }
+}
+
+impl<I: Interner> Pool<I> {
+    /// The number of strings interned per batch
+    const BATCH: usize = 256;
+
+    pub fn new(interner: I) -> Self {
+        Pool {
+            interner,
+            error: None,
+        }
+    }
+
+    pub fn interner(&self) -> &I {
+        &self.interner
+    }
+
+    pub fn interner_mut(&mut self) -> &mut I {
+        &mut self.interner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.interner
+    }
+
+    /// Return the first error since the last call, if a string couldn't
+    /// be interned
+    pub fn take_error(&mut self) -> Result<(), I::Error> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+
+    fn extend_batched<S: AsRef<str>>(&mut self, iter: impl IntoIterator<Item = S>) {
+        let mut iter = iter.into_iter();
+        let mut batch = Vec::with_capacity(Self::BATCH);
+        loop {
+            batch.extend(iter.by_ref().take(Self::BATCH));
+            if batch.is_empty() {
+                break;
+            }
+            if let Err(e) = self.interner.intern_batch(&batch) {
+                self.error.get_or_insert(e);
+            }
+            batch.clear();
+        }
+    }
+}
+
+impl<'a, I: Interner> Extend<&'a str> for Pool<I> {
+    fn extend<T: IntoIterator<Item = &'a str>>(&mut self, iter: T) {
+        self.extend_batched(iter)
+    }
+}
+
+impl<I: Interner> Extend<String> for Pool<I> {
+    fn extend<T: IntoIterator<Item = String>>(&mut self, iter: T) {
+        self.extend_batched(iter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Written against any interner, as a library would be
+    fn intern_twice<I: Interner>(i: &mut I, s: &str) -> I::Key
+    where
+        I::Error: std::fmt::Debug,
+    {
+        assert!(i.get(s).is_none());
+        let k = i.intern(s).unwrap();
+        assert!(i.intern(s).unwrap() == k);
+        assert!(i.get(s) == Some(k));
+        assert_eq!(i.resolve(k), s);
+        k
+    }
+
+    #[test]
+    fn test_global() {
+        let k = intern_twice(&mut Global, "interner global");
+        assert_eq!(k, Str::try_from("interner global").unwrap());
+        assert!(Global.get("a").is_some());
+    }
+
+    #[test]
+    fn test_pool() {
+        let mut pool = Pool::global();
+        pool.extend((0..1000).map(|i| format!("interner pool {i}")));
+        pool.extend([
+            "interner pool a",
+            &"x".repeat(Str::MAX_LEN + 1),
+            "interner pool b",
+        ]);
+        assert!(matches!(pool.take_error(), Err(Error::TooLong { .. })));
+        assert!(pool.take_error().is_ok());
+        for s in [
+            "interner pool 0",
+            "interner pool 999",
+            "interner pool a",
+            "interner pool b",
+        ] {
+            assert!(Global.get(s).is_some());
+        }
+    }
+
+    #[cfg(feature = "lasso")]
+    #[test]
+    fn test_rodeo() {
+        let mut rodeo = ::lasso::Rodeo::default();
+        intern_twice(&mut rodeo, "interner rodeo");
+        let mut pool = Pool::new(rodeo);
+        pool.extend(["rodeo a", "rodeo b"]);
+        assert!(pool.take_error().is_ok());
+        assert!(pool.interner().get("rodeo b").is_some());
+    }
+}
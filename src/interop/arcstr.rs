@@ -0,0 +1,38 @@
+use crate::{Error, Result, Str};
+use ::arcstr::ArcStr;
+
+impl TryFrom<&ArcStr> for Str {
+    type Error = Error;
+
+    /// `ArcStr` literals are referenced in place as with
+    /// `Str::from_static`, other strings are copied into the pool.
+    fn try_from(s: &ArcStr) -> Result<Self> {
+        match ArcStr::as_static(s) {
+            Some(s) => Str::from_static(s),
+            None => Str::try_from(s.as_str()),
+        }
+    }
+}
+
+impl From<Str> for ArcStr {
+    /// `ArcStr` can only borrow static strings laid out at compile
+    /// time, so this copies the string into a new `ArcStr`.
+    fn from(s: Str) -> Self {
+        ArcStr::from(s.as_static())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arcstr() {
+        let a = ::arcstr::literal!("an arcstr literal that is referenced");
+        let s = Str::try_from(&a).unwrap();
+        assert_eq!(s.as_ptr(), a.as_ptr());
+        assert_eq!(ArcStr::from(s), a);
+        let b = ArcStr::from("a dynamic arcstr");
+        assert_eq!(&*Str::try_from(&b).unwrap(), "a dynamic arcstr");
+    }
+}
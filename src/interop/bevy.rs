@@ -0,0 +1,42 @@
+//! `Str` is reflected as an opaque value, like `String`, so components
+//! with interned names show up in inspectors and round trip through
+//! scenes, which serialize it with its serde implementation.
+
+use crate::Str;
+use bevy_reflect::{impl_reflect_opaque, ReflectDeserialize, ReflectSerialize};
+
+impl_reflect_opaque!((in hcstatic_str) Str(Clone, Debug, Hash, PartialEq, Serialize, Deserialize));
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serde::de::DeserializeSeed;
+    use bevy_reflect::{
+        serde::{ReflectDeserializer, ReflectSerializer},
+        FromReflect, PartialReflect, Reflect, TypePath, TypeRegistry,
+    };
+
+    #[derive(Reflect, Debug, PartialEq)]
+    struct Named {
+        name: Str,
+    }
+
+    #[test]
+    fn test_bevy() {
+        assert_eq!(Str::type_path(), "hcstatic_str::Str");
+        let t = Str::try_from("bevy name").unwrap();
+        let reflected = t.reflect_clone().unwrap();
+        assert_eq!(Str::from_reflect(&*reflected), Some(t));
+        let mut registry = TypeRegistry::default();
+        registry.register::<Named>();
+        let named = Named { name: t };
+        let json = serde_json::to_string(&ReflectSerializer::new(&named, &registry)).unwrap();
+        let mut de = serde_json::Deserializer::from_str(&json);
+        let back = ReflectDeserializer::new(&registry)
+            .deserialize(&mut de)
+            .unwrap();
+        assert_eq!(Named::from_reflect(&*back), Some(named));
+        let other = Str::try_from("other name").unwrap();
+        assert_eq!(t.reflect_partial_eq(&other), Some(false));
+    }
+}
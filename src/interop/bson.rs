@@ -0,0 +1,59 @@
+use crate::{Error, Result, Str};
+use ::bson::Bson;
+
+impl From<Str> for Bson {
+    fn from(s: Str) -> Self {
+        Bson::String(s.as_static().into())
+    }
+}
+
+impl TryFrom<&Bson> for Str {
+    type Error = Error;
+
+    /// Intern the value of a `Bson::String`. Values of other types
+    /// aren't converted.
+    fn try_from(b: &Bson) -> Result<Self> {
+        match b {
+            Bson::String(s) => Str::try_from(s.as_str()),
+            _ => Err(Error::Decode("not a BSON string")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::bson::doc;
+
+    #[derive(::serde::Deserialize)]
+    struct Doc {
+        kind: Str,
+        labels: Vec<Str>,
+    }
+
+    #[test]
+    fn test_bson() {
+        let t = Str::try_from("bson value").unwrap();
+        let b = Bson::from(t);
+        assert_eq!(b, Bson::String("bson value".into()));
+        assert_eq!(Str::try_from(&b).unwrap().as_raw(), t.as_raw());
+        assert!(matches!(
+            Str::try_from(&Bson::Int32(1)),
+            Err(Error::Decode(_))
+        ));
+        // Str fields are interned while deserializing documents, both
+        // from a Document and from raw bytes
+        let doc = doc! { "kind": "bson kind", "labels": ["bson a", "bson b"] };
+        let bytes = doc.to_vec().unwrap();
+        for d in [
+            ::bson::deserialize_from_document::<Doc>(doc).unwrap(),
+            ::bson::deserialize_from_slice::<Doc>(&bytes).unwrap(),
+        ] {
+            assert_eq!(&*d.kind, "bson kind");
+            assert_eq!(
+                d.labels,
+                ["bson a", "bson b"].map(|s| Str::try_from(s).unwrap())
+            );
+        }
+    }
+}
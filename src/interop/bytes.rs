@@ -0,0 +1,33 @@
+use crate::{Error, Result, Str};
+use ::bytes::Bytes;
+use std::str;
+
+impl TryFrom<&Bytes> for Str {
+    type Error = Error;
+
+    fn try_from(b: &Bytes) -> Result<Self> {
+        Str::try_from(str::from_utf8(b)?)
+    }
+}
+
+impl From<Str> for Bytes {
+    /// This borrows the interned bytes, it neither copies them nor
+    /// allocates a reference count.
+    fn from(s: Str) -> Self {
+        Bytes::from_static(s.as_static().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        let s = Str::try_from(&Bytes::from_static(b"bytes field")).unwrap();
+        assert_eq!(&*s, "bytes field");
+        let b = Bytes::from(s);
+        assert_eq!(b.as_ptr(), s.as_ptr());
+        assert!(Str::try_from(&Bytes::from_static(b"\xff")).is_err());
+    }
+}
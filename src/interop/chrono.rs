@@ -0,0 +1,49 @@
+use crate::{stackbuf::StackBuf, Error, Result, Str};
+use ::chrono::{DateTime, TimeZone};
+use std::fmt::{Display, Write};
+
+impl Str {
+    /// Format `t` with the strftime style `format`, such as
+    /// `"%Y-%m-%dT%H:%M"`, and intern the result. It is formatted on
+    /// the stack, so nothing is allocated, which pays off when
+    /// timestamps are bucketed and the same strings repeat. A format
+    /// chrono can't parse is `Error::Invalid`.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_timestamp<Tz: TimeZone>(t: &DateTime<Tz>, format: &str) -> Result<Str>
+    where
+        Tz::Offset: Display,
+    {
+        let mut buf = StackBuf::new();
+        write!(buf, "{}", t.format(format))
+            .map_err(|_| Error::Invalid("invalid timestamp format"))?;
+        buf.intern()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn test_intern_timestamp() {
+        let t = Utc.with_ymd_and_hms(2024, 3, 9, 17, 5, 42).unwrap();
+        let s = Str::intern_timestamp(&t, "%Y-%m-%dT%H:%M").unwrap();
+        assert_eq!(&*s, "2024-03-09T17:05");
+        let later = Utc.with_ymd_and_hms(2024, 3, 9, 17, 5, 59).unwrap();
+        assert_eq!(Str::intern_timestamp(&later, "%Y-%m-%dT%H:%M").unwrap(), s);
+        let t = t.with_timezone(&FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(
+            &*Str::intern_timestamp(&t, "%H:%M %z").unwrap(),
+            "18:05 +0100"
+        );
+        assert!(matches!(
+            Str::intern_timestamp(&t, "%Q"),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            Str::intern_timestamp(&t, &"%Y".repeat(100)),
+            Err(Error::TooLong { len: 400 })
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{Error, Result, Str};
+use ::compact_str::CompactString;
+
+impl TryFrom<&CompactString> for Str {
+    type Error = Error;
+
+    fn try_from(s: &CompactString) -> Result<Self> {
+        Str::try_from(s.as_str())
+    }
+}
+
+impl From<Str> for CompactString {
+    /// The interned bytes are static, so this borrows them rather
+    /// than copying.
+    fn from(s: Str) -> Self {
+        CompactString::const_new(s.as_static())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compact_str() {
+        let c = CompactString::from("a compact string that is not inline");
+        let s = Str::try_from(&c).unwrap();
+        assert_eq!(CompactString::from(s), c);
+        assert_eq!(CompactString::from(s).as_ptr(), s.as_ptr());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{Error, Result, Str, MAX_LEN};
+use ::encoding_rs::{CoderResult, Encoding};
+use std::str;
+
+/// Room past `MAX_LEN` for the decoder, which reports a full buffer
+/// once the space left might not hold its next output, not once it is
+/// actually full
+const HEADROOM: usize = 16;
+
+impl Str {
+    /// Decode `bytes` from `encoding`, for example Latin-1 or
+    /// Shift_JIS, and intern the result. Malformed input is replaced
+    /// with U+FFFD. The result is decoded on the stack, so nothing is
+    /// allocated unless it turns out to be over the limit, in which
+    /// case the rest is decoded only to report its length in
+    /// `Error::TooLong`.
+    pub fn from_encoding(bytes: &[u8], encoding: &'static Encoding) -> Result<Str> {
+        let mut decoder = encoding.new_decoder_without_bom_handling();
+        let mut buf = [0; MAX_LEN + HEADROOM];
+        // zero bytes are valid UTF-8
+        let dst = str::from_utf8_mut(&mut buf).unwrap();
+        let (res, read, written, _) = decoder.decode_to_str(bytes, dst, true);
+        match res {
+            CoderResult::InputEmpty if written <= MAX_LEN => Str::try_from(&dst[..written]),
+            CoderResult::InputEmpty => Err(Error::TooLong { len: written }),
+            CoderResult::OutputFull => {
+                let mut rest = String::with_capacity(
+                    decoder
+                        .max_utf8_buffer_length(bytes.len() - read)
+                        .unwrap_or(0),
+                );
+                let _ = decoder.decode_to_string(&bytes[read..], &mut rest, true);
+                let len = written + rest.len();
+                if len > MAX_LEN {
+                    return Err(Error::TooLong { len });
+                }
+                rest.insert_str(0, &dst[..written]);
+                Str::try_from(rest.as_str())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    #[test]
+    fn test_from_encoding() {
+        let t = Str::from_encoding(b"caf\xe9", WINDOWS_1252).unwrap();
+        assert_eq!(&*t, "café");
+        let t = Str::from_encoding(b"\x93\xfa\x96\x7b", SHIFT_JIS).unwrap();
+        assert_eq!(&*t, "日本");
+        assert!(matches!(
+            Str::from_encoding(&[0xe9; 200], WINDOWS_1252),
+            Err(Error::TooLong { len: 400 })
+        ));
+        // the decoder stops short of a full buffer, so exactly MAX_LEN
+        // bytes of output, ending in a two byte char, must still fit
+        let mut exact = vec![b'a'];
+        exact.extend([0xe9; 127]);
+        let t = Str::from_encoding(&exact, WINDOWS_1252).unwrap();
+        assert_eq!(t.len(), MAX_LEN);
+        assert!(t.ends_with("éé"));
+        let mut exact = vec![0xe9; 127];
+        exact.push(b'a');
+        assert_eq!(Str::from_encoding(&exact, WINDOWS_1252).unwrap().len(), 255);
+        assert!(matches!(
+            Str::from_encoding(&[0xe9; 128], WINDOWS_1252),
+            Err(Error::TooLong { len: 256 })
+        ));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{Result, Str};
+use ::itoa::{Buffer, Integer};
+
+impl Str {
+    /// Intern the decimal form of `n`, formatted on the stack, so
+    /// nothing is allocated. The integers from 0 to 999 are common
+    /// strings, found without hashing or locking.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_int(n: impl Integer) -> Result<Str> {
+        Str::try_from(Buffer::new().format(n))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern_int() {
+        assert_eq!(&*Str::intern_int(404u16).unwrap(), "404");
+        assert_eq!(Str::intern_int(7i8).unwrap(), Str::try_from("7").unwrap());
+        assert_eq!(&*Str::intern_int(-12i64).unwrap(), "-12");
+        assert_eq!(&*Str::intern_int(u128::MAX).unwrap(), u128::MAX.to_string());
+    }
+}
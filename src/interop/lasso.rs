@@ -0,0 +1,60 @@
+use crate::{Interner, Result, Str, ROOT};
+use ::lasso::{Key, LassoError, Rodeo, RodeoReader};
+use std::hash::BuildHasher;
+
+/// Intern every string in `rodeo` into the global pool under a single
+/// lock. The result is indexed by `Key::into_usize`, so the `Str` for
+/// a key `k` is `res[k.into_usize()]`.
+pub fn import_rodeo<K: Key, S: BuildHasher>(rodeo: &Rodeo<K, S>) -> Result<Vec<Str>> {
+    let mut root = ROOT.lock();
+    root.all.try_reserve(rodeo.len())?;
+    rodeo.strings().map(|s| root.intern(s)).collect()
+}
+
+/// Copy every string in the global pool into a new `RodeoReader`, in
+/// the order they were interned, so keys are the same from run to run
+/// if the strings are interned in the same order. The built in common
+/// strings are not stored in the pool, so they are not included.
+pub fn export_rodeo() -> RodeoReader {
+    let root = ROOT.lock();
+    let mut rodeo = Rodeo::with_capacity(::lasso::Capacity::for_strings(root.all.len()));
+    for s in root.strings() {
+        rodeo.get_or_intern_static(s.as_static());
+    }
+    rodeo.into_reader()
+}
+
+impl<K: Key, S: BuildHasher + Clone> Interner for Rodeo<K, S> {
+    type Key = K;
+    type Error = LassoError;
+
+    fn intern(&mut self, s: &str) -> Result<K, LassoError> {
+        self.try_get_or_intern(s)
+    }
+
+    fn get(&self, s: &str) -> Option<K> {
+        Rodeo::get(self, s)
+    }
+
+    fn resolve(&self, key: K) -> &str {
+        Rodeo::resolve(self, &key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rodeo() {
+        let mut rodeo = Rodeo::default();
+        let k0 = rodeo.get_or_intern("rodeo a");
+        let k1 = rodeo.get_or_intern("rodeo b");
+        let strs = import_rodeo(&rodeo).unwrap();
+        assert_eq!(&*strs[k0.into_usize()], "rodeo a");
+        assert_eq!(&*strs[k1.into_usize()], "rodeo b");
+        let reader = export_rodeo();
+        let key = |s| reader.get(s).unwrap().into_usize();
+        assert!(key("rodeo a") < key("rodeo b"));
+    }
+}
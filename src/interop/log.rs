@@ -0,0 +1,33 @@
+//! `Str` is a string value in the key-value API of `log`, so it can be
+//! passed as `log::info!(name = t; "...")`. The value borrows the
+//! interned bytes, so nothing is copied, and sinks see a plain string.
+
+use crate::Str;
+use ::log::kv::{ToValue, Value};
+
+impl ToValue for Str {
+    fn to_value(&self) -> Value<'_> {
+        Value::from(self.as_static())
+    }
+}
+
+impl From<Str> for Value<'_> {
+    fn from(t: Str) -> Self {
+        Value::from(t.as_static())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_kv() {
+        let t = Str::try_from("log kv value").unwrap();
+        let v = t.to_value();
+        assert_eq!(v.to_borrowed_str(), Some("log kv value"));
+        assert_eq!(v.to_string(), "log kv value");
+        let v: Value<'static> = t.into();
+        assert_eq!(v.to_borrowed_str().map(str::as_ptr), Some(t.as_ptr()));
+    }
+}
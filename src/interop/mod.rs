@@ -0,0 +1,65 @@
+//! Conversions to and from other string types, each behind a
+//! feature named after the crate it integrates with.
+
+#[cfg(feature = "string_cache")]
+mod string_cache;
+
+#[cfg(feature = "string_cache")]
+pub use self::string_cache::intern_atoms;
+
+#[cfg(feature = "lasso")]
+mod lasso;
+
+#[cfg(feature = "lasso")]
+pub use self::lasso::{export_rodeo, import_rodeo};
+
+#[cfg(feature = "compact_str")]
+mod compact_str;
+
+#[cfg(feature = "smol_str")]
+mod smol_str;
+
+#[cfg(feature = "arcstr")]
+mod arcstr;
+
+#[cfg(feature = "bytes")]
+mod bytes;
+
+#[cfg(feature = "encoding_rs")]
+mod encoding_rs;
+
+#[cfg(feature = "redis")]
+mod redis;
+
+#[cfg(feature = "bson")]
+mod bson;
+
+#[cfg(feature = "bevy")]
+mod bevy;
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck;
+
+#[cfg(feature = "valuable")]
+mod valuable;
+
+#[cfg(feature = "log-kv")]
+mod log;
+
+#[cfg(feature = "ustr")]
+mod ustr;
+
+#[cfg(feature = "uuid")]
+mod uuid;
+
+#[cfg(feature = "itoa")]
+mod itoa;
+
+#[cfg(feature = "ryu")]
+mod ryu;
+
+#[cfg(feature = "chrono")]
+mod chrono;
+
+#[cfg(feature = "ustr")]
+pub use self::ustr::import_ustr_cache;
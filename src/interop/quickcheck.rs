@@ -0,0 +1,65 @@
+//! Arbitrary strings of any length up to `Str::MAX_LEN`, for property
+//! tests. Generated strings are interned, so a long run of properties
+//! grows the pool with every string it tries.
+
+use crate::Str;
+use ::quickcheck::{Arbitrary, Gen};
+
+impl Arbitrary for Str {
+    /// An arbitrary `String`, cut at the last char boundary that fits
+    fn arbitrary(g: &mut Gen) -> Self {
+        Str::intern_truncated(&String::arbitrary(g)).expect("interning an arbitrary string")
+    }
+
+    /// Shorter and shorter prefixes, from the empty string up to the
+    /// string without its last char
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let s = self.as_static();
+        let mut last = None;
+        let mut step = s.len();
+        Box::new(std::iter::from_fn(move || {
+            while step > 0 {
+                let mut end = s.len() - step;
+                step /= 2;
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                if last.is_none_or(|last| end > last) {
+                    last = Some(end);
+                    return Some(Str::try_from(&s[..end]).expect("a prefix of an interned string"));
+                }
+            }
+            None
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::quickcheck::{quickcheck, TestResult};
+
+    quickcheck! {
+        fn prop_arbitrary_str(t: Str) -> bool {
+            t.len() <= Str::MAX_LEN && Str::try_from(t.as_static()).unwrap() == t
+        }
+
+        fn prop_shrink_truncates(t: Str) -> TestResult {
+            let shrunk: Vec<Str> = t.shrink().collect();
+            TestResult::from_bool(
+                shrunk.windows(2).all(|w| w[0].len() < w[1].len())
+                    && shrunk.iter().all(|u| u.len() < t.len() && t.starts_with(&**u)),
+            )
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        let t = Str::try_from("quickcheck").unwrap();
+        let shrunk: Vec<&str> = t.shrink().map(|t| t.as_static()).collect();
+        assert_eq!(shrunk, ["", "quick", "quickche", "quickchec"]);
+        assert_eq!(Str::try_from("").unwrap().shrink().count(), 0);
+        let mut g = Gen::new(1000);
+        assert!((0..20).all(|_| Str::arbitrary(&mut g).len() <= Str::MAX_LEN));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::Str;
+use ::redis::{FromRedisValue, ParsingError, RedisWrite, ToRedisArgs, ToSingleRedisArg, Value};
+
+impl ToRedisArgs for Str {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        out.write_arg(self.as_bytes());
+    }
+}
+
+impl ToSingleRedisArg for Str {}
+
+impl FromRedisValue for Str {
+    /// Strings are interned straight out of the reply, so reading a
+    /// key or label that is already interned allocates nothing.
+    /// Numbers aren't converted, unlike for `String`.
+    fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
+        let v = match v {
+            Value::Attribute { data, .. } => data,
+            v => v,
+        };
+        let t = match v {
+            Value::BulkString(bytes) => Str::from_utf8(bytes),
+            Value::SimpleString(s) | Value::VerbatimString { text: s, .. } => {
+                Str::try_from(s.as_str())
+            }
+            Value::Okay => Str::try_from("OK"),
+            v => return Err(format!("{v:?} is not a string").into()),
+        };
+        t.map_err(|e| e.to_string().into())
+    }
+
+    fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        Self::from_redis_value_ref(&v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redis() {
+        let t = Str::try_from("redis key").unwrap();
+        assert_eq!(t.to_redis_args(), vec![b"redis key".to_vec()]);
+        let v = Value::BulkString(b"redis key".to_vec());
+        assert_eq!(Str::from_redis_value_ref(&v).unwrap().as_raw(), t.as_raw());
+        let v = Value::SimpleString("redis label".into());
+        assert_eq!(&*Str::from_redis_value(v).unwrap(), "redis label");
+        let v: Vec<Str> = FromRedisValue::from_redis_value(Value::Array(vec![
+            Value::BulkString(b"a".to_vec()),
+            Value::BulkString(b"b".to_vec()),
+        ]))
+        .unwrap();
+        assert_eq!(v, ["a", "b"].map(|s| Str::try_from(s).unwrap()));
+        assert!(Str::from_redis_value(Value::Int(1)).is_err());
+        assert!(Str::from_redis_value(Value::BulkString(vec![0xff])).is_err());
+        assert!(Str::from_redis_value(Value::BulkString(vec![b'x'; 256])).is_err());
+    }
+}
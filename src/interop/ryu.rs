@@ -0,0 +1,29 @@
+use crate::{Result, Str};
+use ::ryu::{Buffer, Float};
+
+impl Str {
+    /// Intern the shortest decimal form of `x` that reads back as the
+    /// same float, such as `0.25` or `1e-7`, formatted on the stack, so
+    /// nothing is allocated. Non finite values are `NaN`, `inf` and
+    /// `-inf`.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_float(x: impl Float) -> Result<Str> {
+        Str::try_from(Buffer::new().format(x))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern_float() {
+        assert_eq!(&*Str::intern_float(0.25).unwrap(), "0.25");
+        assert_eq!(&*Str::intern_float(1.0f32).unwrap(), "1.0");
+        assert_eq!(&*Str::intern_float(1e-7).unwrap(), "1e-7");
+        assert_eq!(&*Str::intern_float(f64::NEG_INFINITY).unwrap(), "-inf");
+        let t = Str::intern_float(2.5).unwrap();
+        assert_eq!(t, Str::try_from("2.5").unwrap());
+        assert_eq!(t.parse::<f64>().unwrap(), 2.5);
+    }
+}
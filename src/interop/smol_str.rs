@@ -0,0 +1,29 @@
+use crate::{Error, Result, Str};
+use ::smol_str::SmolStr;
+
+impl TryFrom<&SmolStr> for Str {
+    type Error = Error;
+
+    fn try_from(s: &SmolStr) -> Result<Self> {
+        Str::try_from(s.as_str())
+    }
+}
+
+impl From<Str> for SmolStr {
+    fn from(s: Str) -> Self {
+        SmolStr::new_static(s.as_static())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_smol_str() {
+        let t = SmolStr::new("a smol string that is too long to be inline");
+        let s = Str::try_from(&t).unwrap();
+        assert_eq!(SmolStr::from(s), t);
+        assert_eq!(SmolStr::from(s).as_ptr(), s.as_ptr());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{Error, Result, Str, ROOT};
+use ::string_cache::{Atom, StaticAtomSet};
+
+impl<S: StaticAtomSet> TryFrom<&Atom<S>> for Str {
+    type Error = Error;
+
+    fn try_from(a: &Atom<S>) -> Result<Self> {
+        Str::try_from(&**a)
+    }
+}
+
+impl<S: StaticAtomSet> TryFrom<Atom<S>> for Str {
+    type Error = Error;
+
+    fn try_from(a: Atom<S>) -> Result<Self> {
+        Str::try_from(&*a)
+    }
+}
+
+impl<S: StaticAtomSet> From<Str> for Atom<S> {
+    fn from(s: Str) -> Self {
+        Atom::from(s.as_static())
+    }
+}
+
+/// Intern every atom in `atoms` under a single lock, returning the
+/// handles in the same order. This is meant for migrating existing
+/// atom tables over to `Str`.
+pub fn intern_atoms<'a, S: StaticAtomSet + 'a>(
+    atoms: impl IntoIterator<Item = &'a Atom<S>>,
+) -> Result<Vec<Str>> {
+    let mut root = ROOT.lock();
+    atoms.into_iter().map(|a| root.intern(a)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::string_cache::DefaultAtom;
+
+    #[test]
+    fn test_atoms() {
+        let atoms: Vec<DefaultAtom> = ["atom a", "atom b"]
+            .into_iter()
+            .map(DefaultAtom::from)
+            .collect();
+        let strs = intern_atoms(&atoms).unwrap();
+        assert_eq!(&*strs[1], "atom b");
+        assert_eq!(Str::try_from(&atoms[0]).unwrap().as_raw(), strs[0].as_raw());
+        assert_eq!(DefaultAtom::from(strs[1]), atoms[1]);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{Error, Result, Str, ROOT};
+use ::ustr::Ustr;
+
+impl TryFrom<Ustr> for Str {
+    type Error = Error;
+
+    /// `Ustr`s live forever too, so strings long enough are referenced
+    /// in place as with `Str::from_static` instead of being copied.
+    fn try_from(u: Ustr) -> Result<Self> {
+        Str::from_static(u.as_str())
+    }
+}
+
+impl TryFrom<&Ustr> for Str {
+    type Error = Error;
+
+    fn try_from(u: &Ustr) -> Result<Self> {
+        Str::try_from(*u)
+    }
+}
+
+impl From<Str> for Ustr {
+    /// This interns the string in ustr's cache, copying it the first
+    /// time.
+    fn from(t: Str) -> Self {
+        Ustr::from(t.as_static())
+    }
+}
+
+/// Intern every string in ustr's global cache under a single lock,
+/// referencing them in place where they are long enough, and return
+/// the number interned. This is meant for migrating a program whose
+/// dependencies fill the ustr cache over to `Str`. Strings longer
+/// than `Str::MAX_LEN` can't be interned and are skipped.
+pub fn import_ustr_cache() -> Result<usize> {
+    let strs: Vec<&'static str> = ::ustr::string_cache_iter()
+        .filter(|s| s.len() <= Str::MAX_LEN)
+        .collect();
+    let mut root = ROOT.lock();
+    root.all.try_reserve(strs.len())?;
+    for s in &strs {
+        root.intern_static(s)?;
+    }
+    Ok(strs.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ustr() {
+        let u = Ustr::from("a ustr long enough to be referenced in place");
+        let t = Str::try_from(u).unwrap();
+        assert_eq!(t.as_ptr(), u.as_ptr());
+        assert_eq!(Str::try_from(&u).unwrap().as_raw(), t.as_raw());
+        assert_eq!(
+            &*Str::try_from(Ustr::from("short ustr")).unwrap(),
+            "short ustr"
+        );
+        let v: Ustr = Str::try_from("ustr from str").unwrap().into();
+        assert_eq!(v, "ustr from str");
+        Ustr::from("ustr cached");
+        Ustr::from(&"u".repeat(Str::MAX_LEN + 1));
+        assert!(import_ustr_cache().unwrap() >= 3);
+        assert!(Str::try_from("ustr cached").is_ok());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{Result, Str};
+use ::uuid::{fmt::Simple, Uuid};
+
+impl Str {
+    /// Intern the hyphenated lower case form of `uuid`, as `to_string`
+    /// would give, formatted on the stack, so nothing is allocated
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_uuid(uuid: &Uuid) -> Result<Str> {
+        let mut buf = Uuid::encode_buffer();
+        Str::try_from(&*uuid.hyphenated().encode_lower(&mut buf))
+    }
+
+    /// Intern `uuid` as 32 lower case hex digits without hyphens
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_uuid_simple(uuid: &Uuid) -> Result<Str> {
+        let mut buf = [0; Simple::LENGTH];
+        Str::try_from(&*uuid.simple().encode_lower(&mut buf))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern_uuid() {
+        let uuid = Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        let t = Str::intern_uuid(&uuid).unwrap();
+        assert_eq!(&*t, uuid.to_string());
+        assert_eq!(&*t, "67e55044-10b1-426f-9247-bb680e5fe0c8");
+        let t = Str::intern_uuid_simple(&uuid).unwrap();
+        assert_eq!(&*t, "67e5504410b1426f9247bb680e5fe0c8");
+    }
+}
@@ -0,0 +1,43 @@
+//! `tracing::Value` is sealed, so it can't be implemented for `Str`,
+//! but tracing records any `Valuable` with `field::valuable` when it
+//! is built with `tracing_unstable`, and `Str` is one that is visited
+//! as a plain string. Without that, `t.as_static()` records the
+//! string without copying it.
+
+use crate::Str;
+use ::valuable::{Valuable, Value, Visit};
+
+impl Valuable for Str {
+    fn as_value(&self) -> Value<'_> {
+        Value::String(self.as_static())
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        visit.visit_value(self.as_value())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Collect(Vec<String>);
+
+    impl Visit for Collect {
+        fn visit_value(&mut self, value: Value<'_>) {
+            if let Value::String(s) = value {
+                self.0.push(s.into())
+            }
+        }
+    }
+
+    #[test]
+    fn test_valuable() {
+        let t = Str::try_from("valuable field").unwrap();
+        assert!(matches!(t.as_value(), Value::String("valuable field")));
+        let v = vec![t, Str::try_from("another").unwrap()];
+        let mut c = Collect(Vec::new());
+        v.visit(&mut c);
+        assert_eq!(c.0, ["valuable field", "another"]);
+    }
+}
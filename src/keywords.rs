@@ -0,0 +1,86 @@
+//! The `declare_keywords!` macro
+
+/// Declare an enum of keywords, each mapped to an interned `Str`.
+///
+/// ```
+/// hcstatic_str::declare_keywords! {
+///     pub enum Keyword {
+///         Fn = "fn",
+///         Let = "let",
+///     }
+/// }
+///
+/// let s = hcstatic_str::Str::try_from("let").unwrap();
+/// assert_eq!(Keyword::from_str(s), Some(Keyword::Let));
+/// assert_eq!(&*Keyword::Fn.as_str_interned(), "fn");
+/// ```
+///
+/// The enum name and visibility may be left off, in which case a
+/// `pub enum Keyword` is declared. Besides the enum, this generates
+///
+/// * `ALL`, every keyword in declaration order
+/// * `as_str`, the keyword as a `&'static str`
+/// * `as_str_interned`, the keyword as a `Str`, interned the first
+///   time any keyword of the enum is used
+/// * `from_str`, which maps a `Str` back to the keyword by comparing
+///   pointers rather than bytes
+#[macro_export]
+macro_rules! declare_keywords {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($var:ident = $lit:literal),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        $vis enum $name {
+            $($var),*
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$var),*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$var => $lit),*
+                }
+            }
+
+            // the interned keywords in declaration order, and sorted by
+            // address for from_str
+            #[allow(clippy::type_complexity)]
+            fn interned() -> &'static (
+                ::std::vec::Vec<$crate::Str>,
+                ::std::vec::Vec<(::std::num::NonZeroUsize, $name)>,
+            ) {
+                static TABLE: $crate::__private::Lazy<(
+                    ::std::vec::Vec<$crate::Str>,
+                    ::std::vec::Vec<(::std::num::NonZeroUsize, $name)>,
+                )> = $crate::__private::Lazy::new(|| {
+                    let strs: ::std::vec::Vec<$crate::Str> = $name::ALL
+                        .iter()
+                        .map(|k| $crate::Str::from_static(k.as_str()).expect("keyword is too long"))
+                        .collect();
+                    let mut by_addr: ::std::vec::Vec<_> =
+                        strs.iter().map(|s| s.as_raw()).zip($name::ALL.iter().copied()).collect();
+                    by_addr.sort_by_key(|(a, _)| *a);
+                    (strs, by_addr)
+                });
+                &TABLE
+            }
+
+            pub fn as_str_interned(self) -> $crate::Str {
+                Self::interned().0[self as usize]
+            }
+
+            pub fn from_str(s: $crate::Str) -> ::std::option::Option<$name> {
+                let by_addr = &Self::interned().1;
+                by_addr
+                    .binary_search_by_key(&s.as_raw(), |(a, _)| *a)
+                    .ok()
+                    .map(|i| by_addr[i].1)
+            }
+        }
+    };
+    ($($var:ident = $lit:literal),* $(,)?) => {
+        $crate::declare_keywords! {
+            pub enum Keyword { $($var = $lit),* }
+        }
+    };
+}
@@ -0,0 +1,250 @@
+//! Interned BCP 47 language tags
+
+use crate::{stackbuf::StackBuf, Error, Result, Str};
+use std::{fmt, ops::Deref};
+
+/// A well formed BCP 47 language tag, such as `en-US` or
+/// `zh-Hant-TW`, interned in its canonical case: the script subtag is
+/// title case, the region upper case, and everything else lower case,
+/// so `en-us` and `EN-US` are the same `LangTag` as `en-US`. The tag
+/// is checked for well formedness, not against the subtag registry,
+/// and grandfathered tags other than private use `x-` tags are
+/// rejected.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LangTag(Str);
+
+/// Where a subtag before the first extension can appear, in order
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Kind {
+    Language,
+    ExtLang,
+    Script,
+    Region,
+    Variant,
+}
+
+fn kind(sub: &[u8]) -> Result<Kind> {
+    let alpha = sub.iter().all(u8::is_ascii_alphabetic);
+    let digits = sub.iter().all(u8::is_ascii_digit);
+    match sub.len() {
+        3 if alpha => Ok(Kind::ExtLang),
+        4 if alpha => Ok(Kind::Script),
+        2 if alpha => Ok(Kind::Region),
+        3 if digits => Ok(Kind::Region),
+        4 if sub[0].is_ascii_digit() => Ok(Kind::Variant),
+        5..=8 => Ok(Kind::Variant),
+        _ => Err(Error::Invalid("language tag has a malformed subtag")),
+    }
+}
+
+/// Check `s` and call `f` with each subtag, split off its dash, and
+/// its kind, which is None from the first singleton on
+fn walk<'a>(s: &'a str, mut f: impl FnMut(&'a str, Option<Kind>)) -> Result<()> {
+    let mut prev = None;
+    // set once a singleton has been seen, to the minimum length of the
+    // subtags after it
+    let mut extension: Option<usize> = None;
+    let mut need = false;
+    for (i, sub) in s.split('-').enumerate() {
+        let b = sub.as_bytes();
+        if b.is_empty() || b.len() > 8 || !b.iter().all(u8::is_ascii_alphanumeric) {
+            return Err(Error::Invalid(
+                "language tag subtags must be 1 to 8 letters or digits",
+            ));
+        }
+        if b.len() == 1 && extension != Some(1) {
+            if i == 0 && !b.eq_ignore_ascii_case(b"x") {
+                return Err(Error::Invalid("language tag must start with a language"));
+            }
+            if need {
+                return Err(Error::Invalid("language tag has an empty extension"));
+            }
+            extension = Some(if b.eq_ignore_ascii_case(b"x") { 1 } else { 2 });
+            need = true;
+            f(sub, None);
+            continue;
+        }
+        if let Some(min) = extension {
+            if b.len() < min {
+                return Err(Error::Invalid("language tag has a malformed subtag"));
+            }
+            need = false;
+            f(sub, None);
+            continue;
+        }
+        let k = if i == 0 {
+            if !b.iter().all(u8::is_ascii_alphabetic) || b.len() == 4 {
+                return Err(Error::Invalid("language tag must start with a language"));
+            }
+            Kind::Language
+        } else {
+            kind(b)?
+        };
+        let repeats = matches!(k, Kind::ExtLang | Kind::Variant);
+        match prev {
+            Some(p) if p > k || (p == k && !repeats) => {
+                return Err(Error::Invalid("language tag subtags are out of order"))
+            }
+            _ if k == Kind::ExtLang && !matches!(prev, Some(Kind::Language | Kind::ExtLang)) => {
+                return Err(Error::Invalid("language tag subtags are out of order"))
+            }
+            _ => (),
+        }
+        prev = Some(k);
+        f(sub, Some(k));
+    }
+    if need {
+        return Err(Error::Invalid("language tag has an empty extension"));
+    }
+    Ok(())
+}
+
+fn canonical(sub: &str, kind: Option<Kind>) -> impl Iterator<Item = char> + '_ {
+    sub.bytes().enumerate().map(move |(i, b)| match kind {
+        Some(Kind::Script) if i == 0 => b.to_ascii_uppercase() as char,
+        Some(Kind::Region) => b.to_ascii_uppercase() as char,
+        _ => b.to_ascii_lowercase() as char,
+    })
+}
+
+impl LangTag {
+    /// The interned tag
+    pub fn get(self) -> Str {
+        self.0
+    }
+
+    fn find(self, kind: Kind) -> Option<&'static str> {
+        let mut found = None;
+        // the tag was checked when it was interned
+        let _ = walk(self.0.as_static(), |sub, k| {
+            if k == Some(kind) {
+                found = found.or(Some(sub));
+            }
+        });
+        found
+    }
+
+    /// The primary language subtag, or `x` for a private use tag
+    pub fn language(self) -> &'static str {
+        self.0.as_static().split('-').next().unwrap_or("")
+    }
+
+    /// The script subtag, if there is one
+    pub fn script(self) -> Option<&'static str> {
+        self.find(Kind::Script)
+    }
+
+    /// The region subtag, if there is one
+    pub fn region(self) -> Option<&'static str> {
+        self.find(Kind::Region)
+    }
+}
+
+impl TryFrom<&str> for LangTag {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        let mut same = true;
+        walk(s, |sub, k| same &= canonical(sub, k).eq(sub.chars()))?;
+        if same {
+            return Ok(LangTag(Str::try_from(s)?));
+        }
+        let mut buf = StackBuf::new();
+        let mut first = true;
+        walk(s, |sub, k| {
+            if !first {
+                buf.push('-');
+            }
+            first = false;
+            canonical(sub, k).for_each(|c| buf.push(c));
+        })?;
+        Ok(LangTag(buf.intern()?))
+    }
+}
+
+impl TryFrom<Str> for LangTag {
+    type Error = Error;
+
+    fn try_from(s: Str) -> Result<Self> {
+        LangTag::try_from(s.as_static())
+    }
+}
+
+impl From<LangTag> for Str {
+    fn from(t: LangTag) -> Str {
+        t.0
+    }
+}
+
+impl Deref for LangTag {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.0.as_static()
+    }
+}
+
+impl fmt::Debug for LangTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for LangTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tag(s: &str) -> LangTag {
+        LangTag::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn test_langtag() {
+        assert_eq!(tag("en-us"), tag("en-US"));
+        assert_eq!(tag("EN-us").get(), Str::try_from("en-US").unwrap());
+        let t = tag("ZH-hant-tw");
+        assert_eq!(&*t, "zh-Hant-TW");
+        assert_eq!(
+            (t.language(), t.script(), t.region()),
+            ("zh", Some("Hant"), Some("TW"))
+        );
+        assert_eq!(&*tag("es-419"), "es-419");
+        assert_eq!(&*tag("sl-ROZAJ-biske-1994"), "sl-rozaj-biske-1994");
+        assert_eq!(&*tag("zh-YUE-hk"), "zh-yue-HK");
+        // extensions and private use are lower case, even where they
+        // look like a script or region
+        let t = tag("de-DE-u-CO-PHONEBK-x-Latn-AB");
+        assert_eq!(&*t, "de-DE-u-co-phonebk-x-latn-ab");
+        assert_eq!(t.script(), None);
+        assert_eq!(&*tag("X-Private"), "x-private");
+        assert_eq!(tag("fr").region(), None);
+        for bad in [
+            "",
+            "en-",
+            "-en",
+            "e",
+            "1en",
+            "en_US",
+            "en-US-Latn",
+            "en-toolongsubtag",
+            "en-u",
+            "en-u-x-a",
+            "en-a-b",
+            "en-US-US",
+            "en-ab-cde",
+            "abcd",
+            "én",
+        ] {
+            assert!(
+                matches!(LangTag::try_from(bad), Err(Error::Invalid(_))),
+                "{bad}"
+            );
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Strings that are only interned if they are used
+
+use crate::{Result, Str, MAX_LEN};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::OnceLock,
+};
+
+#[derive(Clone)]
+enum Source<'a> {
+    Static(&'static str),
+    Borrowed(&'a str),
+    Owned(Box<str>),
+}
+
+/// A string that is interned the first time its handle is needed, and
+/// keeps the handle from then on. Values that are made often but only
+/// sometimes used as keys, such as fields parsed from every request,
+/// can then skip the hash and the pool lock unless they get that far.
+/// Equality and hashing are those of the string, so a `LazyStr` works
+/// as a key without being interned.
+///
+/// Statics, which can't call `Str::try_from`, are made with the const
+/// `LazyStr::new`, which checks the literal is short enough when the
+/// static is compiled.
+///
+/// ```
+/// use hcstatic_str::{intern, LazyStr};
+///
+/// static NAME: LazyStr = LazyStr::new("plugin name");
+///
+/// assert_eq!(&**NAME, "plugin name");
+/// let field = LazyStr::from(String::from("lazy field"));
+/// assert!(!field.is_interned());
+/// assert_eq!(field.try_get().unwrap(), intern("lazy field"));
+/// ```
+#[derive(Clone)]
+pub struct LazyStr<'a> {
+    s: Source<'a>,
+    t: OnceLock<Str>,
+}
+
+impl LazyStr<'static> {
+    /// A string literal, interned as `Str::from_static` interns it
+    ///
+    /// # Panics
+    ///
+    /// If `s` is longer than `Str::MAX_LEN` bytes, which is a compile
+    /// error in a static.
+    pub const fn new(s: &'static str) -> LazyStr<'static> {
+        assert!(s.len() <= MAX_LEN, "the string is too long to intern");
+        LazyStr {
+            s: Source::Static(s),
+            t: OnceLock::new(),
+        }
+    }
+}
+
+impl LazyStr<'_> {
+    /// The string, without interning it
+    pub fn as_str(&self) -> &str {
+        match &self.s {
+            Source::Static(s) => s,
+            Source::Borrowed(s) => s,
+            Source::Owned(s) => s,
+        }
+    }
+
+    /// The interned string, interning it if this is the first use
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_get(&self) -> Result<Str> {
+        if let Some(t) = self.t.get() {
+            return Ok(*t);
+        }
+        let t = match &self.s {
+            Source::Static(s) => Str::from_static(s)?,
+            _ => Str::try_from(self.as_str())?,
+        };
+        Ok(*self.t.get_or_init(|| t))
+    }
+
+    /// Like `try_get`, for strings known to be short enough
+    ///
+    /// # Panics
+    ///
+    /// If the string can't be interned, as `intern` does.
+    #[track_caller]
+    pub fn get(&self) -> Str {
+        match self.try_get() {
+            Ok(t) => t,
+            Err(e) => panic!("can't intern {:?}: {e}", self.as_str()),
+        }
+    }
+
+    /// Whether the string has been interned yet
+    pub fn is_interned(&self) -> bool {
+        self.t.get().is_some()
+    }
+}
+
+impl<'a> From<&'a str> for LazyStr<'a> {
+    fn from(s: &'a str) -> Self {
+        LazyStr {
+            s: Source::Borrowed(s),
+            t: OnceLock::new(),
+        }
+    }
+}
+
+impl From<String> for LazyStr<'_> {
+    fn from(s: String) -> Self {
+        LazyStr {
+            s: Source::Owned(s.into()),
+            t: OnceLock::new(),
+        }
+    }
+}
+
+impl From<Str> for LazyStr<'_> {
+    fn from(t: Str) -> Self {
+        LazyStr {
+            s: Source::Static(t.as_static()),
+            t: OnceLock::from(t),
+        }
+    }
+}
+
+/// Interns the string, panicking as `get` does if it can't be
+impl Deref for LazyStr<'_> {
+    type Target = Str;
+
+    fn deref(&self) -> &Str {
+        self.get();
+        self.t.get().unwrap()
+    }
+}
+
+impl PartialEq for LazyStr<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.t.get(), other.t.get()) {
+            (Some(t), Some(u)) => t == u,
+            _ => self.as_str() == other.as_str(),
+        }
+    }
+}
+
+impl Eq for LazyStr<'_> {}
+
+impl Hash for LazyStr<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for LazyStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for LazyStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Global, Interner};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_lazy_str() {
+        let owned = LazyStr::from(String::from("lazy owned"));
+        let line = String::from("lazy borrowed");
+        let borrowed = LazyStr::from(line.as_str());
+        // the cached handle doesn't change how a key hashes
+        #[allow(clippy::mutable_key_type)]
+        let keys: HashSet<LazyStr> = [owned.clone(), borrowed.clone()].into();
+        assert!(keys.contains(&LazyStr::from("lazy owned")));
+        // comparing and hashing don't intern
+        assert!(!owned.is_interned() && Global.get("lazy owned").is_none());
+        assert_eq!(owned.as_str(), "lazy owned");
+        let t = borrowed.try_get().unwrap();
+        assert!(borrowed.is_interned());
+        assert_eq!((borrowed.get(), *borrowed), (t, t));
+        assert_eq!(borrowed, LazyStr::from(t));
+        assert_ne!(borrowed, owned);
+        let long = LazyStr::from("x".repeat(MAX_LEN + 1));
+        assert!(matches!(long.try_get(), Err(crate::Error::TooLong { .. })));
+        assert!(!long.is_interned());
+        static LIT: LazyStr = LazyStr::new("lazy literal");
+        assert_eq!(format!("{LIT} {LIT:?}"), "lazy literal \"lazy literal\"");
+        assert_eq!(&**LIT, "lazy literal");
+    }
+}
@@ -0,0 +1,160 @@
+//! The storage used with the `forbid-unsafe` feature.
+//!
+//! Packing strings into chunks, and reading them back from a one word
+//! handle, needs unsafe code. With `forbid-unsafe` every string is
+//! instead leaked in its own allocation, and a handle is a reference to
+//! a leaked `&'static str`, so the crate contains no unsafe code at all
+//! and is built with `#![forbid(unsafe_code)]`, for downstreams whose
+//! audit policies require that of their dependencies. The API is the
+//! same, except that `Str::from_raw`, which can't be safe, doesn't
+//! exist; use `Str::try_from_raw` instead. The price is two small
+//! allocations per string, and their malloc overhead, instead of
+//! `len + 1` bytes.
+//!
+//! Chunks are kept as a unit of accounting. Each has the size it would
+//! have in packed storage, and entries are laid out in it as they would
+//! be there, so `set_memory_limit`, `stats` and `chunk_report` see
+//! what the packed storage would use, not what the allocator hands
+//! out. `single-threaded` and `shared-pool` need unsafe code, so they
+//! have no effect: the pool uses a mutex, and every copy of the crate
+//! keeps its own pool.
+
+#[cfg(feature = "ids")]
+use crate::sync::{Lazy, Mutex};
+use crate::{common, Result, Root, Str, MAX_LEN, STATIC_RECORD_LEN, TRAILER_LEN};
+#[cfg(feature = "ids")]
+use fxhash::FxHashMap;
+use std::num::NonZeroUsize;
+
+/// The id of every string in the pool, by handle. Strings that are
+/// rolled back keep their entry, but their handles are never handed
+/// out.
+#[cfg(feature = "ids")]
+pub(crate) static IDS: Lazy<Mutex<FxHashMap<usize, u32>>> =
+    Lazy::new(|| Mutex::new(Default::default()));
+
+pub(crate) struct Chunk {
+    /// the size the chunk would have in packed storage
+    pub(crate) capacity: usize,
+    /// the number of bytes the entries would take in packed storage
+    pub(crate) written: usize,
+    /// the number of string bytes stored in the chunk
+    pub(crate) payload: usize,
+    /// the strings in the chunk, each with the offset of its entry
+    strings: Vec<(usize, Str)>,
+}
+
+impl Chunk {
+    pub(crate) fn new(capacity: usize) -> Result<Self> {
+        Ok(Chunk {
+            capacity,
+            written: 0,
+            payload: 0,
+            strings: Vec::new(),
+        })
+    }
+
+    /// The number of bytes that haven't been written yet
+    pub(crate) fn free(&self) -> usize {
+        self.capacity - self.written
+    }
+
+    /// Claim the next `len` bytes of the chunk, returning their offset,
+    /// or None if they don't fit
+    pub(crate) fn claim(&mut self, len: usize) -> Option<usize> {
+        if self.free() < len {
+            return None;
+        }
+        self.written += len;
+        Some(self.written - len)
+    }
+
+    fn push(&mut self, offset: usize, s: &'static str, id: u32) -> Str {
+        let t = Str(Box::leak(Box::new(s)));
+        self.strings.push((offset, t));
+        #[cfg(feature = "ids")]
+        IDS.lock().insert(t.addr(), id);
+        let _ = id;
+        t
+    }
+
+    /// Copy str, which has id `id`, into the chunk, accounting for
+    /// the padding aligning its bytes to `align` would take, returning
+    /// None if it doesn't fit
+    pub(crate) fn insert(&mut self, str: &str, id: u32, align: usize) -> Option<Str> {
+        let header = if str.len() == MAX_LEN { 2 } else { 1 };
+        let pad = (self.written + header).wrapping_neg() & (align - 1);
+        let offset = self.claim(pad + header + str.len() + TRAILER_LEN)? + pad;
+        self.payload += str.len();
+        Some(self.push(offset, Box::leak(str.into()), id))
+    }
+
+    /// Record str, which is already in static memory and has id `id`,
+    /// returning None if it doesn't fit
+    pub(crate) fn insert_static(&mut self, str: &'static str, id: u32) -> Option<Str> {
+        let offset = self.claim(STATIC_RECORD_LEN + TRAILER_LEN)?;
+        Some(self.push(offset, str, id))
+    }
+
+    /// The strings whose entries start at or after byte `from` of the
+    /// chunk
+    pub(crate) fn entries(&self, from: usize) -> impl Iterator<Item = Str> + '_ {
+        let i = self.strings.partition_point(|(offset, _)| *offset < from);
+        self.strings[i..].iter().map(|(_, t)| *t)
+    }
+
+    /// Forget everything written after `written` bytes, which held
+    /// `payload` bytes of strings
+    pub(crate) fn truncate(&mut self, written: usize, payload: usize) {
+        let i = self
+            .strings
+            .partition_point(|(offset, _)| *offset < written);
+        self.strings.truncate(i);
+        self.written = written;
+        self.payload = payload;
+    }
+
+    /// The offset of the entry of `t` in the chunk, if it is in it
+    pub(crate) fn offset_of(&self, t: Str) -> Option<usize> {
+        self.strings
+            .iter()
+            .find(|(_, u)| u.addr() == t.addr())
+            .map(|(offset, _)| *offset)
+    }
+}
+
+/// The id of `t`, which is in the pool
+#[cfg(feature = "ids")]
+pub(crate) fn id(t: Str) -> u32 {
+    IDS.lock()[&t.addr()]
+}
+
+impl Str {
+    /// The value of the handle
+    pub(crate) fn addr(self) -> usize {
+        self.0 as *const &'static str as usize
+    }
+
+    pub(crate) fn get(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl Root {
+    /// The string whose handle is `raw`, if it is the handle of a
+    /// string in the pool. Handles can't be read without knowing they
+    /// are valid, so this searches every string.
+    pub(crate) fn validate(&self, raw: NonZeroUsize) -> Option<Str> {
+        common::all()
+            .chain(self.strings())
+            .find(|t| t.as_raw() == raw)
+    }
+}
+
+/// Sharing a pool needs unsafe code, so with `forbid-unsafe` this does
+/// nothing, and every copy of the crate keeps its own pool.
+#[cfg(feature = "shared-pool")]
+#[macro_export]
+macro_rules! share_pool {
+    () => {};
+}
@@ -2,82 +2,1512 @@
 //!
 //! * supports strings up to 256 bytes
 //! * derefs to a &str, but uses only 1 word on the stack and len + 1 bytes on the heap
-//! * the actual bytes are stored packed into 1 MiB allocations to
-//!   avoid the overhead of lots of small mallocs
+//! * the actual bytes are stored packed into chunks that start at 4
+//!   KiB and grow up to 1 MiB (64 KiB on 32 bit targets, 4 KiB on 16
+//!   bit ones) to avoid the overhead of lots of small mallocs
 //! * Copy!
 //! * hashconsed, the same &str will always produce a pointer to the same memory
+//! * common strings like single ASCII characters and small integers
+//!   are built in, and are found without taking the lock
 //!
 //! CAN NEVER BE DEALLOCATED
+//!
+//! With the `forbid-unsafe` feature each string is instead leaked in
+//! its own allocation, costing malloc overhead per string, and the
+//! crate contains no unsafe code at all. The API is the same, except
+//! that `Str::from_raw` doesn't exist, and `single-threaded` and
+//! `shared-pool` have no effect.
+
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
+
+use crate::sync::Lazy;
+#[cfg(not(feature = "lock-contention"))]
+use crate::sync::Mutex;
+use fxhash::FxHashMap;
+use index::Index;
+#[cfg(feature = "forbid-unsafe")]
+use leaked::Chunk;
+use stackbuf::StackBuf;
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, TryReserveError},
+    fmt,
+    hash::Hash,
+    io, mem,
+    num::NonZeroUsize,
+    ops::Deref,
+    slice::SliceIndex,
+    str,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+#[cfg(not(feature = "forbid-unsafe"))]
+use std::{
+    iter,
+    ptr::{self, NonNull},
+    slice,
+};
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod ascii;
+#[cfg(feature = "audit")]
+mod audit;
+#[cfg(feature = "tokio")]
+mod blocking;
+mod budget;
+mod bulk;
+mod byaddr;
+#[cfg(any(feature = "track-callers", feature = "audit"))]
+mod callsite;
+mod casefold;
+mod common;
+mod config;
+#[cfg(feature = "lock-contention")]
+mod contention;
+mod counter;
+#[cfg(feature = "csv")]
+pub mod csv;
+mod decode;
+mod escape;
+mod export;
+mod fat;
+mod fork;
+#[cfg(feature = "frequency")]
+mod frequency;
+mod frozen;
+#[cfg(feature = "fuzzy")]
+mod fuzzy;
+mod generation;
+mod guard;
+#[cfg(feature = "idna")]
+mod hostname;
+mod ident;
+mod index;
+mod interner;
+mod interop;
+mod keywords;
+mod langtag;
+mod lazy;
+#[cfg(feature = "forbid-unsafe")]
+mod leaked;
+mod limit;
+mod local;
+mod mediatype;
+#[cfg(feature = "mlock")]
+mod mlock;
+#[cfg(feature = "name-hash")]
+mod namehash;
+mod net;
+#[cfg(feature = "numa")]
+mod numa;
+#[cfg(feature = "rayon")]
+mod par;
+mod pieces;
+#[cfg(feature = "prost")]
+pub mod prost;
+#[cfg(feature = "rmp")]
+pub mod rmp;
+mod search;
+mod segpath;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+mod shared;
+mod snapshot;
+#[cfg(feature = "tokio")]
+mod snapshotter;
+mod sort;
+mod stackbuf;
+#[cfg(feature = "ctor")]
+mod startup;
+mod stats;
+mod strmax;
+mod subscribe;
+#[cfg(feature = "ids")]
+mod symbols;
+mod sync;
+pub mod table;
+mod tagged;
+mod tenant;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "substring-index")]
+mod trigram;
+#[cfg(feature = "unicode")]
+mod unicode;
+#[cfg(feature = "utoipa")]
+mod utoipa;
+mod vocab;
+pub mod wire;
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+    pub use crate::shared::{Pool, OURS};
+    pub use crate::sync::Lazy;
+    #[cfg(feature = "ctor")]
+    pub use ctor;
+}
+
+pub use ascii::AsciiStr;
+#[cfg(feature = "audit")]
+pub use audit::{set_audit_sink, AuditRecord, AuditSink};
+#[cfg(feature = "tokio")]
+pub use blocking::intern_many_blocking;
+pub use budget::Budget;
+pub use bulk::{
+    dedup_strings, intern_lines, intern_tokens, load_dictionary, load_static_dictionary, prefill,
+    DedupStats, InternExt, Interned,
+};
+pub use byaddr::StrByAddr;
+#[cfg(any(feature = "track-callers", feature = "audit"))]
+pub use callsite::tagged;
+#[cfg(feature = "track-callers")]
+pub use callsite::{callsite_report, Site, SiteUsage};
+pub use casefold::CaseFoldStr;
+pub use config::{configure_from_env, Config, HashSeed};
+#[cfg(feature = "lock-contention")]
+pub use contention::{lock_waits, LockWaits};
+pub use counter::StrCounter;
+pub use export::export_as_rust;
+pub use fat::FatStr;
+pub use fork::{prepare_fork, ForkGuard};
+#[cfg(feature = "frequency")]
+pub use frequency::{request_count, top_k};
+pub use frozen::{freeze, is_frozen};
+#[cfg(feature = "fuzzy")]
+pub use fuzzy::closest_matches;
+pub use generation::{generation, new_since, Generation};
+pub use guard::{set_intern_guard, Guard, Verdict};
+#[cfg(feature = "idna")]
+pub use hostname::Hostname;
+pub use ident::{IdentStr, Identifier, Validator};
+pub use interner::{Global, Interner, Pool};
+#[cfg(feature = "ustr")]
+pub use interop::import_ustr_cache;
+#[cfg(feature = "string_cache")]
+pub use interop::intern_atoms;
+#[cfg(feature = "lasso")]
+pub use interop::{export_rodeo, import_rodeo};
+pub use langtag::LangTag;
+pub use lazy::LazyStr;
+pub use limit::{set_limit_policy, LimitPolicy, MaybeInterned};
+pub use local::{LocalKey, LocalPool, Remap};
+pub use mediatype::MediaType;
+#[cfg(feature = "mlock")]
+pub use mlock::set_lock_chunks;
+#[cfg(feature = "name-hash")]
+pub use namehash::{get_by_hash, get_by_hash_unverified, name_hash32, name_hash64};
+#[cfg(feature = "numa")]
+pub use numa::{set_numa_policy, NumaPolicy};
+#[cfg(feature = "rayon")]
+pub use par::intern_par;
+pub use pieces::{join_pieces, split_intern};
+pub use search::{autocomplete, longest_prefix_of};
+pub use segpath::SegPath;
+#[cfg(feature = "frequency")]
+pub use snapshot::compact_snapshot;
+#[cfg(feature = "mmap")]
+pub use snapshot::preload_mapped;
+#[cfg(feature = "zstd")]
+pub use snapshot::{decompress_snapshot, write_snapshot_compressed};
+pub use snapshot::{
+    load_delta, load_snapshot, preload_embedded, save_delta, snapshot_strings, write_snapshot,
+};
+#[cfg(feature = "tokio")]
+pub use snapshotter::spawn_snapshotter;
+pub use sort::sort_interned;
+pub use stats::{chunk_report, stats, stats_top, ChunkInfo, Stats, TopStrings};
+#[cfg(feature = "counters")]
+pub use stats::{interning_rates, warn_on_low_hit_ratio, LowHitRatio, Rate, Rates};
+pub use strmax::StrMax;
+pub use subscribe::subscribe;
+#[cfg(feature = "ids")]
+pub use symbols::{SymbolMap, SymbolSet};
+pub use tagged::TaggedStr;
+pub use tenant::{TenantPool, TenantStats};
+#[cfg(feature = "substring-index")]
+pub use trigram::strings_containing;
+#[cfg(feature = "unicode")]
+pub use unicode::grapheme_prefix;
+pub use vocab::VocabBuilder;
+
+// The first chunk is small, so a program that interns a few strings
+// only pays for a few pages. Each chunk after it is sized from how
+// fast the one before it filled, to hold about what the pool writes in
+// CHUNK_LIFETIME, so a program interning millions of strings quickly
+// reaches a few large chunks, while one interning a trickle keeps
+// allocating small ones. Chunks grow up to a maximum sized for the
+// memory a target typically has, so a microcontroller is never asked
+// for a megabyte.
+const MIN_CHUNK_SIZE: usize = 4 * 1024;
+/// How long a new chunk should last at the rate the last one filled
+const CHUNK_LIFETIME: Duration = Duration::from_secs(1);
+/// The fewest entries of the mean size so far a new chunk should hold
+const MIN_CHUNK_ENTRIES: usize = 64;
+/// How many times larger than the last chunk a new one can be, so a
+/// burst doesn't commit much more than the pool already holds
+const MAX_CHUNK_GROWTH: usize = 4;
+#[cfg(target_pointer_width = "64")]
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+#[cfg(target_pointer_width = "32")]
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+#[cfg(target_pointer_width = "16")]
+const MAX_CHUNK_SIZE: usize = 4 * 1024;
+const MAX_LEN: usize = u8::MAX as usize;
+
+// Entries in a chunk are normally a length byte followed by the
+// bytes of the string. A length byte of 255 is followed by a second
+// byte saying what kind of entry it is, either a 255 byte string
+// stored inline, or a record holding a pointer to a string in static
+// memory and its length, or a record holding the handle of a string
+// and a user tag. Entries of copied strings may be preceded by zero
+// bytes of padding, which `set_entry_alignment` asks for. The empty
+// string is a common string, so no entry starts with a zero.
+#[cfg(not(feature = "forbid-unsafe"))]
+const INLINE: u8 = 0;
+#[cfg(not(feature = "forbid-unsafe"))]
+const STATIC: u8 = 1;
+#[cfg(not(feature = "forbid-unsafe"))]
+const TAGGED: u8 = 2;
+const STATIC_RECORD_LEN: usize = 2 + mem::size_of::<usize>() + 1;
+const TAGGED_RECORD_LEN: usize = STATIC_RECORD_LEN;
+// Every entry but a tagged record is followed by a trailer holding
+// the name hash of its string with the name-hash feature, and then
+// its id with the ids feature.
+const HASH_LEN: usize = if cfg!(feature = "name-hash") { 8 } else { 0 };
+const ID_LEN: usize = if cfg!(feature = "ids") { 4 } else { 0 };
+const TRAILER_LEN: usize = HASH_LEN + ID_LEN;
+/// The largest alignment `set_entry_alignment` accepts
+const MAX_ALIGN: usize = 64;
+/// The longest string `set_recycle_slack` puts in the tail of a
+/// retired chunk
+const MAX_RECYCLED_LEN: usize = 16;
+
+/// The trailer written after the entry of `s`, which has id `id`
+#[cfg(not(feature = "forbid-unsafe"))]
+fn trailer(s: &str, id: u32) -> [u8; TRAILER_LEN] {
+    #[allow(unused_mut)]
+    let mut trailer = [0; TRAILER_LEN];
+    #[cfg(feature = "name-hash")]
+    trailer[..HASH_LEN].copy_from_slice(&namehash::name_hash64(s).to_le_bytes());
+    #[cfg(feature = "ids")]
+    trailer[HASH_LEN..].copy_from_slice(&id.to_le_bytes());
+    let _ = (s, id);
+    trailer
+}
+
+// On 64 bit x86 and ARM user space addresses fit comfortably in 55
+// bits, so a handle keeps the length of its string in the top byte,
+// and whether its entry is a static record in the bit below that.
+// Then len needs no memory access, and deref only reads memory to
+// follow a static record. Other targets decode the entry header each
+// time.
+const LEN_SHIFT: Option<u32> = if cfg!(all(
+    not(feature = "forbid-unsafe"),
+    target_pointer_width = "64",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)) {
+    Some(56)
+} else {
+    None
+};
+
+/// Errors that can occur while interning a string
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The string is longer than allowed, which is 255 bytes unless a
+    /// tighter bound such as `StrMax` applies
+    TooLong { len: usize },
+    /// Storing the string would exceed the limit set by `set_memory_limit`
+    CapacityExceeded,
+    /// The allocator could not provide memory to grow the pool
+    AllocFailed,
+    /// Reading strings to intern failed
+    Io(io::Error),
+    /// The bytes to intern are not valid UTF-8
+    Utf8(str::Utf8Error),
+    /// Encoded strings could not be decoded
+    Decode(&'static str),
+    /// The string was rejected by a validation rule
+    Invalid(&'static str),
+    /// The pool has been frozen and the string isn't in it
+    Frozen,
+    /// Storing the string would exceed the quota of the tenant it was
+    /// interned for
+    QuotaExceeded,
+    /// Storing the string would exceed the `Budget` it was interned
+    /// with
+    BudgetExhausted,
+    /// The intern guard refused the string
+    Rejected,
+    /// A `Config` setting is out of range, or could no longer be
+    /// applied
+    Config(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooLong { len } => write!(f, "string is too long ({len} bytes)"),
+            Error::CapacityExceeded => write!(f, "the pool memory limit has been reached"),
+            Error::Frozen => write!(f, "the pool is frozen and the string is not in it"),
+            Error::QuotaExceeded => write!(f, "the tenant's quota has been reached"),
+            Error::BudgetExhausted => write!(f, "the intern budget has been spent"),
+            Error::Rejected => write!(f, "the string was rejected by the intern guard"),
+            Error::AllocFailed => write!(f, "failed to allocate memory for the pool"),
+            Error::Io(e) => write!(f, "io error: {e}"),
+            Error::Utf8(e) => write!(f, "invalid utf-8: {e}"),
+            Error::Decode(e) => write!(f, "decode error: {e}"),
+            Error::Invalid(e) => write!(f, "invalid string: {e}"),
+            Error::Config(e) => write!(f, "invalid configuration: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Utf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
 
-use anyhow::bail;
-use fxhash::FxHashSet;
-use once_cell::sync::Lazy;
-use parking_lot::Mutex;
-use std::{borrow::Borrow, collections::HashSet, hash::Hash, ops::Deref, slice, str};
+impl From<str::Utf8Error> for Error {
+    fn from(e: str::Utf8Error) -> Self {
+        Error::Utf8(e)
+    }
+}
 
-const CHUNK_SIZE: usize = 1 * 1024 * 1024;
+impl From<TryReserveError> for Error {
+    fn from(_: TryReserveError) -> Self {
+        Error::AllocFailed
+    }
+}
+
+impl From<hashbrown::TryReserveError> for Error {
+    fn from(_: hashbrown::TryReserveError) -> Self {
+        Error::AllocFailed
+    }
+}
 
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A fixed size allocation that is never freed or moved. Entries are
+/// appended to the unwritten tail, and once written they are only
+/// ever shared as `&'static [u8]`, so nothing that has been handed out
+/// is written to again. Everything is accessed through `base`, so
+/// reading back entries that were handed out never conflicts with
+/// writing the tail.
+#[cfg(not(feature = "forbid-unsafe"))]
 struct Chunk {
-    data: Vec<u8>,
-    pos: usize,
+    /// the start of the allocation
+    base: NonNull<u8>,
+    /// the size of the allocation
+    capacity: usize,
+    /// the number of bytes written to the front of the chunk
+    written: usize,
+    /// the number of string bytes stored in the chunk
+    payload: usize,
+    /// whether the chunk has a mapping of its own, made by `new_numa`,
+    /// instead of an allocation from a `Vec`, so `release` can free it
+    #[cfg(all(feature = "numa", feature = "mlock", target_os = "linux"))]
+    mapped: bool,
 }
 
+// The chunk owns its allocation, and is only touched under the pool
+// lock.
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl Send for Chunk {}
+
+#[cfg(not(feature = "forbid-unsafe"))]
 impl Chunk {
-    fn new() -> &'static mut Self {
-	Box::leak(Box::new(Chunk {
-	    data: vec![0; CHUNK_SIZE],
-	    pos: 0
-	}))
+    /// Allocate a new chunk, returning an error instead of aborting
+    /// if the allocator can't satisfy the request.
+    #[cfg_attr(feature = "profiling", inline(never))]
+    fn new(capacity: usize) -> Result<Self> {
+        let mut data = Vec::<u8>::new();
+        data.try_reserve_exact(capacity)?;
+        if let Some(shift) = LEN_SHIFT {
+            // entries must leave the bits handles pack into free
+            if (data.as_ptr() as usize + capacity) >> (shift - 1) != 0 {
+                return Err(Error::AllocFailed);
+            }
+        }
+        let base = NonNull::new(data.as_mut_ptr()).unwrap();
+        mem::forget(data);
+        Ok(Chunk {
+            base,
+            capacity,
+            written: 0,
+            payload: 0,
+            #[cfg(all(feature = "numa", feature = "mlock", target_os = "linux"))]
+            mapped: false,
+        })
+    }
+
+    /// Free the memory of a chunk that was never added to the pool
+    #[cfg(feature = "mlock")]
+    fn release(self) {
+        #[cfg(all(feature = "numa", target_os = "linux"))]
+        if self.mapped {
+            return numa::unmap(self.base, self.capacity);
+        }
+        // SAFETY: the allocation was made by a Vec<u8> with this
+        // capacity in new, and nothing refers to it
+        drop(unsafe { Vec::from_raw_parts(self.base.as_ptr(), 0, self.capacity) });
     }
 
-    fn insert(&mut self, str: &str) -> (*mut Chunk, Str) {
+    /// The number of bytes that haven't been written yet
+    fn free(&self) -> usize {
+        self.capacity - self.written
+    }
+
+    /// Write an entry for a string of `str_len` bytes made of the
+    /// concatenation of parts to the chunk, returning None if it
+    /// doesn't fit
+    fn write(&mut self, str_len: usize, is_static: bool, parts: &[&[u8]]) -> Option<Str> {
+        let len = parts.iter().map(|p| p.len()).sum();
+        if self.free() < len {
+            return None;
+        }
+        // SAFETY: the entry fits in the unwritten tail of the
+        // allocation, which nothing else refers to
+        unsafe {
+            let entry = self.base.as_ptr().add(self.written);
+            let mut dst = entry;
+            for p in parts {
+                ptr::copy_nonoverlapping(p.as_ptr(), dst, p.len());
+                dst = dst.add(p.len());
+            }
+            self.written += len;
+            Some(Str::from_entry(
+                NonNull::new_unchecked(entry),
+                str_len,
+                is_static,
+            ))
+        }
+    }
+
+    /// The written part of the chunk
+    fn bytes(&self) -> &'static [u8] {
+        // SAFETY: chunks are never freed, and the written part is
+        // immutable
+        unsafe { slice::from_raw_parts(self.base.as_ptr(), self.written) }
+    }
+
+    /// The strings written starting at byte `from` of the chunk, which
+    /// must be the start of an entry or of its padding. Tagged records
+    /// aren't strings, so they are skipped.
+    fn entries(&self, mut from: usize) -> impl Iterator<Item = Str> + '_ {
+        iter::from_fn(move || loop {
+            if from >= self.written {
+                return None;
+            }
+            // SAFETY: from is the start of an entry in the written
+            // part of the chunk, which is immutable
+            unsafe {
+                let entry = self.base.as_ptr().add(from);
+                let (len, size, is_static) = match *entry {
+                    0 => {
+                        from += 1;
+                        continue;
+                    }
+                    n if n < u8::MAX => (n as usize, 1 + n as usize + TRAILER_LEN, false),
+                    _ if *entry.add(1) == INLINE => (MAX_LEN, 2 + MAX_LEN + TRAILER_LEN, false),
+                    _ if *entry.add(1) == TAGGED => {
+                        from += TAGGED_RECORD_LEN;
+                        continue;
+                    }
+                    _ => (
+                        *entry.add(STATIC_RECORD_LEN - 1) as usize,
+                        STATIC_RECORD_LEN + TRAILER_LEN,
+                        true,
+                    ),
+                };
+                from += size;
+                return Some(Str::from_entry(
+                    NonNull::new_unchecked(entry),
+                    len,
+                    is_static,
+                ));
+            }
+        })
+    }
+
+    /// Forget everything written after `written` bytes, which held
+    /// `payload` bytes of strings
+    fn truncate(&mut self, written: usize, payload: usize) {
+        self.written = written;
+        self.payload = payload;
+    }
+
+    /// The offset of the entry of `t` in the chunk, if it is in it
+    fn offset_of(&self, t: Str) -> Option<usize> {
+        let offset = (t.entry() as usize).wrapping_sub(self.base.as_ptr() as usize);
+        (offset < self.written).then_some(offset)
+    }
+
+    /// Copy str, which has id `id`, into the chunk, with its bytes
+    /// aligned to `align`, returning None if it doesn't fit
+    fn insert(&mut self, str: &str, id: u32, align: usize) -> Option<Str> {
+        let trailer = trailer(str, id);
         let str = str.as_bytes();
-        let mut t = self;
-        loop {
-            if CHUNK_SIZE - t.pos > str.len() {
-                t.data[t.pos] = str.len() as u8;
-                t.data[t.pos + 1..t.pos + 1 + str.len()].copy_from_slice(str);
-                let res = Str(t.data.as_ptr().wrapping_add(t.pos));
-                t.pos += 1 + str.len();
-                break (t, res);
+        let header = if str.len() == MAX_LEN { 2 } else { 1 };
+        let start = self.base.as_ptr() as usize + self.written + header;
+        let pad = start.wrapping_neg() & (align - 1);
+        if self.free() < pad + header + str.len() + TRAILER_LEN {
+            return None;
+        }
+        // SAFETY: the padding fits in the unwritten tail of the
+        // allocation, which nothing else refers to
+        unsafe { self.base.as_ptr().add(self.written).write_bytes(0, pad) };
+        self.written += pad;
+        let t = if str.len() == u8::MAX as usize {
+            self.write(str.len(), false, &[&[u8::MAX, INLINE], str, &trailer])?
+        } else {
+            self.write(str.len(), false, &[&[str.len() as u8], str, &trailer])?
+        };
+        self.payload += str.len();
+        Some(t)
+    }
+
+    /// Write a record pointing to str, which is already in static
+    /// memory and has id `id`, returning None if it doesn't fit
+    fn insert_static(&mut self, str: &'static str, id: u32) -> Option<Str> {
+        let ptr = (str.as_ptr() as usize).to_ne_bytes();
+        self.write(
+            str.len(),
+            true,
+            &[
+                &[u8::MAX, STATIC],
+                &ptr,
+                &[str.len() as u8],
+                &trailer(str, id),
+            ],
+        )
+    }
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+/// The plain entry starting at byte `off` of `region`, a chunk or the
+/// table of common strings, if the bytes there form one holding UTF-8.
+/// Static records hold a pointer that can only be trusted once `off`
+/// is known to be the start of an entry, so they aren't parsed.
+fn plain_entry(region: &'static [u8], off: usize) -> Option<Str> {
+    let (len, start) = match *region.get(off)? {
+        n if n < u8::MAX => (n as usize, 1),
+        _ if *region.get(off + 1)? == INLINE => (MAX_LEN, 2),
+        _ => return None,
+    };
+    str::from_utf8(region.get(off + start..off + start + len)?).ok()?;
+    // SAFETY: the entry is complete and in static memory
+    Some(unsafe { Str::from_entry(NonNull::from(&region[off]), len, false) })
+}
+
+struct Root {
+    all: Index,
+    /// every chunk ever allocated, the last one is being filled.
+    /// Empty until the first insert.
+    chunks: Vec<Chunk>,
+    /// the index of every retired chunk and the offset its unwritten
+    /// tail started at, in the order they were retired
+    tails: Vec<(usize, usize)>,
+    /// the position in `tails` of the tail tiny strings are written to
+    tail: usize,
+    /// whether tiny strings are written to the tails of retired chunks
+    recycle: bool,
+    limit: usize,
+    /// the size chunks grow to
+    max_chunk: usize,
+    /// when the last chunk was allocated and the number of strings in
+    /// the pool then, to size the next one from how fast it filled
+    grown: Option<(Instant, usize)>,
+    /// what the bytes of copied strings are aligned to
+    align: usize,
+    /// channels that are sent every new string
+    subscribers: Vec<mpsc::Sender<Str>>,
+    /// what screens strings before they are inserted
+    guard: Option<Guard>,
+    /// where every new string is recorded
+    #[cfg(feature = "audit")]
+    audit: Option<Box<dyn AuditSink>>,
+    /// set by freeze, after which nothing can be inserted
+    frozen: bool,
+    /// what to do when the memory limit is reached
+    policy: LimitPolicy,
+    /// whether new chunks are faulted in and locked
+    #[cfg(feature = "mlock")]
+    lock_chunks: bool,
+    /// where the pages of new chunks are placed
+    #[cfg(feature = "numa")]
+    numa: NumaPolicy,
+    /// the tagged record of each string and tag, by the raw handle of
+    /// the string
+    tagged: FxHashMap<(NonZeroUsize, u8), TaggedStr>,
+    /// the quotas of tenants
+    tenants: tenant::Tenants,
+    /// every string in the pool, by name hash
+    #[cfg(feature = "name-hash")]
+    by_hash: namehash::ByHash,
+    /// every string in the pool, by its id less the number of common
+    /// strings
+    #[cfg(feature = "ids")]
+    by_id: Vec<Str>,
+}
+
+impl Root {
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn intern(&mut self, s: &str) -> Result<Str> {
+        self.intern_hashed(index::hash(s), s)
+    }
+
+    /// Intern `s`, whose hash, computed before taking the lock, is
+    /// `hash`
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn intern_hashed(&mut self, hash: u64, s: &str) -> Result<Str> {
+        #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+        if let Some(pool) = shared::host() {
+            return pool.intern(s);
+        }
+        if s.len() > u8::MAX as usize {
+            Err(Error::TooLong { len: s.len() })
+        } else if let Some(t) = common::get(s) {
+            stats::hit(t);
+            Ok(t)
+        } else {
+            match self.all.get(hash, s) {
+                Some(t) => {
+                    stats::hit(t);
+                    Ok(t)
+                }
+                None => {
+                    if let Some(r) = self.screen(s)? {
+                        return self.intern_replacement(&r);
+                    }
+                    let t = self.insert(hash, s)?;
+                    stats::miss(t);
+                    #[cfg(feature = "track-callers")]
+                    callsite::record(s.len());
+                    Ok(t)
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn intern_static(&mut self, s: &'static str) -> Result<Str> {
+        #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+        if let Some(pool) = shared::host() {
+            return pool.intern(s);
+        }
+        if s.len() < STATIC_RECORD_LEN {
+            self.intern(s)
+        } else if s.len() > u8::MAX as usize {
+            Err(Error::TooLong { len: s.len() })
+        } else {
+            let hash = index::hash(s);
+            match self.all.get(hash, s) {
+                Some(t) => Ok(t),
+                None => {
+                    if let Some(r) = self.screen(s)? {
+                        return self.intern_replacement(&r);
+                    }
+                    let t = self.insert_static(hash, s)?;
+                    #[cfg(feature = "track-callers")]
+                    callsite::record(s.len());
+                    Ok(t)
+                }
+            }
+        }
+    }
+
+    /// The string whose handle is `raw`, if it is the handle of a
+    /// string in the pool. The entry it points to is only read once it
+    /// is known to be inside a chunk or the table of common strings.
+    #[cfg(not(feature = "forbid-unsafe"))]
+    fn validate(&self, raw: NonZeroUsize) -> Option<Str> {
+        // SAFETY: the handle isn't dereferenced, only its entry address
+        // is computed
+        let entry = unsafe { Str::from_raw_unchecked(raw) }.entry() as usize;
+        let t = match common::at(entry) {
+            Some(t) => t,
+            None => {
+                let chunk = self
+                    .chunks
+                    .iter()
+                    .find(|c| entry.wrapping_sub(c.base.as_ptr() as usize) < c.written)?;
+                let off = entry - chunk.base.as_ptr() as usize;
+                match plain_entry(chunk.bytes(), off) {
+                    // a plain entry is only valid if it is the one the
+                    // index has for its string
+                    Some(t) => self.all.get(index::hash(&t), &t)?,
+                    None => chunk.entries(0).find(|t| t.as_raw() == raw)?,
+                }
+            }
+        };
+        (t.as_raw() == raw).then_some(t)
+    }
+
+    /// Every string in the pool, in the order they were inserted,
+    /// except that strings written to the tail of a retired chunk come
+    /// with the strings of that chunk
+    fn strings(&self) -> impl Iterator<Item = Str> + '_ {
+        self.chunks.iter().flat_map(|c| c.entries(0))
+    }
+
+    /// The id of the next string inserted. The common strings come
+    /// first, and every string in the pool is in the index.
+    fn next_id(&self) -> u32 {
+        common::COUNT + self.all.len() as u32
+    }
+
+    fn insert(&mut self, hash: u64, s: &str) -> Result<Str> {
+        let (id, align) = (self.next_id(), self.align);
+        self.insert_with(hash, s, |chunk| chunk.insert(s, id, align))
+    }
+
+    fn insert_static(&mut self, hash: u64, s: &'static str) -> Result<Str> {
+        let id = self.next_id();
+        self.insert_with(hash, s, |chunk| chunk.insert_static(s, id))
+    }
+
+    fn insert_with(
+        &mut self,
+        hash: u64,
+        s: &str,
+        f: impl Fn(&mut Chunk) -> Option<Str>,
+    ) -> Result<Str> {
+        if self.frozen {
+            return Err(Error::Frozen);
+        }
+        // grow the set before touching the chunk so a failed
+        // allocation leaves the pool unchanged
+        self.all.try_reserve(1)?;
+        #[cfg(feature = "ids")]
+        self.by_id.try_reserve(1)?;
+        #[cfg(feature = "name-hash")]
+        self.by_hash.try_reserve(1)?;
+        let mark = self.mark();
+        let t = self.write_with(s, f)?;
+        let guard = Rollback::new(self, mark);
+        guard.root.all.insert(hash, t);
+        guard.commit();
+        #[cfg(feature = "ids")]
+        self.by_id.push(t);
+        #[cfg(feature = "name-hash")]
+        self.by_hash.insert(t);
+        Generation::publish(self);
+        if !self.subscribers.is_empty() {
+            self.subscribers.retain(|tx| tx.send(t).is_ok());
+        }
+        #[cfg(feature = "audit")]
+        audit::record(&mut self.audit, t);
+        Ok(t)
+    }
+
+    /// Where the next entry will be written, so it can be rolled back
+    fn mark(&self) -> Mark {
+        Mark {
+            chunks: self.chunks.len(),
+            written: self.chunks.last().map_or(0, |c| c.written),
+            payload: self.chunks.last().map_or(0, |c| c.payload),
+            tail: self.tail,
+            tail_end: self
+                .tails
+                .get(self.tail)
+                .map(|(i, _)| (self.chunks[*i].written, self.chunks[*i].payload)),
+        }
+    }
+
+    /// Forget every entry written since `mark`. This is only sound
+    /// while none of them have been handed out. A chunk allocated
+    /// since is kept, empty, for the next insert.
+    fn rollback(&mut self, mark: Mark) {
+        let grown = self.chunks.len() > mark.chunks;
+        if let Some(chunk) = self.chunks.last_mut() {
+            if grown {
+                chunk.truncate(0, 0);
             } else {
-                t = Self::new();
+                chunk.truncate(mark.written, mark.payload);
             }
         }
+        self.tail = mark.tail;
+        if let Some((written, payload)) = mark.tail_end {
+            let i = self.tails[mark.tail].0;
+            self.chunks[i].truncate(written, payload);
+        }
+    }
+
+    /// Allocate a chunk of `size` bytes, placed and locked as the pool
+    /// has been configured to
+    fn new_chunk(&self, size: usize) -> Result<Chunk> {
+        #[cfg(feature = "numa")]
+        let chunk = match self.numa {
+            NumaPolicy::Default => Chunk::new(size)?,
+            policy => Chunk::new_numa(size, policy)?,
+        };
+        #[cfg(not(feature = "numa"))]
+        let chunk = Chunk::new(size)?;
+        #[cfg(feature = "mlock")]
+        if self.lock_chunks {
+            return chunk.lock();
+        }
+        Ok(chunk)
+    }
+
+    /// Write an entry with `f` to the first tail of a retired chunk it
+    /// fits in. Tails are filled in the order their chunks were
+    /// retired, and one an entry doesn't fit in is given up on, so
+    /// what is written to them only ever moves forward.
+    fn write_tail<T>(&mut self, f: impl Fn(&mut Chunk) -> Option<T>) -> Option<T> {
+        while let Some((i, _)) = self.tails.get(self.tail) {
+            if let Some(t) = f(&mut self.chunks[*i]) {
+                return Some(t);
+            }
+            self.tail += 1;
+        }
+        None
+    }
+
+    /// Write an entry for `s` with `f`, moving on to a new chunk if it
+    /// doesn't fit in the current one and the memory limit allows. The
+    /// first chunk is allocated by the first insert, so a failed
+    /// allocation is returned to the caller instead of aborting. Tiny
+    /// strings go to the tail of a retired chunk first, if
+    /// `set_recycle_slack` asks for it.
+    fn write_with<T>(&mut self, s: &str, f: impl Fn(&mut Chunk) -> Option<T>) -> Result<T> {
+        if self.recycle && s.len() <= MAX_RECYCLED_LEN {
+            if let Some(t) = self.write_tail(&f) {
+                return Ok(t);
+            }
+        }
+        let t = match self.chunks.last_mut().and_then(&f) {
+            Some(t) => t,
+            None => {
+                let allocated: usize = self.chunks.iter().map(|c| c.capacity).sum();
+                let now = clock();
+                let mut size = match self.chunks.last() {
+                    None => MIN_CHUNK_SIZE,
+                    Some(last) => {
+                        let (filled_in, entries) = match (now, self.grown) {
+                            (Some(now), Some((at, strings))) => (
+                                Some(now.saturating_duration_since(at)),
+                                self.all.len().saturating_sub(strings),
+                            ),
+                            _ => (None, 0),
+                        };
+                        let written = last.capacity - last.free();
+                        let last = (last.capacity, written);
+                        chunk_size(allocated, last, entries, filled_in, self.max_chunk)
+                    }
+                };
+                if !self.chunks.is_empty() {
+                    size = size.min(self.limit.saturating_sub(allocated));
+                }
+                if size < MIN_CHUNK_SIZE {
+                    match self.policy {
+                        LimitPolicy::Error => (),
+                        LimitPolicy::Panic => panic!("the pool memory limit has been reached"),
+                        LimitPolicy::Callback(f) => f(s),
+                    }
+                    return Err(Error::CapacityExceeded);
+                }
+                self.chunks.try_reserve(1)?;
+                self.tails.try_reserve(1)?;
+                let chunk = self.new_chunk(size)?;
+                if let Some(last) = self.chunks.last() {
+                    self.tails.push((self.chunks.len() - 1, last.written));
+                }
+                self.chunks.push(chunk);
+                self.grown = now.map(|at| (at, self.all.len()));
+                f(self.chunks.last_mut().unwrap()).unwrap()
+            }
+        };
+        Ok(t)
     }
 }
 
-struct Root {
-    all: FxHashSet<Str>,
-    root: *mut Chunk,
+/// The time, for sizing chunks, or None with the `deterministic`
+/// feature, which keeps the sizes independent of timing, or on targets
+/// without a clock
+fn clock() -> Option<Instant> {
+    let timed = !cfg!(feature = "deterministic")
+        && !cfg!(all(target_arch = "wasm32", target_os = "unknown"));
+    timed.then(Instant::now)
 }
 
-unsafe impl Send for Root {}
-unsafe impl Sync for Root {}
+/// The size of the chunk to allocate after the last one, whose
+/// capacity and bytes written are `last` and which is full, when
+/// `allocated` bytes of chunks have been allocated, `entries` strings
+/// have been inserted since the last chunk was, and it filled in
+/// `filled_in`.
+/// That is the insert rate times the mean entry size, the bytes written
+/// a second, over `CHUNK_LIFETIME`, and at least `MIN_CHUNK_ENTRIES`
+/// entries of the mean size. It is never smaller than `last`, nor more
+/// than `MAX_CHUNK_GROWTH` times larger. Without a time, each chunk is
+/// as large as all the chunks before it.
+fn chunk_size(
+    allocated: usize,
+    (capacity, written): (usize, usize),
+    entries: usize,
+    filled_in: Option<Duration>,
+    max: usize,
+) -> usize {
+    let size = match filled_in {
+        None => allocated,
+        Some(elapsed) => {
+            let per_lifetime =
+                written as u128 * CHUNK_LIFETIME.as_nanos() / elapsed.as_nanos().max(1);
+            let mean = written / entries.max(1);
+            usize::try_from(per_lifetime)
+                .unwrap_or(usize::MAX)
+                .max(mean.saturating_mul(MIN_CHUNK_ENTRIES))
+                .clamp(capacity, capacity.saturating_mul(MAX_CHUNK_GROWTH))
+        }
+    };
+    size.clamp(MIN_CHUNK_SIZE, max)
+}
 
-static ROOT: Lazy<Mutex<Root>> = Lazy::new(|| {
-    Mutex::new(Root {
-        all: HashSet::default(),
-        root: Chunk::new(),
+/// The end of the last chunk and of the tail being filled at some
+/// point
+#[derive(Clone, Copy)]
+struct Mark {
+    chunks: usize,
+    written: usize,
+    payload: usize,
+    tail: usize,
+    tail_end: Option<(usize, usize)>,
+}
+
+/// Rolls the chunks back to a mark when dropped, unless committed.
+/// Entries are written before they are registered in the index, so if
+/// registering them unwinds, the guard forgets them, and the chunks and
+/// the index never disagree about what is in the pool.
+struct Rollback<'a> {
+    root: &'a mut Root,
+    mark: Mark,
+    committed: bool,
+}
+
+impl<'a> Rollback<'a> {
+    fn new(root: &'a mut Root, mark: Mark) -> Self {
+        Rollback {
+            root,
+            mark,
+            committed: false,
+        }
+    }
+
+    fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for Rollback<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.root.rollback(self.mark);
+        }
+    }
+}
+
+#[cfg(feature = "lock-contention")]
+type RootMutex = contention::Timed<Root>;
+#[cfg(not(feature = "lock-contention"))]
+type RootMutex = Mutex<Root>;
+
+static ROOT: Lazy<RootMutex> = Lazy::new(|| {
+    RootMutex::new(Root {
+        all: Index::new(),
+        chunks: Vec::new(),
+        tails: Vec::new(),
+        tail: 0,
+        recycle: false,
+        limit: usize::MAX,
+        max_chunk: MAX_CHUNK_SIZE,
+        grown: None,
+        align: 1,
+        subscribers: Vec::new(),
+        guard: None,
+        #[cfg(feature = "audit")]
+        audit: None,
+        frozen: false,
+        policy: LimitPolicy::Error,
+        #[cfg(feature = "mlock")]
+        lock_chunks: false,
+        #[cfg(feature = "numa")]
+        numa: NumaPolicy::Default,
+        tagged: HashMap::default(),
+        tenants: Default::default(),
+        #[cfg(feature = "name-hash")]
+        by_hash: namehash::ByHash::new(),
+        #[cfg(feature = "ids")]
+        by_id: Vec::new(),
     })
 });
 
+/// Limit the total memory the pool may allocate for string storage
+/// to `bytes`. Once the limit is reached, interning a string that is
+/// not already in the pool fails with `Error::CapacityExceeded`,
+/// while strings that are already interned can still be looked up.
+///
+/// The first chunk is always allocated, and the last chunk is cut
+/// down to fit under the limit, unless that would leave it smaller
+/// than the 4 KiB first chunk. What happens when the limit is reached
+/// can be changed with `set_limit_policy`.
+pub fn set_memory_limit(bytes: usize) {
+    ROOT.lock().limit = bytes;
+}
+
+/// Set the size chunks grow up to, which is 1 MiB by default, 64 KiB
+/// on 32 bit targets and 4 KiB on 16 bit ones. The first chunk is
+/// 4 KiB, and each chunk after it is sized to hold what the pool wrote
+/// in about a second while the last one filled, at the rate strings
+/// were inserted and their mean length, at least as large as the last
+/// one and at most 4 times larger. So a program that interns a lot of
+/// strings quickly reaches the maximum, while one that interns a few,
+/// or interns them slowly, never allocates much more than it uses.
+/// With the `deterministic` feature, and on targets without a clock,
+/// sizes don't depend on timing, and each chunk is as large as all the
+/// chunks before it together. A larger maximum means fewer, larger
+/// allocations. `bytes` is clamped to at least 4 KiB and at most
+/// 4 GiB, and chunks that have already been allocated keep their size.
+pub fn set_max_chunk_size(bytes: usize) {
+    ROOT.lock().max_chunk = bytes.clamp(MIN_CHUNK_SIZE, u32::MAX as usize);
+}
+
+/// Align the bytes of every string copied into the pool from now on
+/// to `align` bytes, for example 16 so that SIMD comparisons load them
+/// aligned, or 64 so that no two strings share a cache line. The
+/// default is 1, which packs strings without any padding. Padding is
+/// counted as wasted by `stats`, and costs up to `align - 1` bytes per
+/// string. Strings interned with `Str::from_static` stay where they
+/// are, and with `forbid-unsafe` strings are leaked in allocations of
+/// their own, so this only changes how much the pool accounts for.
+///
+/// # Panics
+///
+/// Panics if `align` isn't a power of two of at most 64.
+pub fn set_entry_alignment(align: usize) {
+    assert!(
+        align.is_power_of_two() && align <= MAX_ALIGN,
+        "entry alignment must be a power of two of at most {MAX_ALIGN}, not {align}"
+    );
+    ROOT.lock().align = align;
+}
+
+/// Store strings of up to 16 bytes in the unwritten tails of retired
+/// chunks, if `on`, before the chunk being filled. A chunk is retired
+/// when the next string doesn't fit in it, so a workload mixing long
+/// and tiny strings otherwise leaves the tails of most chunks unused,
+/// which `stats` reports as slack. It is off by default, because
+/// strings written to the tail of a chunk come out of `new_since`,
+/// snapshots and everything else that walks the pool with the strings
+/// of that chunk, instead of in the order they were interned, so with
+/// the `ids` feature a pool loaded from a snapshot may number them
+/// differently.
+pub fn set_recycle_slack(on: bool) {
+    ROOT.lock().recycle = on;
+}
+
+/// Intern `s`, panicking if it can't be interned, for call sites that
+/// only intern strings known to be short, such as literals, where
+/// handling an error is just noise. Use `Str::try_from` when the
+/// string comes from outside the program.
+///
+/// # Panics
+///
+/// If `s` is longer than `Str::MAX_LEN` bytes, or if the pool can't
+/// grow to store it.
+#[track_caller]
+pub fn intern(s: &str) -> Str {
+    match Str::try_from(s) {
+        Ok(t) => t,
+        Err(Error::TooLong { len }) => {
+            panic!("can't intern a string of {len} bytes, the limit is {MAX_LEN}")
+        }
+        Err(e) => panic!("can't intern {s:?}: {e}"),
+    }
+}
+
 /// This is a pointer into static memory that holds the actual str
 /// slice. This type is 1 word on the stack, the length is stored in
 /// the heap as a byte. Deref is quite cheap, there is no locking to
 /// deref. Only try_from can be expensive since it performs the
 /// hashconsing.
+///
+/// # ABI
+///
+/// `Str` is `repr(transparent)` over a non null pointer, so it has the
+/// size, alignment and calling convention of a pointer, and
+/// `Option<Str>` is the same size with None as null. It can be passed
+/// through C ABIs and plugin boundaries as an opaque pointer sized
+/// value, and turned back into a `Str` on the other side, as long as
+/// both sides share the same pool. The value is not a `char *`: on
+/// some targets it has the length packed into its high bits, and the
+/// bytes it refers to aren't nul terminated, so foreign code must not
+/// dereference it.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+#[cfg(not(feature = "forbid-unsafe"))]
+pub struct Str(NonNull<u8>);
+
+/// With `forbid-unsafe` a `Str` is a reference to a leaked `&str`,
+/// which is also pointer sized, but `Str::from_raw` isn't available.
 #[derive(Clone, Copy)]
-pub struct Str(*const u8);
+#[repr(transparent)]
+#[cfg(feature = "forbid-unsafe")]
+pub struct Str(&'static &'static str);
 
+const _: () = {
+    assert!(mem::size_of::<Str>() == mem::size_of::<*const u8>());
+    assert!(mem::align_of::<Str>() == mem::align_of::<*const u8>());
+    assert!(mem::size_of::<Option<Str>>() == mem::size_of::<*const u8>());
+};
+
+// The entry a Str points to is immutable and lives forever, so it is
+// safe to share and send anywhere.
+#[cfg(not(feature = "forbid-unsafe"))]
 unsafe impl Send for Str {}
+#[cfg(not(feature = "forbid-unsafe"))]
 unsafe impl Sync for Str {}
 
 impl Str {
+    /// The longest string, in bytes, that can be interned
+    pub const MAX_LEN: usize = MAX_LEN;
+
+    /// Whether `s` is short enough to be interned. Interning can
+    /// still fail for other reasons, such as the memory limit.
+    pub const fn is_internable(s: &str) -> bool {
+        s.len() <= MAX_LEN
+    }
+
+    /// Intern a string that already lives in static memory, such as
+    /// a literal. If the string isn't already in the pool, the pool
+    /// records a pointer to it instead of copying its bytes. Strings
+    /// short enough that copying them takes less space than the
+    /// pointer record are copied as usual.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn from_static(s: &'static str) -> Result<Str> {
+        ROOT.lock().intern_static(s)
+    }
+
+    /// Intern `s` without ever waiting for the pool lock. Returns None
+    /// if another thread holds the lock, or if `s` can't be interned,
+    /// so latency sensitive callers can fall back to using the `&str`
+    /// directly. With the `dashmap` or `arc-swap` features strings that
+    /// are already in the unlocked part of the index are found even
+    /// while the lock is held.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_intern_nonblocking(s: &str) -> Option<Str> {
+        #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+        if let Some(pool) = shared::host() {
+            return pool.intern(s).ok();
+        }
+        if let Some(t) = common::get(s) {
+            stats::hit(t);
+            return Some(t);
+        }
+        if let Some(res) = frozen::get(s) {
+            let t = res.ok()?;
+            stats::hit(t);
+            return Some(t);
+        }
+        let hash = index::hash(s);
+        match index::get_unlocked(hash, s) {
+            Some(t) => {
+                stats::hit(t);
+                Some(t)
+            }
+            None => ROOT.try_lock()?.intern_hashed(hash, s).ok(),
+        }
+    }
+
+    /// Intern `s`, returning None if it can't be interned for any
+    /// reason, for callers that would fall back to an owned string
+    /// anyway and don't care why.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_intern(s: &str) -> Option<Str> {
+        Str::try_from(s).ok()
+    }
+
+    /// Intern the longest prefix of `s` that fits, cutting it at the
+    /// last char boundary at or before `MAX_LEN` bytes, instead of
+    /// failing with `TooLong`. It can still fail if the pool can't
+    /// grow.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_truncated(s: &str) -> Result<Str> {
+        let mut end = s.len().min(MAX_LEN);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        Str::try_from(&s[..end])
+    }
+
+    /// Intern `s` repeated `n` times, built on the stack, failing with
+    /// `TooLong` if the result would be longer than `MAX_LEN` bytes.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn repeat(s: &str, n: usize) -> Result<Str> {
+        if s.is_empty() || n == 0 {
+            return Str::try_from("");
+        }
+        // n is at most MAX_LEN / s.len() once this passes
+        let len = s.len().saturating_mul(n);
+        if len > MAX_LEN {
+            return Err(Error::TooLong { len });
+        }
+        let mut buf = StackBuf::new();
+        for _ in 0..n {
+            buf.push_str(s);
+        }
+        Str::try_from(buf.as_str()?)
+    }
+
+    /// Validate that `bytes` are UTF-8 and intern them. The length is
+    /// checked first, so over long input is rejected without being
+    /// scanned.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn from_utf8(bytes: &[u8]) -> Result<Str> {
+        if bytes.len() > MAX_LEN {
+            return Err(Error::TooLong { len: bytes.len() });
+        }
+        Str::try_from(str::from_utf8(bytes)?)
+    }
+
+    /// Return the interned string with its full `'static` lifetime.
+    /// Deref also yields a `'static` str, but the lifetime is hidden
+    /// behind `Deref::Target`, so it is limited to the lifetime of the
+    /// `Str` at the call site.
+    pub fn as_static(self) -> &'static str {
+        self.get()
+    }
+
+    /// Slice the interned string, keeping the `'static` lifetime.
+    /// Indexing a `Str` with `s[a..b]` works through `Deref`, but
+    /// `Index` ties the result to the lifetime of the `Str` itself, so
+    /// it can't be used to keep slices in long lived structures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or not on a char boundary,
+    /// like indexing a `str`.
+    pub fn slice<R: SliceIndex<str, Output = str>>(self, range: R) -> &'static str {
+        &self.as_static()[range]
+    }
+
+    /// Like `slice`, but returns None instead of panicking
+    pub fn get_slice<R: SliceIndex<str, Output = str>>(self, range: R) -> Option<&'static str> {
+        self.as_static().get(range)
+    }
+
+    /// Split the interned string on `pat`, yielding `'static` pieces.
+    /// For patterns other than a `&str` use `as_static().split(..)`.
+    pub fn split_static(self, pat: &str) -> str::Split<'static, &str> {
+        self.as_static().split(pat)
+    }
+
+    /// Iterate over the lines of the interned string as `'static`
+    /// slices
+    pub fn lines_static(self) -> str::Lines<'static> {
+        self.as_static().lines()
+    }
+
+    /// Whether the string starts with `prefix`. On 64 bit x86 and ARM
+    /// both lengths come from the handles, so a prefix longer than the
+    /// string is rejected without touching either entry, and a prefix
+    /// that is the same handle, or whose bytes are the start of the
+    /// string's, as with slices of one static string, matches without
+    /// comparing any bytes.
+    pub fn starts_with_str(self, prefix: Str) -> bool {
+        if prefix.len() > self.len() {
+            return false;
+        }
+        if self.addr() == prefix.addr() {
+            return true;
+        }
+        let (s, p) = (self.as_static(), prefix.as_static());
+        s.as_ptr() == p.as_ptr() || index::bytes_eq(&s.as_bytes()[..p.len()], p.as_bytes())
+    }
+
+    /// Whether the string ends with `suffix`, with the same fast paths
+    /// as `starts_with_str`
+    pub fn ends_with_str(self, suffix: Str) -> bool {
+        if suffix.len() > self.len() {
+            return false;
+        }
+        if self.addr() == suffix.addr() {
+            return true;
+        }
+        let (s, p) = (self.as_static(), suffix.as_static());
+        let tail = &s.as_bytes()[s.len() - p.len()..];
+        tail.as_ptr() == p.as_ptr() || index::bytes_eq(tail, p.as_bytes())
+    }
+
+    /// Return the handle as a nonzero word that is unique to the
+    /// string. It can be turned back into a `Str` with `from_raw`,
+    /// which makes it possible to store handles in FFI userdata,
+    /// atomics, and the like.
+    pub fn as_raw(self) -> NonZeroUsize {
+        NonZeroUsize::new(self.addr()).expect("Str is never null")
+    }
+
+    /// Reconstruct a `Str` from a value returned by `as_raw`. In debug
+    /// builds this checks that `raw` is the handle of an interned
+    /// string, as `try_from_raw` does, and panics if it isn't.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must have been returned by `Str::as_raw` in this process.
+    #[cfg(not(feature = "forbid-unsafe"))]
+    pub unsafe fn from_raw(raw: NonZeroUsize) -> Str {
+        // the single threaded pool can only be locked by its owner, so
+        // handles used on other threads can't be checked
+        #[cfg(not(feature = "single-threaded"))]
+        debug_assert!(
+            Str::try_from_raw(raw).is_some(),
+            "{raw:#x} is not the handle of an interned string"
+        );
+        Str::from_raw_unchecked(raw)
+    }
+
+    /// Reconstruct a `Str` from a value returned by `as_raw`, or return
+    /// None if `raw` isn't the handle of an interned string, so a
+    /// handle corrupted on its way through FFI is caught where it comes
+    /// back instead of causing a wild read later. This takes the pool
+    /// lock, and handles of static strings are found by searching
+    /// their chunk, so it is much slower than `from_raw`.
+    pub fn try_from_raw(raw: NonZeroUsize) -> Option<Str> {
+        #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+        if let Some(pool) = shared::host() {
+            return pool.try_from_raw(raw);
+        }
+        ROOT.lock().validate(raw)
+    }
+
+    /// Reconstruct a `Str` without any checks
+    ///
+    /// # Safety
+    ///
+    /// As for `from_raw`
+    #[cfg(not(feature = "forbid-unsafe"))]
+    pub(crate) unsafe fn from_raw_unchecked(raw: NonZeroUsize) -> Str {
+        Str(NonNull::new_unchecked(raw.get() as *mut u8))
+    }
+
+    /// The value of the handle
+    #[cfg(not(feature = "forbid-unsafe"))]
+    fn addr(self) -> usize {
+        self.0.as_ptr() as usize
+    }
+
+    /// Make the handle of the entry at `entry`, which holds a string of
+    /// `len` bytes, and is a static record if `is_static`.
+    ///
+    /// # Safety
+    ///
+    /// `entry` must be a complete entry in static memory.
+    #[cfg(not(feature = "forbid-unsafe"))]
+    unsafe fn from_entry(entry: NonNull<u8>, len: usize, is_static: bool) -> Str {
+        match LEN_SHIFT {
+            None => Str(entry),
+            Some(shift) => {
+                let tag = (len << shift) | ((is_static as usize) << (shift - 1));
+                Str(NonNull::new_unchecked(entry.as_ptr().wrapping_add(tag)))
+            }
+        }
+    }
+
+    /// The length of the string in bytes
+    pub fn len(&self) -> usize {
+        match LEN_SHIFT {
+            None => self.get().len(),
+            Some(shift) => self.addr() >> shift,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The address of the entry the handle refers to
+    #[cfg(not(feature = "forbid-unsafe"))]
+    fn entry(&self) -> *const u8 {
+        let ptr = self.0.as_ptr() as *const u8;
+        match LEN_SHIFT {
+            None => ptr,
+            Some(shift) => ptr.wrapping_sub(ptr as usize >> (shift - 1) << (shift - 1)),
+        }
+    }
+
+    /// The address of the trailer after the entry of the string, which
+    /// must be in a chunk, since the common strings have no trailers
+    #[cfg(all(
+        not(feature = "forbid-unsafe"),
+        any(feature = "name-hash", feature = "ids")
+    ))]
+    fn trailer_ptr(&self) -> *const u8 {
+        let entry = self.entry();
+        // SAFETY: the handle refers to a complete entry
+        unsafe {
+            entry.add(match *entry {
+                n if n < u8::MAX => 1 + n as usize,
+                _ if *entry.add(1) == INLINE => 2 + MAX_LEN,
+                _ => STATIC_RECORD_LEN,
+            })
+        }
+    }
+
+    /// Where the entry of the string is in the pool, as the index of
+    /// its chunk in allocation order and its byte offset in that
+    /// chunk, or None for the built in common strings, which aren't in
+    /// any chunk. Chunk indexes match `chunk_report`. This takes the
+    /// pool lock and searches the chunks, so it is meant for debugging
+    /// and analysis tools.
+    pub fn location(self) -> Option<(usize, usize)> {
+        let root = ROOT.lock();
+        root.chunks
+            .iter()
+            .enumerate()
+            .find_map(|(i, c)| Some((i, c.offset_of(self)?)))
+    }
+
+    #[cfg(not(feature = "forbid-unsafe"))]
     fn get(&self) -> &'static str {
         unsafe {
-            let len = *self.0 as usize;
-            let ptr = self.0.wrapping_add(1);
+            let (mut ptr, mut len, is_static) = match LEN_SHIFT {
+                None => {
+                    let ptr = self.0.as_ptr() as *const u8;
+                    let len = *ptr as usize;
+                    let is_static = len == u8::MAX as usize && *ptr.wrapping_add(1) == STATIC;
+                    (ptr, len, is_static)
+                }
+                Some(shift) => {
+                    let is_static = (self.0.as_ptr() as usize >> (shift - 1)) & 1 == 1;
+                    (self.entry(), self.len(), is_static)
+                }
+            };
+            if is_static {
+                ptr = ptr.wrapping_add(2);
+                if LEN_SHIFT.is_none() {
+                    len = *ptr.wrapping_add(mem::size_of::<usize>()) as usize;
+                }
+                ptr = (ptr as *const *const u8).read_unaligned();
+            } else if len == u8::MAX as usize {
+                ptr = ptr.wrapping_add(2);
+            } else {
+                ptr = ptr.wrapping_add(1);
+            }
             let slice = slice::from_raw_parts(ptr, len);
             str::from_utf8_unchecked(slice)
         }
@@ -104,15 +1534,29 @@ impl AsRef<str> for Str {
     }
 }
 
+impl fmt::Debug for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
 impl Hash for Str {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        (&**self).hash(state)
+        (**self).hash(state)
     }
 }
 
 impl PartialEq for Str {
     fn eq(&self, other: &Self) -> bool {
-        &**self == &**other
+        // equal strings are almost always the same handle, so check
+        // that before looking at the bytes
+        self.addr() == other.addr() || **self == **other
     }
 }
 
@@ -120,37 +1564,67 @@ impl Eq for Str {}
 
 impl PartialOrd for Str {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        (&**self).partial_cmp(&**other)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Str {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        (&**self).cmp(&**other)
+        (**self).cmp(&**other)
     }
 }
 
 impl TryFrom<&str> for Str {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(s: &str) -> Result<Self, Self::Error> {
-        if s.as_bytes().len() > u8::MAX as usize {
-            bail!("string is too long")
-        } else {
-            let mut root = ROOT.lock();
-	    match root.all.get(s) {
-                Some(t) => Ok(*t),
-                None => unsafe {
-		    let (r, t) = (*root.root).insert(s);
-		    root.root = r;
-		    root.all.insert(t);
-		    Ok(t)
-                }
-	    }
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn try_from(s: &str) -> Result<Self> {
+        #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+        if let Some(pool) = shared::host() {
+            return pool.intern(s);
+        }
+        if let Some(t) = common::get(s) {
+            stats::hit(t);
+            return Ok(t);
+        }
+        if let Some(res) = frozen::get(s) {
+            let t = res?;
+            stats::hit(t);
+            return Ok(t);
+        }
+        // hash before taking the lock so only the probe and the insert
+        // happen under it
+        let hash = index::hash(s);
+        match index::get_unlocked(hash, s) {
+            Some(t) => {
+                stats::hit(t);
+                Ok(t)
+            }
+            None => ROOT.lock().intern_hashed(hash, s),
         }
     }
 }
 
+/// Interns the string, so `Str` works with `parse` and with
+/// serde_with's `DisplayFromStr`
+impl str::FromStr for Str {
+    type Err = Error;
+
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn from_str(s: &str) -> Result<Self> {
+        Str::try_from(s)
+    }
+}
+
+impl TryFrom<&[u8]> for Str {
+    type Error = Error;
+
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Str::from_utf8(bytes)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -173,6 +1647,316 @@ mod test {
         assert_eq!(t0.0, t1.0);
     }
 
+    #[test]
+    fn test_too_long() {
+        let s = "a".repeat(256);
+        assert!(matches!(
+            Str::try_from(s.as_str()),
+            Err(Error::TooLong { len: 256 })
+        ));
+        assert_eq!(&*Str::try_from(&s[..255]).unwrap(), &s[..255]);
+    }
+
+    #[test]
+    fn test_static() {
+        static S: &str = "a string literal that is long enough to be referenced";
+        let t0 = Str::from_static(S).unwrap();
+        assert_eq!(t0.as_ptr(), S.as_ptr());
+        let t1 = Str::try_from(String::from(S).as_str()).unwrap();
+        assert_eq!(t0.0, t1.0);
+        let t2 = Str::from_static("short").unwrap();
+        assert_eq!(&*t2, "short");
+    }
+
+    #[test]
+    fn test_nonblocking() {
+        let t0 = Str::try_from("nonblocking").unwrap();
+        let t1 = Str::try_intern_nonblocking("nonblocking").unwrap();
+        assert_eq!(t0.0, t1.0);
+        let _guard = ROOT.lock();
+        assert!(Str::try_intern_nonblocking("nonblocking new").is_none());
+        #[cfg(all(feature = "dashmap", not(feature = "arc-swap")))]
+        assert_eq!(Str::try_intern_nonblocking("nonblocking"), Some(t0));
+        #[cfg(not(any(feature = "dashmap", feature = "arc-swap")))]
+        assert!(Str::try_intern_nonblocking("nonblocking").is_none());
+    }
+
+    #[test]
+    fn test_as_static() {
+        let s: &'static str = {
+            let t = Str::try_from("as static").unwrap();
+            t.as_static()
+        };
+        assert_eq!(s, "as static");
+    }
+
+    #[test]
+    #[cfg(not(feature = "forbid-unsafe"))]
+    fn test_raw() {
+        let t0 = Str::try_from("raw").unwrap();
+        let t1 = unsafe { Str::from_raw(t0.as_raw()) };
+        assert_eq!(t0.0, t1.0);
+        assert_eq!(&*t1, "raw");
+    }
+
+    #[test]
+    fn test_try_from_raw() {
+        let plain = Str::try_from("try from raw").unwrap();
+        let inline = Str::try_from("r".repeat(MAX_LEN).as_str()).unwrap();
+        let record = Str::from_static("a static string for try_from_raw").unwrap();
+        let common = Str::try_from("7").unwrap();
+        for t in [plain, inline, record, common] {
+            assert_eq!(
+                Str::try_from_raw(t.as_raw()).map(Str::as_raw),
+                Some(t.as_raw())
+            );
+        }
+        // inside an entry
+        for raw in [plain.as_raw().get() + 1, record.as_raw().get() + 2] {
+            assert!(Str::try_from_raw(NonZeroUsize::new(raw).unwrap()).is_none());
+        }
+        let outside = Box::leak(Box::new(0u64)) as *mut u64 as usize;
+        assert!(Str::try_from_raw(NonZeroUsize::new(outside).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_keywords() {
+        declare_keywords! {
+            Fn = "fn",
+            Let = "let",
+            Match = "match",
+        }
+        for k in Keyword::ALL {
+            let s = Str::try_from(k.as_str()).unwrap();
+            assert_eq!(s.0, k.as_str_interned().0);
+            assert_eq!(Keyword::from_str(s), Some(*k));
+        }
+        assert_eq!(Keyword::from_str(Str::try_from("letx").unwrap()), None);
+    }
+
+    #[test]
+    fn test_slice() {
+        let s: &'static str = {
+            let t = Str::try_from("slice me").unwrap();
+            t.slice(6..)
+        };
+        assert_eq!(s, "me");
+        let t = Str::try_from("é").unwrap();
+        assert_eq!(t.get_slice(..1), None);
+        assert_eq!(t.get_slice(..), Some("é"));
+    }
+
+    #[test]
+    fn test_split() {
+        let (parts, lines): (Vec<&'static str>, Vec<&'static str>) = {
+            let t = Str::try_from("a::b::c\nd").unwrap();
+            (t.split_static("::").collect(), t.lines_static().collect())
+        };
+        assert_eq!(parts, ["a", "b", "c\nd"]);
+        assert_eq!(lines, ["a::b::c", "d"]);
+    }
+
+    #[test]
+    fn test_starts_with_str() {
+        let t = |s| Str::try_from(s).unwrap();
+        let path = t("/api/v1/users");
+        assert!(path.starts_with_str(t("/api/")));
+        assert!(path.starts_with_str(path));
+        assert!(path.starts_with_str(t("")));
+        assert!(!path.starts_with_str(t("/apx")));
+        assert!(!t("/api").starts_with_str(path));
+        assert!(path.ends_with_str(t("users")));
+        assert!(path.ends_with_str(t("")));
+        assert!(!path.ends_with_str(t("user")));
+        assert!(!t("users").ends_with_str(path));
+        // prefixes and suffixes referencing the same static bytes
+        static ROUTE: &str = "/static/route/prefix/that/is/long/enough/to/reference";
+        let whole = Str::from_static(ROUTE).unwrap();
+        assert!(whole.starts_with_str(Str::from_static(&ROUTE[..40]).unwrap()));
+        assert!(whole.ends_with_str(Str::from_static(&ROUTE[10..]).unwrap()));
+        assert!(!whole.ends_with_str(Str::from_static(&ROUTE[..41]).unwrap()));
+    }
+
+    #[cfg(not(feature = "single-threaded"))]
+    #[test]
+    fn test_concurrent() {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..1000)
+                        .map(|i| Str::try_from(format!("concurrent {i}").as_str()).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let res: Vec<Vec<Str>> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        for r in &res[1..] {
+            for (a, b) in r.iter().zip(&res[0]) {
+                assert_eq!(a.as_raw(), b.as_raw());
+            }
+        }
+    }
+
+    #[test]
+    fn test_len() {
+        static LONG: &str = "a static string long enough to get a pointer record";
+        let max = "m".repeat(MAX_LEN);
+        for s in ["", "7", "len", LONG, max.as_str()] {
+            let t = Str::try_from(s).unwrap();
+            assert_eq!((t.len(), t.is_empty(), &*t), (s.len(), s.is_empty(), s));
+        }
+        let t = Str::from_static(LONG).unwrap();
+        assert_eq!((t.len(), &*t), (LONG.len(), LONG));
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = Str::try_from("eq a").unwrap();
+        let b = Str::try_from(String::from("eq a").as_str()).unwrap();
+        let c = Str::try_from("eq c").unwrap();
+        assert!(a == b && a.0 == b.0);
+        assert!(a != c);
+    }
+
+    #[test]
+    fn test_is_internable() {
+        let max = "x".repeat(Str::MAX_LEN);
+        assert!(Str::is_internable(&max));
+        assert!(Str::is_internable(""));
+        assert!(!Str::is_internable(&format!("{max}x")));
+        assert_eq!(Str::try_from(max.as_str()).unwrap().len(), Str::MAX_LEN);
+    }
+
+    #[test]
+    fn test_try_intern() {
+        assert_eq!(Str::try_intern("try intern").as_deref(), Some("try intern"));
+        assert!(Str::try_intern(&"x".repeat(Str::MAX_LEN + 1)).is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let t: Str = "parsed".parse().unwrap();
+        assert_eq!(t, Str::try_from("parsed").unwrap());
+        assert_eq!(format!("{t:>8}"), "  parsed");
+        assert!("x".repeat(Str::MAX_LEN + 1).parse::<Str>().is_err());
+    }
+
+    #[test]
+    fn test_intern_truncated() {
+        let long = format!("{}é", "t".repeat(MAX_LEN - 1));
+        let t = Str::intern_truncated(&long).unwrap();
+        assert_eq!(t.len(), MAX_LEN - 1);
+        assert!(long.starts_with(&*t));
+        assert_eq!(&*Str::intern_truncated("truncated").unwrap(), "truncated");
+    }
+
+    #[test]
+    fn test_from_utf8() {
+        let t = Str::from_utf8(b"from utf8").unwrap();
+        assert_eq!(t, Str::try_from(&b"from utf8"[..]).unwrap());
+        assert!(matches!(Str::from_utf8(b"bad \xff"), Err(Error::Utf8(_))));
+        assert!(matches!(
+            Str::from_utf8(&[0xff; MAX_LEN + 1]),
+            Err(Error::TooLong { len }) if len == MAX_LEN + 1
+        ));
+    }
+
+    #[test]
+    fn test_location() {
+        let a = Str::try_from("location a").unwrap();
+        let (chunk, offset) = a.location().unwrap();
+        assert!(offset < chunk_report()[chunk].capacity);
+        assert_ne!(
+            Str::try_from("location b").unwrap().location(),
+            Some((chunk, offset))
+        );
+        assert_eq!(Str::try_from("1").unwrap().location(), None);
+    }
+
+    #[test]
+    fn test_repeat() {
+        assert_eq!(&*Str::repeat("  ", 4).unwrap(), "        ");
+        assert_eq!(&*Str::repeat("ab", 0).unwrap(), "");
+        let t = Str::repeat("é", MAX_LEN / 2).unwrap();
+        assert_eq!(t.len(), MAX_LEN - 1);
+        assert!(matches!(
+            Str::repeat("ab", MAX_LEN),
+            Err(Error::TooLong { len }) if len == 2 * MAX_LEN
+        ));
+        assert!(Str::repeat("ab", usize::MAX).is_err());
+        assert_eq!(&*Str::repeat("", usize::MAX).unwrap(), "");
+        assert_eq!(&*Str::repeat("", 3).unwrap(), "");
+    }
+
+    #[test]
+    fn test_chunk_size() {
+        const K: usize = 1024;
+        let ms = |n| Some(Duration::from_millis(n));
+        // a burst of short strings grows as fast as it is allowed to
+        assert_eq!(chunk_size(4 * K, (4 * K, 4 * K), 240, ms(1), K * K), 16 * K);
+        assert_eq!(
+            chunk_size(20 * K, (16 * K, 16 * K), 960, ms(4), K * K),
+            64 * K
+        );
+        assert_eq!(chunk_size(4 * K, (4 * K, 4 * K), 240, ms(1), 8 * K), 8 * K);
+        // a steady rate gets chunks that last about a second
+        assert_eq!(
+            chunk_size(96 * K, (64 * K, 64 * K), 4000, ms(500), K * K),
+            128 * K
+        );
+        // a trickle keeps the size it has, however much is allocated
+        assert_eq!(
+            chunk_size(4 * K, (4 * K, 4 * K), 240, ms(5000), K * K),
+            4 * K
+        );
+        assert_eq!(
+            chunk_size(512 * K, (256 * K, 256 * K), 9000, ms(60_000), K * K),
+            256 * K
+        );
+        // long strings get room for more than a handful of them
+        assert_eq!(
+            chunk_size(4 * K, (4 * K, 4 * K), 16, ms(5000), K * K),
+            16 * K
+        );
+        // without a clock chunks double the pool
+        assert_eq!(chunk_size(12 * K, (8 * K, 8 * K), 500, None, K * K), 12 * K);
+        assert_eq!(
+            chunk_size(4 * K, (4 * K, 4 * K), 0, Some(Duration::ZERO), K * K),
+            16 * K
+        );
+    }
+
+    #[test]
+    fn test_intern() {
+        assert_eq!(intern("intern fn"), Str::try_from("intern fn").unwrap());
+        let long = "x".repeat(MAX_LEN + 1);
+        let e = std::panic::catch_unwind(|| intern(&long)).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<String>().unwrap(),
+            "can't intern a string of 256 bytes, the limit is 255"
+        );
+    }
+
+    #[test]
+    fn test_rollback() {
+        use std::panic::{self, AssertUnwindSafe};
+        let s = "rolled back";
+        let mut root = ROOT.lock();
+        let before: Vec<Str> = root.strings().collect();
+        let r = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mark = root.mark();
+            let id = root.next_id();
+            let t = root.write_with(s, |chunk| chunk.insert(s, id, 1)).unwrap();
+            let _guard = Rollback::new(&mut root, mark);
+            panic!("indexing {t:?} failed")
+        }));
+        assert!(r.is_err());
+        assert_eq!(root.strings().collect::<Vec<_>>(), before);
+        assert!(root.all.get(index::hash(s), s).is_none());
+        let t = root.intern(s).unwrap();
+        assert_eq!(root.strings().last(), Some(t));
+    }
+
     #[test]
     fn test_lots() {
         for _ in 0..1000000 {
@@ -0,0 +1,191 @@
+//! What happens when the memory limit is reached
+
+use crate::{Result, Str, ROOT};
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// What interning does when storing a new string would exceed the
+/// limit set by `set_memory_limit`
+#[derive(Debug, Clone, Copy, Default)]
+pub enum LimitPolicy {
+    /// Fail with `Error::CapacityExceeded`
+    #[default]
+    Error,
+    /// Panic. The pool is left unchanged, so it is still usable if
+    /// the panic is caught.
+    Panic,
+    /// Call the function with the string that didn't fit, then fail
+    /// with `Error::CapacityExceeded`. The function runs while the
+    /// pool lock is held, so it must not intern anything; it is meant
+    /// for raising an alert or flagging that load should be shed.
+    Callback(fn(&str)),
+}
+
+/// Set what interning does when the memory limit is reached. Callers
+/// that would rather fall back to the uninterned string than see an
+/// error can use `Str::intern_or_borrow` instead.
+pub fn set_limit_policy(policy: LimitPolicy) {
+    ROOT.lock().policy = policy;
+}
+
+/// A string that was interned if that was possible, and borrowed if
+/// it wasn't, for APIs that take both interned strings and transient
+/// or over long ones without interning every one. Equality, ordering
+/// and hashing are those of the string, whichever form it is in, so
+/// an interned and a borrowed copy of a string are equal.
+#[derive(Debug, Clone, Copy)]
+pub enum MaybeInterned<'a> {
+    Interned(Str),
+    Borrowed(&'a str),
+}
+
+impl<'a> MaybeInterned<'a> {
+    /// The interned string, if it was interned
+    pub fn interned(&self) -> Option<Str> {
+        match self {
+            MaybeInterned::Interned(t) => Some(*t),
+            MaybeInterned::Borrowed(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            MaybeInterned::Interned(t) => t.as_static(),
+            MaybeInterned::Borrowed(s) => s,
+        }
+    }
+
+    /// The interned string, interning it if it is borrowed
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn into_interned(self) -> Result<Str> {
+        match self {
+            MaybeInterned::Interned(t) => Ok(t),
+            MaybeInterned::Borrowed(s) => Str::try_from(s),
+        }
+    }
+}
+
+impl From<Str> for MaybeInterned<'_> {
+    fn from(t: Str) -> Self {
+        MaybeInterned::Interned(t)
+    }
+}
+
+impl<'a> From<&'a str> for MaybeInterned<'a> {
+    fn from(s: &'a str) -> Self {
+        MaybeInterned::Borrowed(s)
+    }
+}
+
+impl PartialEq for MaybeInterned<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (MaybeInterned::Interned(t), MaybeInterned::Interned(u)) => t == u,
+            _ => self.as_str() == other.as_str(),
+        }
+    }
+}
+
+impl Eq for MaybeInterned<'_> {}
+
+impl PartialEq<str> for MaybeInterned<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for MaybeInterned<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<Str> for MaybeInterned<'_> {
+    fn eq(&self, other: &Str) -> bool {
+        match self {
+            MaybeInterned::Interned(t) => t == other,
+            MaybeInterned::Borrowed(s) => *s == &**other,
+        }
+    }
+}
+
+impl PartialOrd for MaybeInterned<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MaybeInterned<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for MaybeInterned<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Borrow<str> for MaybeInterned<'_> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Deref for MaybeInterned<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for MaybeInterned<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl Str {
+    /// Intern `s`, or return it borrowed if it can't be interned, for
+    /// hot paths that must keep working when the pool is over its
+    /// memory limit. Under `LimitPolicy::Panic` this still panics when
+    /// the limit is reached.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_or_borrow(s: &str) -> MaybeInterned<'_> {
+        match Str::try_from(s) {
+            Ok(t) => MaybeInterned::Interned(t),
+            Err(_) => MaybeInterned::Borrowed(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_maybe_interned() {
+        let t = Str::try_from("maybe interned").unwrap();
+        let line = String::from("maybe interned");
+        let (i, b) = (MaybeInterned::from(t), MaybeInterned::from(line.as_str()));
+        assert_eq!(i, b);
+        assert!(i == "maybe interned" && b == *"maybe interned");
+        assert!(i == t && b == t);
+        assert!(MaybeInterned::from("maybe a") < b);
+        let mut m = HashMap::new();
+        m.insert(i, 1);
+        assert_eq!(m.get(&b), Some(&1));
+        assert_eq!(m.get("maybe interned"), Some(&1));
+        assert_eq!(b.into_interned().unwrap(), t);
+        let long = "x".repeat(Str::MAX_LEN + 1);
+        assert!(MaybeInterned::from(long.as_str()).into_interned().is_err());
+    }
+}
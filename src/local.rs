@@ -0,0 +1,228 @@
+//! Interning into a private pool and publishing it to the global one
+
+use crate::{Error, Interner, Result, Str, ROOT};
+use fxhash::FxHasher;
+use hashbrown::HashTable;
+use std::{
+    hash::{Hash, Hasher},
+    ops::Index,
+};
+
+/// The key of a string in a `LocalPool`, which is its position in the
+/// order the pool first saw it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LocalKey(u32);
+
+impl LocalKey {
+    /// The position of the string in its pool
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A hashconsed pool owned by one thread, that never takes the global
+/// lock, so workers can each build a symbol table in parallel. Its
+/// strings are stored packed in a buffer of its own and freed with it,
+/// unless `commit` publishes them to the global pool.
+#[derive(Debug, Default, Clone)]
+pub struct LocalPool {
+    /// the bytes of every string, in the order they were interned
+    bytes: String,
+    /// the end of each string in `bytes`
+    ends: Vec<u32>,
+    /// the key of each string, by the hash of the string
+    keys: HashTable<u32>,
+}
+
+fn hash(s: &str) -> u64 {
+    let mut h = FxHasher::default();
+    s.hash(&mut h);
+    h.finish()
+}
+
+impl LocalPool {
+    pub fn new() -> Self {
+        LocalPool::default()
+    }
+
+    /// The number of distinct strings in the pool
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    fn str(&self, key: u32) -> &str {
+        let i = key as usize;
+        let start = if i == 0 { 0 } else { self.ends[i - 1] as usize };
+        &self.bytes[start..self.ends[i] as usize]
+    }
+
+    /// Intern `s`, returning the key of the existing copy if there is
+    /// one. Fails with `Error::TooLong` for a string the global pool
+    /// couldn't take, and with `Error::CapacityExceeded` once the pool
+    /// holds 4 GiB.
+    pub fn intern(&mut self, s: &str) -> Result<LocalKey> {
+        if !Str::is_internable(s) {
+            return Err(Error::TooLong { len: s.len() });
+        }
+        let h = hash(s);
+        if let Some(k) = self.keys.find(h, |k| self.str(*k) == s) {
+            return Ok(LocalKey(*k));
+        }
+        let end = u32::try_from(self.bytes.len() + s.len()).map_err(|_| Error::CapacityExceeded)?;
+        let key = self.ends.len() as u32;
+        self.bytes.try_reserve(s.len())?;
+        self.ends.try_reserve(1)?;
+        self.bytes.push_str(s);
+        self.ends.push(end);
+        let (bytes, ends) = (&self.bytes, &self.ends);
+        self.keys.insert_unique(h, key, |k| {
+            let i = *k as usize;
+            let start = if i == 0 { 0 } else { ends[i - 1] as usize };
+            hash(&bytes[start..ends[i] as usize])
+        });
+        Ok(LocalKey(key))
+    }
+
+    /// Find the key of `s`, if it is in the pool
+    pub fn get(&self, s: &str) -> Option<LocalKey> {
+        self.keys
+            .find(hash(s), |k| self.str(*k) == s)
+            .map(|k| LocalKey(*k))
+    }
+
+    /// The string `key` was returned for
+    ///
+    /// # Panics
+    ///
+    /// If `key` is from another pool with more strings.
+    pub fn resolve(&self, key: LocalKey) -> &str {
+        self.str(key.0)
+    }
+
+    /// Every string in the pool with its key, in key order
+    pub fn iter(&self) -> impl Iterator<Item = (LocalKey, &str)> + '_ {
+        (0..self.len() as u32).map(|k| (LocalKey(k), self.str(k)))
+    }
+
+    /// Intern every string into the global pool under a single lock,
+    /// so no other thread's insert lands in between, returning the
+    /// handle of each key. If it fails, the strings before the one
+    /// that failed stay in the global pool.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn commit(self) -> Result<Remap> {
+        let mut root = ROOT.lock();
+        root.all.try_reserve(self.len())?;
+        let strs = (0..self.len() as u32)
+            .map(|k| root.intern(self.str(k)))
+            .collect::<Result<_>>()?;
+        Ok(Remap(strs))
+    }
+}
+
+impl Interner for LocalPool {
+    type Key = LocalKey;
+    type Error = Error;
+
+    fn intern(&mut self, s: &str) -> Result<LocalKey> {
+        LocalPool::intern(self, s)
+    }
+
+    fn get(&self, s: &str) -> Option<LocalKey> {
+        LocalPool::get(self, s)
+    }
+
+    fn resolve(&self, key: LocalKey) -> &str {
+        LocalPool::resolve(self, key)
+    }
+}
+
+/// The global handle of every string of a committed `LocalPool`, by
+/// its local key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remap(Vec<Str>);
+
+impl Remap {
+    /// The global handle of `key`, or None if it isn't from the pool
+    /// that was committed
+    pub fn get(&self, key: LocalKey) -> Option<Str> {
+        self.0.get(key.index()).copied()
+    }
+
+    /// The number of strings that were committed
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The global handles in key order
+    pub fn into_vec(self) -> Vec<Str> {
+        self.0
+    }
+}
+
+impl Index<LocalKey> for Remap {
+    type Output = Str;
+
+    fn index(&self, key: LocalKey) -> &Str {
+        &self.0[key.index()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Global;
+    use std::thread;
+
+    #[test]
+    fn test_local_pool() {
+        let pools: Vec<(LocalPool, Vec<LocalKey>)> = thread::scope(|s| {
+            let workers: Vec<_> = (0..4)
+                .map(|w| {
+                    s.spawn(move || {
+                        let mut pool = LocalPool::new();
+                        let keys: Vec<LocalKey> = (0..500)
+                            .map(|i| pool.intern(&format!("local {}", (i * (w + 1)) % 700)))
+                            .collect::<Result<_>>()
+                            .unwrap();
+                        (pool, keys)
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        // nothing is in the global pool until it is committed
+        assert!(Global.get("local 3").is_none());
+        let (pool, keys) = &pools[0];
+        assert_eq!(pool.len(), 500);
+        assert_eq!(pool.get("local 7"), Some(keys[7]));
+        assert_eq!(pool.resolve(keys[499]), "local 499");
+        assert_eq!(pool.get("local 500"), None);
+        assert_eq!(pool.iter().nth(3), Some((keys[3], "local 3")));
+        assert_eq!(pools[1].0.len(), 350);
+        assert_eq!(pools[1].1[350], pools[1].1[0]);
+        for (pool, keys) in pools {
+            let strs: Vec<String> = keys.iter().map(|k| pool.resolve(*k).into()).collect();
+            let zero = pool.get("local 0").unwrap();
+            let remap = pool.commit().unwrap();
+            for (k, s) in keys.iter().zip(&strs) {
+                assert_eq!(&*remap[*k], s);
+                assert_eq!(remap.get(*k), Global.get(s));
+            }
+            assert_eq!(remap.get(zero), Global.get("local 0"));
+        }
+        let mut pool = LocalPool::new();
+        assert!(matches!(
+            pool.intern(&"x".repeat(Str::MAX_LEN + 1)),
+            Err(Error::TooLong { .. })
+        ));
+        assert!(pool.commit().unwrap().is_empty());
+    }
+}
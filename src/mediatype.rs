@@ -0,0 +1,167 @@
+//! Interned media types
+
+use crate::{stackbuf::StackBuf, Error, Result, Str};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// A media type such as `text/html` or `application/vnd.api+json`,
+/// checked to be a `type/subtype` pair of HTTP tokens and interned in
+/// lower case, since media types are case insensitive. The type and
+/// subtype are interned as well, so reading them is free. Equality,
+/// ordering and hashing are those of the whole string.
+#[derive(Clone, Copy)]
+pub struct MediaType {
+    full: Str,
+    type_: Str,
+    subtype: Str,
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+impl MediaType {
+    /// The whole media type
+    pub fn get(self) -> Str {
+        self.full
+    }
+
+    /// The part before the slash, such as `text`
+    pub fn type_(self) -> Str {
+        self.type_
+    }
+
+    /// The part after the slash, such as `html`
+    pub fn subtype(self) -> Str {
+        self.subtype
+    }
+
+    /// The media type of a `Content-Type` header value, ignoring its
+    /// parameters and the whitespace around it
+    pub fn from_content_type(value: &str) -> Result<MediaType> {
+        let essence = value.split(';').next().unwrap_or("");
+        MediaType::try_from(essence.trim_matches([' ', '\t']))
+    }
+}
+
+impl TryFrom<&str> for MediaType {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        let (type_, subtype) = match s.split_once('/') {
+            Some((t, st)) if is_token(t) && is_token(st) => (t, st),
+            _ => return Err(Error::Invalid("media type must be a type/subtype pair")),
+        };
+        if s.bytes().any(|b| b.is_ascii_uppercase()) {
+            let mut buf = StackBuf::new();
+            for b in s.bytes() {
+                buf.push(b.to_ascii_lowercase() as char);
+            }
+            return MediaType::try_from(buf.intern()?);
+        }
+        Ok(MediaType {
+            full: Str::try_from(s)?,
+            type_: Str::try_from(type_)?,
+            subtype: Str::try_from(subtype)?,
+        })
+    }
+}
+
+impl TryFrom<Str> for MediaType {
+    type Error = Error;
+
+    fn try_from(s: Str) -> Result<Self> {
+        MediaType::try_from(s.as_static())
+    }
+}
+
+impl From<MediaType> for Str {
+    fn from(m: MediaType) -> Str {
+        m.full
+    }
+}
+
+impl Deref for MediaType {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.full.as_static()
+    }
+}
+
+impl PartialEq for MediaType {
+    fn eq(&self, other: &Self) -> bool {
+        self.full == other.full
+    }
+}
+
+impl Eq for MediaType {}
+
+impl PartialOrd for MediaType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MediaType {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.full.cmp(&other.full)
+    }
+}
+
+impl Hash for MediaType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.full.hash(state)
+    }
+}
+
+impl fmt::Debug for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.full, f)
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.full, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_media_type() {
+        let m = MediaType::try_from("Application/VND.api+JSON").unwrap();
+        assert_eq!(&*m, "application/vnd.api+json");
+        assert_eq!(m, MediaType::try_from("application/vnd.api+json").unwrap());
+        assert_eq!(&*m.type_(), "application");
+        assert_eq!(m.subtype(), Str::try_from("vnd.api+json").unwrap());
+        let m = MediaType::from_content_type(" text/HTML ; charset=UTF-8").unwrap();
+        assert_eq!(
+            (&*m, &*m.type_(), &*m.subtype()),
+            ("text/html", "text", "html")
+        );
+        for bad in [
+            "",
+            "text",
+            "text/",
+            "/html",
+            "text/html/x",
+            "text /html",
+            "text/ht ml",
+        ] {
+            assert!(
+                matches!(MediaType::try_from(bad), Err(Error::Invalid(_))),
+                "{bad}"
+            );
+        }
+    }
+}
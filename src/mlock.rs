@@ -0,0 +1,47 @@
+//! Keeping chunk memory resident, behind the `mlock` feature
+
+#[cfg(not(feature = "forbid-unsafe"))]
+use crate::Error;
+use crate::{Chunk, Result, ROOT};
+
+/// Fault in and lock into RAM every chunk allocated from now on, or
+/// stop doing so. Reading an interned string then never takes a page
+/// fault on first touch, and its bytes are never swapped out, which
+/// matters on hot paths where a fault costs more than the lookup.
+/// Call it at startup, before anything is interned, since chunks that
+/// have already been allocated aren't affected.
+///
+/// Locking is done with `mlock` on unix targets. It is limited by
+/// `RLIMIT_MEMLOCK`, and when a new chunk can't be locked growing the
+/// pool fails with `Error::AllocFailed`. Other targets only fault the
+/// pages in, and with `forbid-unsafe` this has no effect.
+pub fn set_lock_chunks(lock: bool) {
+    ROOT.lock().lock_chunks = lock;
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+impl Chunk {
+    /// Write every page of a new chunk, and lock it on unix targets,
+    /// freeing it if that fails
+    pub(crate) fn lock(self) -> Result<Self> {
+        let ptr = self.base.as_ptr();
+        // SAFETY: the chunk owns capacity bytes at base, none of which
+        // have been handed out yet
+        unsafe { ptr.write_bytes(0, self.capacity) };
+        #[cfg(unix)]
+        // SAFETY: the range is the allocation of the chunk
+        if unsafe { libc::mlock(ptr as *const libc::c_void, self.capacity) } != 0 {
+            self.release();
+            return Err(Error::AllocFailed);
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl Chunk {
+    /// There is no chunk memory to lock
+    pub(crate) fn lock(self) -> Result<Self> {
+        Ok(self)
+    }
+}
@@ -0,0 +1,156 @@
+//! Stable name hashes, behind the `name-hash` feature.
+//!
+//! Engines and asset pipelines often refer to names by hash, so the
+//! hash has to be the same on every platform, in every build, and in
+//! tools written in other languages. The name hash is the 64 bit
+//! FNV-1a hash of the UTF-8 bytes of the string, and the 32 bit hash
+//! is the exclusive or of its high and low halves. With the feature
+//! every entry in a chunk is followed by the 64 bit hash of its
+//! string, computed when the string is interned, so reading it back
+//! costs a load instead of a pass over the string, for 8 bytes per
+//! string. The built in common strings are at most 5 bytes long, and
+//! their hashes are computed when asked for, as are the hashes of all
+//! strings with `forbid-unsafe`.
+//!
+//! The pool also keeps every string in a table by name hash, for 8
+//! bytes and a control byte per string, so a wire format that sends
+//! precomputed name hashes can resolve them with `get_by_hash` without
+//! hashing anything on the receiver.
+
+use crate::{common, Str, ROOT};
+use hashbrown::{HashTable, TryReserveError};
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The 64 bit name hash of `s`, the same as `Str::name_hash64` of the
+/// interned `s`, for computing hashes at compile time or without
+/// interning
+pub const fn name_hash64(s: &str) -> u64 {
+    let s = s.as_bytes();
+    let mut hash = OFFSET_BASIS;
+    let mut i = 0;
+    while i < s.len() {
+        hash = (hash ^ s[i] as u64).wrapping_mul(PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// The 32 bit name hash of `s`, the same as `Str::name_hash32` of the
+/// interned `s`
+pub const fn name_hash32(s: &str) -> u32 {
+    fold(name_hash64(s))
+}
+
+const fn fold(hash: u64) -> u32 {
+    (hash ^ (hash >> 32)) as u32
+}
+
+/// Every string in the pool, including the common strings, by name
+/// hash
+pub(crate) struct ByHash(HashTable<Str>);
+
+impl ByHash {
+    pub(crate) fn new() -> Self {
+        let mut table = HashTable::with_capacity(common::COUNT as usize);
+        for t in common::all() {
+            table.insert_unique(t.name_hash64(), t, |t| t.name_hash64());
+        }
+        ByHash(table)
+    }
+
+    pub(crate) fn try_reserve(&mut self, n: usize) -> Result<(), TryReserveError> {
+        self.0.try_reserve(n, |t| t.name_hash64())
+    }
+
+    /// Insert `t`, which was just added to the pool
+    pub(crate) fn insert(&mut self, t: Str) {
+        self.0
+            .insert_unique(t.name_hash64(), t, |t| t.name_hash64());
+    }
+}
+
+/// The interned string whose 64 bit name hash is `hash`, if it is
+/// `verify`. Only the bytes of the string the hash leads to are
+/// compared with `verify`, nothing is hashed.
+pub fn get_by_hash(hash: u64, verify: &str) -> Option<Str> {
+    ROOT.lock()
+        .by_hash
+        .0
+        .find(hash, |t| t.as_bytes() == verify.as_bytes())
+        .copied()
+}
+
+/// The interned string whose 64 bit name hash is `hash`, without
+/// checking it is the string the sender meant, for trusted peers that
+/// only send hashes of strings they know are interned. Distinct strings
+/// with the same name hash are possible, if unlikely, and this returns
+/// any one of them; use `get_by_hash` when that matters.
+pub fn get_by_hash_unverified(hash: u64) -> Option<Str> {
+    ROOT.lock().by_hash.0.find(hash, |_| true).copied()
+}
+
+impl Str {
+    /// The 64 bit name hash of the string, which is stable across
+    /// platforms, builds and versions of the crate, so it can be stored
+    /// in files. It is read from the pool, not recomputed.
+    pub fn name_hash64(self) -> u64 {
+        #[cfg(not(feature = "forbid-unsafe"))]
+        if common::id(self).is_none() {
+            // SAFETY: the trailer of every entry in a chunk starts with
+            // the name hash of its string
+            return u64::from_le_bytes(unsafe { (self.trailer_ptr() as *const [u8; 8]).read() });
+        }
+        name_hash64(self.as_static())
+    }
+
+    /// The 32 bit name hash of the string, with the same stability as
+    /// `name_hash64`
+    pub fn name_hash32(self) -> u32 {
+        fold(self.name_hash64())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MAX_LEN;
+
+    #[test]
+    fn test_name_hash() {
+        // the FNV-1a test vectors
+        assert_eq!(name_hash64(""), 0xcbf29ce484222325);
+        assert_eq!(name_hash64("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(name_hash64("foobar"), 0x85944171f73967e8);
+        assert_eq!(name_hash32("foobar"), 0x85944171 ^ 0xf73967e8);
+        let long = "n".repeat(Str::MAX_LEN);
+        let statik: &'static str = "a name hash from static memory";
+        for s in ["", "7", "true", "name hash", &long] {
+            let t = Str::try_from(s).unwrap();
+            assert_eq!(t.name_hash64(), name_hash64(s));
+            assert_eq!(t.name_hash32(), name_hash32(s));
+        }
+        let t = Str::from_static(statik).unwrap();
+        assert_eq!(t.name_hash64(), name_hash64(statik));
+    }
+
+    #[test]
+    fn test_get_by_hash() {
+        let t = Str::try_from("by name hash").unwrap();
+        let s = Str::from_static("a static string found by its name hash").unwrap();
+        for t in [t, s, Str::try_from("true").unwrap()] {
+            assert_eq!(get_by_hash(name_hash64(&t), &t), Some(t));
+            assert_eq!(get_by_hash_unverified(name_hash64(&t)), Some(t));
+        }
+        assert_eq!(get_by_hash(name_hash64(&t), "by name hash?"), None);
+        assert_eq!(
+            get_by_hash(
+                name_hash64("never interned by hash"),
+                "never interned by hash"
+            ),
+            None
+        );
+        assert!(get_by_hash_unverified(name_hash64(&"h".repeat(MAX_LEN + 1))).is_none());
+    }
+}
@@ -0,0 +1,50 @@
+//! Interning network addresses
+
+use crate::{stackbuf::StackBuf, Result, Str};
+use std::{
+    fmt::Write,
+    net::{IpAddr, SocketAddr},
+};
+
+impl Str {
+    /// Intern the textual form of `ip`, as `to_string` would give,
+    /// formatted on the stack, so nothing is allocated. IPv6
+    /// addresses are written in their compressed lower case form, so
+    /// each address has a single string.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_ip(ip: IpAddr) -> Result<Str> {
+        let mut buf = StackBuf::new();
+        let _ = write!(buf, "{ip}");
+        buf.intern()
+    }
+
+    /// Intern the textual form of `addr`, such as `10.0.0.1:443` or
+    /// `[::1]:8080`, formatted on the stack
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_socket_addr(addr: SocketAddr) -> Result<Str> {
+        let mut buf = StackBuf::new();
+        let _ = write!(buf, "{addr}");
+        buf.intern()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_intern_ip() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(&*Str::intern_ip(ip).unwrap(), "10.0.0.1");
+        let ip: IpAddr = "2001:DB8:0:0:0:0:0:1".parse().unwrap();
+        assert_eq!(&*Str::intern_ip(ip).unwrap(), "2001:db8::1");
+        let addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 8080);
+        assert_eq!(&*Str::intern_socket_addr(addr).unwrap(), "[::1]:8080");
+        let addr: SocketAddr = "[fe80::1%4]:1".parse().unwrap();
+        assert_eq!(
+            Str::intern_socket_addr(addr).unwrap(),
+            Str::try_from(&*addr.to_string()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Placing chunk memory on NUMA nodes, behind the `numa` feature
+
+use crate::{Chunk, Result, ROOT};
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+use crate::{Error, LEN_SHIFT};
+use std::io;
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+use std::ptr::{self, NonNull};
+
+/// Where the pages of new chunks are placed on a NUMA machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumaPolicy {
+    /// Wherever the kernel puts them, normally on the node of the
+    /// thread that first writes each page, which is the thread that
+    /// interned the first string in it
+    #[default]
+    Default,
+    /// Only on the given node, for pools that are read by threads
+    /// pinned to it
+    Bind(u32),
+    /// Spread page by page over every node the process may use, so
+    /// reading strings costs the same on average from every node
+    Interleave,
+}
+
+/// Set where the pages of chunks allocated from now on are placed. A
+/// pool is one set of chunks shared by every thread, so it can't be
+/// local to every node at once: `Bind` puts it next to the threads
+/// whose latency matters, and `Interleave` evens the cost out instead
+/// of leaving it to whichever thread allocated each chunk. Call it at
+/// startup, before anything is interned, since chunks that have
+/// already been allocated stay where they are.
+///
+/// The policy is applied with `mbind` on Linux, and returns the
+/// kernel's error if it rejects it, for example because the node
+/// doesn't exist. On other targets, and with `forbid-unsafe`, it has
+/// no effect.
+pub fn set_numa_policy(policy: NumaPolicy) -> io::Result<()> {
+    #[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+    {
+        // try the policy on a page of its own, so a bad one is
+        // reported here rather than when the pool next grows
+        let len = page_size();
+        let page = map(len)?;
+        let res = bind(page, len, policy);
+        // SAFETY: page is a mapping of len bytes that nothing uses
+        unsafe { libc::munmap(page.as_ptr().cast(), len) };
+        res?;
+    }
+    ROOT.lock().numa = policy;
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Map `len` bytes of fresh anonymous memory
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+fn map(len: usize) -> io::Result<NonNull<u8>> {
+    // SAFETY: an anonymous private mapping has no preconditions
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(NonNull::new(ptr.cast()).unwrap())
+}
+
+/// Apply `policy` to the `len` bytes at `ptr`, which must be page
+/// aligned and not yet written
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+fn bind(ptr: NonNull<u8>, len: usize, policy: NumaPolicy) -> io::Result<()> {
+    const WORDS: usize = 16;
+    const BITS: usize = WORDS * libc::c_ulong::BITS as usize;
+    let mut mask = [0 as libc::c_ulong; WORDS];
+    let mode = match policy {
+        NumaPolicy::Default => libc::MPOL_DEFAULT,
+        NumaPolicy::Bind(node) => {
+            let node = node as usize;
+            if node >= BITS {
+                return Err(io::Error::from(io::ErrorKind::InvalidInput));
+            }
+            let word = libc::c_ulong::BITS as usize;
+            mask[node / word] |= 1 << (node % word);
+            libc::MPOL_BIND
+        }
+        NumaPolicy::Interleave => {
+            // the kernel drops the nodes the process can't use
+            mask = [libc::c_ulong::MAX; WORDS];
+            libc::MPOL_INTERLEAVE
+        }
+    };
+    // SAFETY: the range is a mapping the caller owns, and the mask is
+    // BITS bits long. The kernel ignores the last bit of maxnode.
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            ptr.as_ptr(),
+            len,
+            mode,
+            mask.as_ptr(),
+            BITS + 1,
+            0,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+impl Chunk {
+    /// Allocate a chunk in its own mapping, with `policy` applied to
+    /// its pages
+    pub(crate) fn new_numa(capacity: usize, policy: NumaPolicy) -> Result<Self> {
+        let len = capacity.next_multiple_of(page_size());
+        let base = map(len).map_err(|_| Error::AllocFailed)?;
+        // entries must leave the bits handles pack into free
+        let fits = LEN_SHIFT.is_none_or(|shift| (base.as_ptr() as usize + len) >> (shift - 1) == 0);
+        if !fits || bind(base, len, policy).is_err() {
+            unmap(base, capacity);
+            return Err(Error::AllocFailed);
+        }
+        Ok(Chunk {
+            base,
+            capacity,
+            written: 0,
+            payload: 0,
+            #[cfg(feature = "mlock")]
+            mapped: true,
+        })
+    }
+}
+
+/// Unmap the memory of a chunk allocated by `new_numa`
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+pub(crate) fn unmap(base: NonNull<u8>, capacity: usize) {
+    let len = capacity.next_multiple_of(page_size());
+    // SAFETY: the chunk was mapped with this length, and nothing
+    // refers to it
+    unsafe { libc::munmap(base.as_ptr().cast(), len) };
+}
+
+#[cfg(not(all(target_os = "linux", not(feature = "forbid-unsafe"))))]
+impl Chunk {
+    /// Pages can't be placed on this target
+    pub(crate) fn new_numa(capacity: usize, _policy: NumaPolicy) -> Result<Self> {
+        Chunk::new(capacity)
+    }
+}
@@ -0,0 +1,103 @@
+//! Interning in parallel, behind the `rayon` feature
+
+#[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+use crate::shared;
+use crate::{common, frozen, index, stats, Error, Result, Str, MAX_LEN, ROOT};
+use rayon::prelude::*;
+
+/// How many new strings are inserted each time the lock is taken
+const BATCH: usize = 4096;
+
+/// What the parallel pass found out about one string
+enum Probe {
+    Found(Str),
+    Missing(u64),
+}
+
+/// Check `s` and look it up everywhere it can be found without the
+/// lock, hashing it if it has to be inserted
+fn probe(s: &str) -> Result<Probe> {
+    if s.len() > MAX_LEN {
+        return Err(Error::TooLong { len: s.len() });
+    }
+    if let Some(t) = common::get(s) {
+        return Ok(Probe::Found(t));
+    }
+    if let Some(res) = frozen::get(s) {
+        return res.map(Probe::Found);
+    }
+    let hash = index::hash(s);
+    Ok(match index::get_unlocked(hash, s) {
+        Some(t) => Probe::Found(t),
+        None => Probe::Missing(hash),
+    })
+}
+
+/// Intern every string in `strs`, returning their handles in the same
+/// order. Checking, hashing and probing the index for strings that are
+/// already interned is spread over rayon's thread pool; with the
+/// `dashmap` or `arc-swap` index that probe takes no lock, so a batch
+/// that is mostly hits scales with the number of threads. The pool has
+/// a single lock, so the strings that are new are then inserted one
+/// after another, taking the lock once per few thousand of them.
+///
+/// If a string can't be interned the error is returned, and the
+/// strings inserted before it remain in the pool.
+pub fn intern_par(strs: &[&str]) -> Result<Vec<Str>> {
+    #[cfg(all(feature = "shared-pool", not(feature = "forbid-unsafe")))]
+    if let Some(pool) = shared::host() {
+        return strs.par_iter().map(|s| pool.intern(s)).collect();
+    }
+    let mut probes = strs
+        .par_iter()
+        .map(|s| probe(s))
+        .collect::<Result<Vec<_>>>()?;
+    let mut missing = Vec::new();
+    for (i, p) in probes.iter().enumerate() {
+        // hits are counted here, on the calling thread, since with
+        // `frequency` counting takes a lock
+        match p {
+            Probe::Found(t) => stats::hit(*t),
+            Probe::Missing(hash) => missing.push((i, *hash)),
+        }
+    }
+    for batch in missing.chunks(BATCH) {
+        let mut root = ROOT.lock();
+        root.all.try_reserve(batch.len())?;
+        for (i, hash) in batch {
+            probes[*i] = Probe::Found(root.intern_hashed(*hash, strs[*i])?);
+        }
+    }
+    Ok(probes
+        .into_iter()
+        .map(|p| match p {
+            Probe::Found(t) => t,
+            Probe::Missing(_) => unreachable!("every missing string was inserted"),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern_par() {
+        let owned: Vec<String> = (0..10_000).map(|i| format!("par {}", i % 3000)).collect();
+        let mut strs: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+        strs.push("");
+        strs.push("par 0");
+        let ts = intern_par(&strs).unwrap();
+        assert_eq!(ts.len(), strs.len());
+        for (s, t) in strs.iter().zip(&ts) {
+            assert_eq!(&**t, *s);
+            assert_eq!(*t, Str::try_from(*s).unwrap());
+        }
+        assert_eq!(ts.last(), ts.first());
+        let long = "x".repeat(MAX_LEN + 1);
+        assert!(matches!(
+            intern_par(&["par ok", &long]),
+            Err(Error::TooLong { .. })
+        ));
+    }
+}
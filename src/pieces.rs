@@ -0,0 +1,50 @@
+//! Storing strings longer than the limit as several interned pieces
+
+use crate::{Result, Str, MAX_LEN};
+
+/// Split `s` into pieces of at most `MAX_LEN` bytes, cut on char
+/// boundaries, and intern each of them. Long strings that mostly
+/// repeat then share their pieces in the pool. Every piece but the
+/// last is as long as the char boundaries allow, so the same string
+/// always splits the same way. Use `join_pieces` to get `s` back.
+pub fn split_intern(mut s: &str) -> Result<Vec<Str>> {
+    let mut pieces = Vec::with_capacity(s.len().div_ceil(MAX_LEN));
+    while !s.is_empty() {
+        let mut end = s.len().min(MAX_LEN);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (piece, rest) = s.split_at(end);
+        pieces.push(Str::try_from(piece)?);
+        s = rest;
+    }
+    Ok(pieces)
+}
+
+/// Reassemble a string split by `split_intern`
+pub fn join_pieces(pieces: &[Str]) -> String {
+    let mut s = String::with_capacity(pieces.iter().map(|p| p.len()).sum());
+    for p in pieces {
+        s.push_str(p);
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_intern() {
+        let long = "piece ünïcödé ".repeat(100);
+        let pieces = split_intern(&long).unwrap();
+        assert!(pieces.iter().all(|p| p.len() <= MAX_LEN));
+        assert_eq!(join_pieces(&pieces), long);
+        let again = split_intern(&long).unwrap();
+        assert!(pieces
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| a.as_raw() == b.as_raw()));
+        assert!(split_intern("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! Protobuf support, behind the `prost` feature.
+//!
+//! prost decodes `string` fields into a fresh `String`. For fields
+//! that hold one of a small set of values, such as the names of
+//! things, a hand written `Message` impl can use these functions, which
+//! mirror `prost::encoding::string`, for a `Str` field instead. Values
+//! are interned straight out of the decode buffer, so repeated values
+//! cost no allocation at all.
+
+use crate::{Error, Str, MAX_LEN};
+use ::prost::{
+    bytes::{Buf, BufMut},
+    encoding::{
+        check_wire_type, decode_varint, encode_key, encode_varint, encoded_len_varint, key_len,
+        DecodeContext, WireType,
+    },
+    DecodeError,
+};
+
+fn decode_error(e: Error) -> DecodeError {
+    // prost has no other way to build an error from a Message impl
+    #[allow(deprecated)]
+    DecodeError::new(e.to_string())
+}
+
+/// Encode `value` as string field `tag`
+pub fn encode(tag: u32, value: &Str, buf: &mut impl BufMut) {
+    encode_key(tag, WireType::LengthDelimited, buf);
+    encode_varint(value.len() as u64, buf);
+    buf.put_slice(value.as_bytes());
+}
+
+/// Decode a string field into `value`, interning it
+pub fn merge(
+    wire_type: WireType,
+    value: &mut Str,
+    buf: &mut impl Buf,
+    _ctx: DecodeContext,
+) -> Result<(), DecodeError> {
+    check_wire_type(WireType::LengthDelimited, wire_type)?;
+    let len = decode_varint(buf)? as usize;
+    if len > buf.remaining() {
+        return Err(decode_error(Error::Decode("buffer underflow")));
+    }
+    if len > MAX_LEN {
+        return Err(decode_error(Error::TooLong { len }));
+    }
+    let chunk = buf.chunk();
+    let t = if chunk.len() >= len {
+        let t = Str::from_utf8(&chunk[..len]);
+        buf.advance(len);
+        t
+    } else {
+        // the field is split across chunks of the buffer
+        let mut tmp = [0; MAX_LEN];
+        buf.copy_to_slice(&mut tmp[..len]);
+        Str::from_utf8(&tmp[..len])
+    };
+    *value = t.map_err(decode_error)?;
+    Ok(())
+}
+
+/// The encoded length of `value` as string field `tag`
+pub fn encoded_len(tag: u32, value: &Str) -> usize {
+    key_len(tag) + encoded_len_varint(value.len() as u64) + value.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::prost::{encoding::skip_field, Message};
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Label {
+        name: Option<Str>,
+    }
+
+    impl Message for Label {
+        fn encode_raw(&self, buf: &mut impl BufMut) {
+            if let Some(name) = &self.name {
+                encode(1, name, buf)
+            }
+        }
+
+        fn merge_field(
+            &mut self,
+            tag: u32,
+            wire_type: WireType,
+            buf: &mut impl Buf,
+            ctx: DecodeContext,
+        ) -> Result<(), DecodeError> {
+            match tag {
+                1 => {
+                    let mut name = Str::try_from("").unwrap();
+                    merge(wire_type, &mut name, buf, ctx)?;
+                    self.name = Some(name);
+                    Ok(())
+                }
+                _ => skip_field(wire_type, tag, buf, ctx),
+            }
+        }
+
+        fn encoded_len(&self) -> usize {
+            self.name.as_ref().map_or(0, |n| encoded_len(1, n))
+        }
+
+        fn clear(&mut self) {
+            self.name = None
+        }
+    }
+
+    #[test]
+    fn test_prost() {
+        let label = Label {
+            name: Some(Str::try_from("prost label").unwrap()),
+        };
+        let bytes = label.encode_to_vec();
+        assert_eq!(bytes.len(), label.encoded_len());
+        assert_eq!(Label::decode(&bytes[..]).unwrap(), label);
+        let mut bad = bytes.clone();
+        bad[2] = 0xff;
+        assert!(Label::decode(&bad[..]).is_err());
+        assert!(Label::decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! MessagePack support, behind the `rmp` feature.
+//!
+//! rmp_serde hands string fields to the visitor borrowed straight out
+//! of the input when it deserializes from a slice, and `Str` interns
+//! the borrowed `&str` without building a `String` first, so ingesting
+//! messages whose strings are already in the pool allocates nothing
+//! for them. `from_slice` deserializes that way. MessagePack written by
+//! other languages usually encodes strings as `str`, which `Str` reads
+//! as readily as the `bin` it writes itself; `serialize` writes a `Str`
+//! field as `str` for consumers that expect that.
+
+use crate::Str;
+use ::serde::{de, Deserialize, Deserializer, Serializer};
+use std::{fmt, str};
+
+pub use rmp_serde::decode::Error;
+
+/// Deserialize a `T` from the MessagePack in `buf`, interning its
+/// `Str` fields straight out of `buf`
+pub fn from_slice<'a, T: Deserialize<'a>>(buf: &'a [u8]) -> Result<T, Error> {
+    rmp_serde::from_slice(buf)
+}
+
+/// Serialize a `Str` as a MessagePack `str`, for use with
+/// `#[serde(serialize_with = "hcstatic_str::rmp::serialize")]`
+pub fn serialize<S: Serializer>(t: &Str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(t)
+}
+
+/// Deserialize a `Str` from a MessagePack `str` or `bin`, always as a
+/// plain string, even inside a `serde::Dictionary`, for use with
+/// `#[serde(deserialize_with = "hcstatic_str::rmp::deserialize")]`
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Str, D::Error> {
+    struct Visitor;
+
+    impl de::Visitor<'_> for Visitor {
+        type Value = Str;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a string of at most {} bytes", Str::MAX_LEN)
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Str, E> {
+            Str::try_from(v).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Str, E> {
+            self.visit_str(str::from_utf8(v).map_err(E::custom)?)
+        }
+    }
+
+    deserializer.deserialize_str(Visitor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Sent {
+        host: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Received {
+        #[serde(serialize_with = "serialize")]
+        host: Str,
+        tags: Vec<Str>,
+    }
+
+    #[test]
+    fn test_rmp() {
+        let sent = Sent {
+            host: "rmp host".into(),
+            tags: vec!["rmp a".into(), "rmp b".into()],
+        };
+        let buf = rmp_serde::to_vec(&sent).unwrap();
+        let r: Received = from_slice(&buf).unwrap();
+        assert_eq!(&*r.host, "rmp host");
+        assert_eq!(
+            r.tags,
+            ["rmp a", "rmp b"].map(|s| Str::try_from(s).unwrap())
+        );
+        // Str writes bin by default, and str with serialize
+        let buf = rmp_serde::to_vec(&r).unwrap();
+        assert!(buf.windows(3).any(|w| w == [0xa8, b'r', b'm']));
+        assert!(buf.windows(3).any(|w| w == [0xc4, 5, b'r']));
+        for s in [
+            &[0xa5, b'r', b'm', b'p', b' ', b'c'][..],
+            &[0xc4, 5, b'r', b'm', b'p', b' ', b'c'],
+        ] {
+            let t = deserialize(&mut rmp_serde::Deserializer::from_read_ref(s)).unwrap();
+            assert_eq!(&*t, "rmp c");
+        }
+        let long = rmp_serde::to_vec(&"x".repeat(Str::MAX_LEN + 1)).unwrap();
+        assert!(from_slice::<Str>(&long).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Finding interned strings by something other than their exact
+//! contents
+
+use crate::{
+    common,
+    generation::{new_since, Generation},
+    index,
+    sync::{Lazy, Mutex},
+    Str, MAX_LEN, ROOT,
+};
+
+/// Every interned string in order, for prefix queries, and the
+/// generation it is up to date with
+pub(crate) static SORTED: Lazy<Mutex<(Vec<Str>, Generation)>> =
+    Lazy::new(|| Mutex::new((Vec::new(), Generation::default())));
+
+/// The longest non empty interned string that is a prefix of `query`,
+/// such as the route template matching a path. Every prefix of `query`
+/// up to `Str::MAX_LEN` bytes is looked up, longest first, under a
+/// single lock acquisition, so this costs at most one probe of the
+/// index per byte of the query.
+pub fn longest_prefix_of(query: &str) -> Option<Str> {
+    let root = ROOT.lock();
+    (1..=query.len().min(MAX_LEN))
+        .rev()
+        .filter(|i| query.is_char_boundary(*i))
+        .find_map(|i| {
+            let prefix = &query[..i];
+            common::get(prefix).or_else(|| root.all.get(index::hash(prefix), prefix))
+        })
+}
+
+/// Up to `limit` interned strings starting with `prefix`, for tab
+/// completion. With the `frequency` feature the most often interned
+/// come first, otherwise, and among strings interned equally often,
+/// they are in order. The strings are kept sorted in an index that is
+/// built the first time this is called, and brought up to date with
+/// the strings interned since on every later call, so finding the
+/// matches is a binary search. The built in common strings aren't
+/// searched.
+pub fn autocomplete(prefix: &str, limit: usize) -> Vec<Str> {
+    let mut sorted = SORTED.lock();
+    let (strs, seen) = &mut *sorted;
+    let (mut new, generation) = new_since(*seen);
+    new.sort_unstable();
+    strs.extend(new);
+    // two sorted runs, which the stable sort merges in linear time
+    strs.sort();
+    *seen = generation;
+    let start = strs.partition_point(|t| t.as_static() < prefix);
+    let matches = strs[start..]
+        .iter()
+        .take_while(|t| t.starts_with(prefix))
+        .copied();
+    #[cfg(not(feature = "frequency"))]
+    return matches.take(limit).collect();
+    #[cfg(feature = "frequency")]
+    {
+        let mut matches: Vec<Str> = matches.collect();
+        drop(sorted);
+        crate::frequency::rank(&mut matches);
+        matches.truncate(limit);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_of() {
+        for s in ["/lpm/users", "/lpm/users/", "/lpm/users/é"] {
+            Str::try_from(s).unwrap();
+        }
+        let lpm = |q| longest_prefix_of(q).map(|t| t.as_static());
+        assert_eq!(lpm("/lpm/users/42"), Some("/lpm/users/"));
+        assert_eq!(lpm("/lpm/users"), Some("/lpm/users"));
+        assert_eq!(lpm("/lpm/usersx"), Some("/lpm/users"));
+        assert_eq!(lpm("/lpm/users/éé"), Some("/lpm/users/é"));
+        // single characters are always interned
+        assert_eq!(lpm("/lpm/nothing"), Some("/"));
+        assert_eq!(lpm("é"), None);
+        assert_eq!(lpm(""), None);
+        let long = format!("/lpm/users/{}", "x".repeat(300));
+        assert_eq!(lpm(&long), Some("/lpm/users/"));
+    }
+
+    #[test]
+    fn test_autocomplete() {
+        for s in ["ac:print", "ac:println", "ac:panic", "ac/other"] {
+            Str::try_from(s).unwrap();
+        }
+        #[cfg(feature = "frequency")]
+        Str::try_from("ac:println").unwrap();
+        let complete = |p, limit| {
+            autocomplete(p, limit)
+                .into_iter()
+                .map(Str::as_static)
+                .collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "frequency"))]
+        assert_eq!(complete("ac:p", 10), ["ac:panic", "ac:print", "ac:println"]);
+        #[cfg(feature = "frequency")]
+        assert_eq!(complete("ac:p", 10), ["ac:println", "ac:panic", "ac:print"]);
+        assert_eq!(complete("ac:print", 10).len(), 2);
+        assert_eq!(complete("ac:p", 1).len(), 1);
+        assert!(complete("ac:q", 10).is_empty());
+        // interned after the index was built
+        Str::try_from("ac:pop").unwrap();
+        assert_eq!(complete("ac:po", 10), ["ac:pop"]);
+    }
+}
@@ -0,0 +1,205 @@
+//! Hashconsed hierarchical paths made of interned segments
+
+use crate::sync::Lazy;
+use crate::sync::Mutex;
+use crate::{Result, Str};
+use fxhash::FxHashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    mem::{self, MaybeUninit},
+    num::NonZeroUsize,
+    ptr,
+};
+
+const NODES_PER_BLOCK: usize = 1024;
+
+struct Node {
+    parent: SegPath,
+    name: Str,
+    depth: usize,
+}
+
+/// Nodes are carved off the front of a block that is never freed, in
+/// the same way as strings in a chunk, so every path is one small
+/// allocation shared by all paths under it.
+pub(crate) struct Nodes {
+    all: FxHashMap<(Option<NonZeroUsize>, NonZeroUsize), SegPath>,
+    free: &'static mut [MaybeUninit<Node>],
+}
+
+impl Nodes {
+    fn alloc(&mut self, node: Node) -> &'static Node {
+        if self.free.is_empty() {
+            let mut block = Vec::with_capacity(NODES_PER_BLOCK);
+            block.resize_with(NODES_PER_BLOCK, MaybeUninit::uninit);
+            self.free = block.leak();
+        }
+        let (slot, free) = mem::take(&mut self.free).split_first_mut().unwrap();
+        self.free = free;
+        slot.write(node)
+    }
+}
+
+pub(crate) static NODES: Lazy<Mutex<Nodes>> = Lazy::new(|| {
+    Mutex::new(Nodes {
+        all: HashMap::default(),
+        free: &mut [],
+    })
+});
+
+/// A `/` separated hierarchical name, like a metric or netidx path,
+/// stored as a chain of interned segments. Paths are hashconsed, so
+/// a path shares its storage with its parent and every other path
+/// under that parent, and equality is a pointer comparison. The type
+/// is 1 word and `Copy`, like `Str`.
+#[derive(Clone, Copy)]
+pub struct SegPath(Option<&'static Node>);
+
+impl SegPath {
+    /// The root path, `/`
+    pub const ROOT: SegPath = SegPath(None);
+
+    fn addr(self) -> Option<NonZeroUsize> {
+        self.0
+            .and_then(|n| NonZeroUsize::new(n as *const Node as usize))
+    }
+
+    fn child(self, name: Str) -> SegPath {
+        let mut nodes = NODES.lock();
+        let key = (self.addr(), name.as_raw());
+        if let Some(p) = nodes.all.get(&key) {
+            return *p;
+        }
+        let depth = self.depth() + 1;
+        let node = nodes.alloc(Node {
+            parent: self,
+            name,
+            depth,
+        });
+        let p = SegPath(Some(node));
+        nodes.all.insert(key, p);
+        p
+    }
+
+    /// Parse a path, empty segments (from leading, trailing, or
+    /// repeated `/`) are ignored. Fails if a segment can't be interned.
+    pub fn parse(path: &str) -> Result<SegPath> {
+        SegPath::ROOT.append(path)
+    }
+
+    /// Append the segments of the relative path `rel` to this path
+    pub fn append(self, rel: &str) -> Result<SegPath> {
+        let mut p = self;
+        for seg in rel.split('/').filter(|s| !s.is_empty()) {
+            p = p.child(Str::try_from(seg)?);
+        }
+        Ok(p)
+    }
+
+    /// The path without its last segment, None for the root
+    pub fn parent(self) -> Option<SegPath> {
+        self.0.map(|n| n.parent)
+    }
+
+    /// The last segment of the path, None for the root
+    pub fn name(self) -> Option<Str> {
+        self.0.map(|n| n.name)
+    }
+
+    /// The number of segments in the path
+    pub fn depth(self) -> usize {
+        self.0.map(|n| n.depth).unwrap_or(0)
+    }
+
+    /// True if `prefix` is this path or one of its ancestors
+    pub fn starts_with(self, prefix: SegPath) -> bool {
+        let mut p = self;
+        while p.depth() > prefix.depth() {
+            p = p.parent().unwrap();
+        }
+        p == prefix
+    }
+
+    /// The segments of the path from the root down
+    pub fn segments(self) -> Vec<Str> {
+        let mut res = Vec::with_capacity(self.depth());
+        let mut p = self;
+        while let Some(n) = p.0 {
+            res.push(n.name);
+            p = n.parent;
+        }
+        res.reverse();
+        res
+    }
+}
+
+impl PartialEq for SegPath {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.0, other.0) {
+            (None, None) => true,
+            (Some(a), Some(b)) => ptr::eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for SegPath {}
+
+impl Hash for SegPath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+
+impl fmt::Display for SegPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segs = self.segments();
+        if segs.is_empty() {
+            return write!(f, "/");
+        }
+        for s in segs {
+            write!(f, "/{}", &*s)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SegPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl TryFrom<&str> for SegPath {
+    type Error = crate::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        SegPath::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_segpath() {
+        let a = SegPath::parse("/metrics/host/cpu").unwrap();
+        let b = SegPath::parse("metrics//host/cpu/").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "/metrics/host/cpu");
+        assert_eq!(a.depth(), 3);
+        let host = a.parent().unwrap();
+        assert_eq!(host, SegPath::parse("/metrics/host").unwrap());
+        assert_eq!(host.append("cpu").unwrap(), a);
+        assert_eq!(&*a.name().unwrap(), "cpu");
+        assert!(a.starts_with(host));
+        assert!(a.starts_with(SegPath::ROOT));
+        assert!(!host.starts_with(a));
+        assert!(!a.starts_with(SegPath::parse("/metrics/hos").unwrap()));
+        assert_eq!(SegPath::ROOT.to_string(), "/");
+        assert_eq!(SegPath::parse("/").unwrap(), SegPath::ROOT);
+    }
+}
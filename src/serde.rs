@@ -0,0 +1,262 @@
+//! Serde support, behind the `serde` feature.
+//!
+//! A `Str` serializes as a plain string in human readable formats, and
+//! as bytes in compact ones, so binary formats copy it verbatim
+//! instead of treating it as text. Wrapping a message in
+//! `Dictionary` switches every `Str` inside it to dictionary mode,
+//! where the first occurrence of each string is serialized along with
+//! a u32 id, and later occurrences are serialized as only the id. This
+//! is meant for binary formats such as bincode, where messages that
+//! repeat the same strings many times shrink considerably. Both ends
+//! must use `Dictionary`, and the format must deserialize values in
+//! the order they were serialized. Human readable formats such as
+//! JSON ignore `Dictionary` and keep plain strings, so they stay
+//! readable.
+//!
+//! With the `serde_with` feature, `PlainStr` adapts `Str` fields to
+//! `#[serde_as]` annotations. `Str` also implements `Display` and
+//! `FromStr`, so `DisplayFromStr` works with it as it does with
+//! `String`.
+//!
+//! `InterningDeserializer` wraps the deserializer of any format so
+//! that every string it reads is interned.
+
+use crate::Str;
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use fxhash::FxHashMap;
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    num::NonZeroUsize,
+    str,
+};
+
+mod interning;
+
+pub use interning::InterningDeserializer;
+
+thread_local! {
+    static SER: RefCell<Option<FxHashMap<NonZeroUsize, u32>>> = const { RefCell::new(None) };
+    static DE: RefCell<Option<Vec<Str>>> = const { RefCell::new(None) };
+}
+
+impl Serialize for Str {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(self);
+        }
+        let id = SER.with(|ids| {
+            ids.borrow_mut().as_mut().map(|ids| {
+                let next = u32::try_from(ids.len()).expect("too many strings");
+                match ids.entry(self.as_raw()) {
+                    Entry::Occupied(e) => (*e.get(), None),
+                    Entry::Vacant(e) => {
+                        e.insert(next);
+                        (next, Some(self.as_static()))
+                    }
+                }
+            })
+        });
+        match id {
+            None => serializer.serialize_bytes(self.as_bytes()),
+            Some(entry) => entry.serialize(serializer),
+        }
+    }
+}
+
+/// Deserializes a `Str` from a plain string or bytes regardless of
+/// mode
+struct Plain(Str);
+
+impl<'de> Deserialize<'de> for Plain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = Plain;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a string of at most {} bytes", u8::MAX)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Plain, E> {
+                Str::try_from(v).map(Plain).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Plain, E> {
+                let s = str::from_utf8(v).map_err(E::custom)?;
+                self.visit_str(s)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Visitor)
+        } else {
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Str {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() || DE.with(|strs| strs.borrow().is_none()) {
+            return Plain::deserialize(deserializer).map(|p| p.0);
+        }
+        let (id, s) = <(u32, Option<Plain>)>::deserialize(deserializer)?;
+        DE.with(|strs| {
+            let mut strs = strs.borrow_mut();
+            let strs = strs.as_mut().unwrap();
+            match s {
+                Some(Plain(s)) if id as usize == strs.len() => {
+                    strs.push(s);
+                    Ok(s)
+                }
+                Some(_) => Err(de::Error::custom("string ids out of order")),
+                None => strs
+                    .get(id as usize)
+                    .copied()
+                    .ok_or_else(|| de::Error::custom("unknown string id")),
+            }
+        })
+    }
+}
+
+/// Restores the previous dictionary state of a thread when dropped,
+/// so nested and panicking (de)serializations don't leave it behind.
+struct Guard<T: 'static>(
+    &'static std::thread::LocalKey<RefCell<Option<T>>>,
+    Option<T>,
+);
+
+impl<T: 'static> Guard<T> {
+    fn new(key: &'static std::thread::LocalKey<RefCell<Option<T>>>, v: T) -> Self {
+        Guard(key, key.with(|c| c.replace(Some(v))))
+    }
+}
+
+impl<T: 'static> Drop for Guard<T> {
+    fn drop(&mut self) {
+        let prev = self.1.take();
+        self.0.with(|c| *c.borrow_mut() = prev);
+    }
+}
+
+/// A wrapper that (de)serializes every `Str` inside it in dictionary
+/// mode, see the module documentation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dictionary<T>(pub T);
+
+impl<T: Serialize> Serialize for Dictionary<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _guard = Guard::new(&SER, HashMap::default());
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Dictionary<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let _guard = Guard::new(&DE, Vec::new());
+        T::deserialize(deserializer).map(Dictionary)
+    }
+}
+
+/// A serde_with adapter that (de)serializes a `Str` as a plain
+/// string in every format, ignoring `Dictionary` and never writing
+/// bytes, for fields read by consumers that expect text. It composes
+/// like the adapters serde_with provides, for example
+/// `#[serde_as(as = "Option<PlainStr>")]` or
+/// `#[serde_as(as = "HashMap<PlainStr, _>")]`.
+#[cfg(feature = "serde_with")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainStr;
+
+#[cfg(feature = "serde_with")]
+impl serde_with::SerializeAs<Str> for PlainStr {
+    fn serialize_as<S: Serializer>(t: &Str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(t)
+    }
+}
+
+#[cfg(feature = "serde_with")]
+impl<'de> serde_with::DeserializeAs<'de, Str> for PlainStr {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Str, D::Error> {
+        Plain::deserialize(deserializer).map(|p| p.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        let s = Str::try_from("serde plain").unwrap();
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(json, "\"serde plain\"");
+        let t: Str = serde_json::from_str(&json).unwrap();
+        assert_eq!(s.as_raw(), t.as_raw());
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let v: Vec<Str> = ["serde dict a", "serde dict b"]
+            .iter()
+            .cycle()
+            .take(100)
+            .map(|s| Str::try_from(*s).unwrap())
+            .collect();
+        let plain = bincode::serialize(&v).unwrap();
+        let dict = bincode::serialize(&Dictionary(&v)).unwrap();
+        assert!(dict.len() < plain.len() / 2);
+        let Dictionary(w): Dictionary<Vec<Str>> = bincode::deserialize(&dict).unwrap();
+        assert_eq!(v, w);
+        let json = serde_json::to_string(&Dictionary(&v)).unwrap();
+        assert_eq!(json, serde_json::to_string(&v).unwrap());
+        let Dictionary(w): Dictionary<Vec<Str>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(v, w);
+    }
+
+    #[test]
+    fn test_compact() {
+        let s = Str::try_from("serde compact").unwrap();
+        let bin = bincode::serialize(&s).unwrap();
+        assert_eq!(bin, bincode::serialize(&b"serde compact"[..]).unwrap());
+        let t: Str = bincode::deserialize(&bin).unwrap();
+        assert_eq!(s, t);
+    }
+
+    #[cfg(feature = "serde_with")]
+    #[test]
+    fn test_serde_with() {
+        use serde_with::{serde_as, DisplayFromStr};
+
+        #[serde_as]
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Record {
+            #[serde_as(as = "PlainStr")]
+            name: Str,
+            #[serde_as(as = "Vec<DisplayFromStr>")]
+            tags: Vec<Str>,
+            #[serde_as(as = "Option<PlainStr>")]
+            owner: Option<Str>,
+        }
+
+        let r = Record {
+            name: Str::try_from("serde with name").unwrap(),
+            tags: vec![Str::try_from("serde with tag").unwrap()],
+            owner: None,
+        };
+        let json = serde_json::to_string(&r).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"serde with name","tags":["serde with tag"],"owner":null}"#
+        );
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), r);
+        // plain strings even in compact formats and dictionary mode
+        let bin = bincode::serialize(&Dictionary(&r)).unwrap();
+        assert!(bin.windows(15).any(|w| w == b"serde with name"));
+        let Dictionary(back): Dictionary<Record> = bincode::deserialize(&bin).unwrap();
+        assert_eq!(back, r);
+    }
+}
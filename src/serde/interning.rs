@@ -0,0 +1,396 @@
+//! A deserializer adapter that interns every string it sees
+
+use crate::Str;
+use ::serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use std::fmt;
+
+/// Which strings are interned, shared by every wrapper in one
+/// deserialization
+#[derive(Clone, Copy)]
+struct Filter<'a> {
+    max_len: usize,
+    pred: Option<&'a dyn Fn(&str) -> bool>,
+}
+
+impl Filter<'_> {
+    fn intern(&self, s: &str) -> Option<&'static str> {
+        if s.len() > self.max_len || !self.pred.is_none_or(|pred| pred(s)) {
+            return None;
+        }
+        Str::try_from(s).ok().map(Str::as_static)
+    }
+}
+
+/// Wraps any serde `Deserializer` so that every string it produces,
+/// map keys and enum variant names included, is interned and handed
+/// to the visitor as a string borrowed from the pool. Repeated
+/// strings are stored once however the input is read, and types that
+/// borrow, such as `&str` fields, can be deserialized even from a
+/// reader, which could otherwise only produce owned strings.
+///
+/// Strings longer than the maximum length, those rejected by the
+/// filter, and those that can't be interned, for example because the
+/// memory limit has been reached, are passed through unchanged.
+///
+/// ```
+/// use hcstatic_str::serde::InterningDeserializer;
+/// use serde::Deserialize;
+///
+/// let json = br#"{"host": "web1", "tags": ["web", "prod"]}"#;
+/// let mut de = serde_json::Deserializer::from_reader(&json[..]);
+/// #[derive(Deserialize)]
+/// struct Record {
+///     host: &'static str,
+///     tags: Vec<&'static str>,
+/// }
+/// let r = Record::deserialize(InterningDeserializer::new(&mut de)).unwrap();
+/// assert_eq!((r.host, r.tags.as_slice()), ("web1", &["web", "prod"][..]));
+/// ```
+pub struct InterningDeserializer<'a, D> {
+    inner: D,
+    filter: Filter<'a>,
+}
+
+impl<'a, D> InterningDeserializer<'a, D> {
+    /// Intern every string of at most `Str::MAX_LEN` bytes read by
+    /// `inner`
+    pub fn new(inner: D) -> Self {
+        InterningDeserializer {
+            inner,
+            filter: Filter {
+                max_len: Str::MAX_LEN,
+                pred: None,
+            },
+        }
+    }
+
+    /// Only intern strings of at most `max_len` bytes
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.filter.max_len = max_len.min(Str::MAX_LEN);
+        self
+    }
+
+    /// Only intern strings for which `pred` returns true, for example
+    /// to keep unique ids or free text out of the pool
+    pub fn filter(mut self, pred: &'a dyn Fn(&str) -> bool) -> Self {
+        self.filter.pred = Some(pred);
+        self
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, D::Error> {
+                let visitor = Wrap { inner: visitor, filter: self.filter };
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for InterningDeserializer<'_, D> {
+    type Error = D::Error;
+
+    forward! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Wraps a visitor, a seed, or one of the accessors a visitor is
+/// handed, so everything nested in it is wrapped in turn
+struct Wrap<'a, T> {
+    inner: T,
+    filter: Filter<'a>,
+}
+
+impl<'a, T> Wrap<'a, T> {
+    fn wrap<U>(&self, inner: U) -> Wrap<'a, U> {
+        Wrap {
+            inner,
+            filter: self.filter,
+        }
+    }
+
+    fn deserializer<D>(&self, inner: D) -> InterningDeserializer<'a, D> {
+        InterningDeserializer {
+            inner,
+            filter: self.filter,
+        }
+    }
+}
+
+macro_rules! visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<V::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<V::Value, E> {
+        match self.filter.intern(v) {
+            Some(s) => self.inner.visit_borrowed_str(s),
+            None => self.inner.visit_str(v),
+        }
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<V::Value, E> {
+        let s = self.filter.intern(v).unwrap_or(v);
+        self.inner.visit_borrowed_str(s)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<V::Value, E> {
+        match self.filter.intern(&v) {
+            Some(s) => self.inner.visit_borrowed_str(s),
+            None => self.inner.visit_string(v),
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<V::Value, D::Error> {
+        let d = self.deserializer(d);
+        self.inner.visit_some(d)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<V::Value, D::Error> {
+        let d = self.deserializer(d);
+        self.inner.visit_newtype_struct(d)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        let seq = self.wrap(seq);
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        let map = self.wrap(map);
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        let data = self.wrap(data);
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<'_, T> {
+    type Value = T::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<T::Value, D::Error> {
+        let d = self.deserializer(d);
+        self.inner.deserialize(d)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A: EnumAccess<'de>> EnumAccess<'de> for Wrap<'a, A> {
+    type Error = A::Error;
+    type Variant = Wrap<'a, A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self::Variant), A::Error> {
+        let filter = self.filter;
+        let seed = self.wrap(seed);
+        let (v, inner) = self.inner.variant_seed(seed)?;
+        Ok((v, Wrap { inner, filter }))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Host { name: &'static str },
+        Label(&'static str),
+    }
+
+    fn from_reader<T: Deserialize<'static>>(
+        json: &str,
+        max_len: usize,
+        pred: &dyn Fn(&str) -> bool,
+    ) -> serde_json::Result<T> {
+        let mut de = serde_json::Deserializer::from_reader(json.as_bytes());
+        T::deserialize(
+            InterningDeserializer::new(&mut de)
+                .max_len(max_len)
+                .filter(pred),
+        )
+    }
+
+    #[test]
+    fn test_interning_deserializer() {
+        let json =
+            r#"{"interning a": [{"Host": {"name": "interning b"}}, {"Label": "interning c"}]}"#;
+        let m: HashMap<&str, Vec<Kind>> = from_reader(json, Str::MAX_LEN, &|_| true).unwrap();
+        let (k, v) = m.iter().next().unwrap();
+        let a = Str::try_from("interning a").unwrap();
+        assert_eq!(k.as_ptr(), a.as_static().as_ptr());
+        assert_eq!(
+            v,
+            &[
+                Kind::Host {
+                    name: "interning b"
+                },
+                Kind::Label("interning c")
+            ]
+        );
+        let Kind::Label(c) = v[1] else { unreachable!() };
+        assert_eq!(c.as_ptr(), Str::try_from(c).unwrap().as_static().as_ptr());
+        // strings that aren't interned are passed through, so a reader
+        // can't lend them
+        let json = r#"["interning d", "interning rejected"]"#;
+        let reject = |s: &str| !s.ends_with("rejected");
+        assert!(from_reader::<Vec<&str>>(json, Str::MAX_LEN, &reject).is_err());
+        let v: Vec<String> = from_reader(json, Str::MAX_LEN, &reject).unwrap();
+        assert_eq!(v, ["interning d", "interning rejected"]);
+        assert!(from_reader::<Vec<&str>>(json, 11, &|_| true).is_err());
+        let v: Vec<&str> = from_reader(json, 18, &|_| true).unwrap();
+        assert_eq!(v.len(), 2);
+    }
+}
@@ -0,0 +1,208 @@
+//! Sharing one pool between every copy of the crate in a process.
+//!
+//! Each copy of the crate linked into a process, whether in separate
+//! dynamic libraries or as semver incompatible versions, has its own
+//! pool, so the same string interned by two of them gives two
+//! different handles. With the `shared-pool` feature the executable
+//! can call `share_pool` at startup to publish its pool, and every
+//! other copy built with the feature then interns into that pool
+//! instead of its own.
+//!
+//! The executable publishes its pool with `share_pool!`, which defines
+//! an exported symbol pointing to a `repr(C)` table of `extern "C"`
+//! functions, tagged with a fingerprint of the entry layout, so copies
+//! whose handles can't be read by each other never share. The other
+//! copies look the symbol up with `dlsym`, so what they find is always
+//! part of the running image, never an address left over from a parent
+//! process or an image replaced by `exec`. Strings interned by any copy
+//! can be read by all of them, since reading a handle never touches the
+//! pool. `TaggedStr`s and frozen tables are still per copy.
+
+use crate::{sync::Lazy, Error, Result, Str, LEN_SHIFT, TRAILER_LEN};
+use std::{mem, num::NonZeroUsize, ptr, slice, str};
+
+/// The name of the symbol `share_pool!` defines, nul terminated for
+/// `dlsym`. It carries the ABI version, so copies with a different one
+/// never find each other's table.
+const SYMBOL: &[u8] = b"hcstatic_str_shared_pool_v2\0";
+
+/// Bumped whenever the layout of entries or handles changes
+const ABI: u64 = 2;
+
+/// Identifies the layout of entries and handles, which every copy
+/// sharing a pool must agree on
+const FINGERPRINT: u64 = {
+    let shift = match LEN_SHIFT {
+        Some(shift) => shift as u64,
+        None => 0,
+    };
+    (ABI << 24) | ((TRAILER_LEN as u64) << 16) | (shift << 8) | mem::size_of::<usize>() as u64
+};
+
+/// The table of functions a pool is shared through. The fingerprint
+/// must stay the first field in every version.
+#[repr(C)]
+pub struct Pool {
+    fingerprint: u64,
+    /// Intern the `len` bytes at `ptr`, which must be UTF-8, storing
+    /// the raw handle in `out`, and return 0, or an error code
+    intern: unsafe extern "C" fn(ptr: *const u8, len: usize, out: *mut usize) -> u8,
+    /// Return 1 if `raw` is the handle of a string in the pool, else 0
+    validate: extern "C" fn(raw: usize) -> u8,
+}
+
+#[doc(hidden)]
+pub static OURS: Pool = Pool {
+    fingerprint: FINGERPRINT,
+    intern: intern_ffi,
+    validate: validate_ffi,
+};
+
+/// The pool published by another copy, found the first time this copy
+/// interns anything
+static HOST: Lazy<Option<&'static Pool>> = Lazy::new(find);
+
+unsafe extern "C" fn intern_ffi(ptr: *const u8, len: usize, out: *mut usize) -> u8 {
+    // SAFETY: the caller passes a valid UTF-8 string
+    let s = unsafe { str::from_utf8_unchecked(slice::from_raw_parts(ptr, len)) };
+    match Str::try_from(s) {
+        Ok(t) => {
+            unsafe { *out = t.as_raw().get() };
+            0
+        }
+        Err(Error::TooLong { .. }) => 1,
+        Err(Error::CapacityExceeded) => 2,
+        Err(Error::AllocFailed) => 3,
+        Err(Error::Frozen) => 4,
+        Err(Error::Rejected) => 5,
+        Err(_) => u8::MAX,
+    }
+}
+
+extern "C" fn validate_ffi(raw: usize) -> u8 {
+    NonZeroUsize::new(raw).and_then(Str::try_from_raw).is_some() as u8
+}
+
+/// The table `share_pool!` published, if it is visible to `dlsym`
+#[cfg(unix)]
+fn lookup() -> Option<&'static Pool> {
+    // SAFETY: SYMBOL is nul terminated
+    let sym = unsafe { libc::dlsym(libc::RTLD_DEFAULT, SYMBOL.as_ptr().cast()) };
+    // SAFETY: a symbol with this name is only defined by share_pool!,
+    // as a `&'static Pool`, in an image that is loaded for as long as
+    // the process runs
+    (!sym.is_null()).then(|| unsafe { *sym.cast::<&'static Pool>() })
+}
+
+#[cfg(not(unix))]
+fn lookup() -> Option<&'static Pool> {
+    None
+}
+
+/// `pool` if it is another copy's, with the same layout as ours
+fn accept(pool: &'static Pool) -> Option<&'static Pool> {
+    (pool.fingerprint == FINGERPRINT && !ptr::eq(pool, &OURS)).then_some(pool)
+}
+
+fn find() -> Option<&'static Pool> {
+    lookup().and_then(accept)
+}
+
+/// The pool of another copy that this copy should intern into, if
+/// there is one
+pub(crate) fn host() -> Option<&'static Pool> {
+    *HOST
+}
+
+impl Pool {
+    pub(crate) fn intern(&self, s: &str) -> Result<Str> {
+        if s.len() > Str::MAX_LEN {
+            return Err(Error::TooLong { len: s.len() });
+        }
+        let mut raw = 0;
+        // SAFETY: s is valid UTF-8 and raw is a valid out pointer
+        match unsafe { (self.intern)(s.as_ptr(), s.len(), &mut raw) } {
+            // SAFETY: the host returned the handle of one of its
+            // strings, which has the same layout as ours
+            0 => Ok(unsafe { Str::from_raw_unchecked(NonZeroUsize::new(raw).unwrap()) }),
+            1 => Err(Error::TooLong { len: s.len() }),
+            2 => Err(Error::CapacityExceeded),
+            4 => Err(Error::Frozen),
+            5 => Err(Error::Rejected),
+            _ => Err(Error::AllocFailed),
+        }
+    }
+
+    pub(crate) fn try_from_raw(&self, raw: NonZeroUsize) -> Option<Str> {
+        // SAFETY: the host checked that raw is the handle of one of its
+        // strings
+        ((self.validate)(raw.get()) == 1).then(|| unsafe { Str::from_raw_unchecked(raw) })
+    }
+}
+
+/// Publish this copy's pool so every other copy of the crate in the
+/// process built with the `shared-pool` feature interns into it.
+/// Invoke it once, at the top level of the executable, or of a library
+/// that is never unloaded, since it defines the exported symbol
+/// `hcstatic_str_shared_pool_v2`.
+///
+/// ```ignore
+/// hcstatic_str::share_pool!();
+/// ```
+///
+/// The other copies find the symbol with `dlsym`, so it has to be in
+/// the dynamic symbol table: link an executable on Linux with
+/// `-C link-arg=-rdynamic` or
+/// `-C link-arg=-Wl,--export-dynamic-symbol=hcstatic_str_shared_pool_v2`.
+/// Each copy looks for it once, the first time it interns, and keeps
+/// its own pool if it isn't found, as it always does without unix.
+#[macro_export]
+macro_rules! share_pool {
+    () => {
+        const _: () = {
+            #[export_name = "hcstatic_str_shared_pool_v2"]
+            #[used]
+            static SHARED_POOL: &$crate::__private::Pool = &$crate::__private::OURS;
+        };
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::share_pool!();
+
+    static OTHER: Pool = Pool {
+        fingerprint: FINGERPRINT,
+        intern: intern_ffi,
+        validate: validate_ffi,
+    };
+
+    static MISMATCHED: Pool = Pool {
+        fingerprint: FINGERPRINT + 1,
+        intern: intern_ffi,
+        validate: validate_ffi,
+    };
+
+    #[test]
+    fn test_share_pool() {
+        // the pool we published is our own, if the test binary exports
+        // it at all, so we don't delegate to it
+        assert!(lookup().is_none_or(|pool| ptr::eq(pool, &OURS)));
+        assert!(find().is_none());
+        assert!(accept(&OURS).is_none());
+        assert!(accept(&MISMATCHED).is_none());
+        assert!(accept(&OTHER).is_some_and(|pool| ptr::eq(pool, &OTHER)));
+        assert!(SYMBOL.starts_with(format!("hcstatic_str_shared_pool_v{ABI}\0").as_bytes()));
+        // but another copy would find it and get our handles
+        let t = OURS.intern("shared pool").unwrap();
+        assert_eq!(t.as_raw(), Str::try_from("shared pool").unwrap().as_raw());
+        assert!(matches!(
+            OURS.intern(&"x".repeat(Str::MAX_LEN + 1)),
+            Err(Error::TooLong { .. })
+        ));
+        assert_eq!((OURS.validate)(t.as_raw().get()), 1);
+        assert_eq!((OURS.validate)(t.as_raw().get() + 1), 0);
+    }
+}
@@ -0,0 +1,714 @@
+//! Saving the pool and preloading it from a saved copy
+//!
+//! A snapshot starts with a header: the magic bytes `hcsv`, then the
+//! format version and the layout flags as little endian `u16`s, then
+//! the number of strings as a little endian `u64`, then the CRC-32C of
+//! the header so far as a little endian `u32`. The strings follow in
+//! the order they were interned, grouped into sections of at most
+//! 4096 strings. A section is its length in bytes as a little endian
+//! `u32`, then each string as a length byte and then the bytes of the
+//! string, then the CRC-32C of the strings as a little endian `u32`.
+//!
+//! Readers reject versions newer than they know and flags they don't
+//! understand rather than guessing at the layout, reject input with
+//! more or fewer strings than the header says, and check every
+//! checksum before using the data it covers, so a changed, truncated
+//! or damaged snapshot is never loaded silently. Version 2 snapshots,
+//! which have no checksums and a single section with no length, and
+//! version 1 snapshots, which are the magic bytes `hcs1` followed
+//! directly by the strings, are still read.
+//!
+//! With the `zstd` feature snapshots can be written compressed, which
+//! is flagged in the header. Only the strings are compressed, so the
+//! header can always be read.
+//!
+//! A delta is a snapshot of just the strings interned since some
+//! generation. Deltas can be appended one after another to a file
+//! that starts with a full snapshot, so a service can checkpoint what
+//! it has added without rewriting everything, and the whole log is
+//! read back with `load_delta`.
+//!
+//! With the `frequency` feature `compact_snapshot` rewrites a snapshot
+//! with the most often interned strings first, so preloading it packs
+//! them together, and the pages holding them stay hot.
+
+use crate::{new_since, Error, Generation, Result, Str, ROOT};
+#[cfg(feature = "frequency")]
+use fxhash::FxHashMap;
+#[cfg(feature = "zstd")]
+use std::borrow::Cow;
+#[cfg(feature = "frequency")]
+use std::cmp::Reverse;
+#[cfg(feature = "mmap")]
+use std::{fs::File, path::Path};
+use std::{io, str};
+
+const MAGIC: &[u8] = b"hcsv";
+const MAGIC_V1: &[u8] = b"hcs1";
+const VERSION: u16 = 3;
+/// The flag saying that the strings are zstd compressed
+const COMPRESSED: u16 = 1;
+/// The layout flags this version understands
+const KNOWN_FLAGS: u16 = COMPRESSED;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 2 + 8;
+/// The number of strings in each checksummed section
+const SECTION: usize = 4096;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC-32C of `bytes`
+pub(crate) fn crc32c(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, b| {
+        CRC_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Write a snapshot of every string in the pool to `w`, returning the
+/// number of strings written. The built in common strings are not
+/// included. The pool is only locked while the strings are gathered,
+/// not while they are written.
+pub fn write_snapshot(mut w: impl io::Write) -> Result<usize> {
+    let strs: Vec<Str> = ROOT.lock().strings().collect();
+    write_header(&mut w, 0, strs.len())?;
+    write_strings(&mut w, &strs)?;
+    Ok(strs.len())
+}
+
+/// Write a delta snapshot of the strings interned since `since` to
+/// `w`, returning the number of strings written and the generation to
+/// pass to the next call. A delta since the default generation holds
+/// the whole pool, so a log can start with one. Deltas are never
+/// compressed, so they can be appended to the same file and read back
+/// together with `load_delta`.
+pub fn save_delta(since: Generation, mut w: impl io::Write) -> Result<(usize, Generation)> {
+    let (strs, generation) = new_since(since);
+    write_header(&mut w, 0, strs.len())?;
+    write_strings(&mut w, &strs)?;
+    Ok((strs.len(), generation))
+}
+
+/// Like `write_snapshot`, but compress the strings with zstd at
+/// `level`, where 0 is zstd's default. Symbol tables typically shrink
+/// by an order of magnitude or more.
+#[cfg(feature = "zstd")]
+pub fn write_snapshot_compressed(mut w: impl io::Write, level: i32) -> Result<usize> {
+    let strs: Vec<Str> = ROOT.lock().strings().collect();
+    write_header(&mut w, COMPRESSED, strs.len())?;
+    let mut enc = zstd::Encoder::new(w, level)?;
+    write_strings(&mut enc, &strs)?;
+    enc.finish()?;
+    Ok(strs.len())
+}
+
+fn write_header(w: impl io::Write, flags: u16, count: usize) -> Result<()> {
+    write_versioned_header(w, VERSION, flags, count)
+}
+
+/// Write a header for `version`, which is at least 2, so a snapshot
+/// can be rewritten without changing the layout of its strings
+fn write_versioned_header(
+    mut w: impl io::Write,
+    version: u16,
+    flags: u16,
+    count: usize,
+) -> Result<()> {
+    let mut header = MAGIC.to_vec();
+    header.extend(version.to_le_bytes());
+    header.extend(flags.to_le_bytes());
+    header.extend((count as u64).to_le_bytes());
+    if version >= 3 {
+        header.extend(crc32c(&header).to_le_bytes());
+    }
+    w.write_all(&header)?;
+    Ok(())
+}
+
+fn write_strings(mut w: impl io::Write, strs: &[impl AsRef<str>]) -> Result<()> {
+    let mut section = Vec::new();
+    for strs in strs.chunks(SECTION) {
+        section.clear();
+        for s in strs {
+            let s = s.as_ref();
+            section.push(s.len() as u8);
+            section.extend_from_slice(s.as_bytes());
+        }
+        w.write_all(&(section.len() as u32).to_le_bytes())?;
+        w.write_all(&section)?;
+        w.write_all(&crc32c(&section).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Rewrite the snapshot in `bytes` to `w` with its strings ordered by
+/// how often this process has interned them, most often first,
+/// returning the number of strings written. Strings interned equally
+/// often, including those never interned, keep their order. Nothing is
+/// interned, so this can run offline at the end of a representative
+/// run, and the snapshot it writes preloaded by the next one. The
+/// snapshot is written uncompressed; compressed input is read with the
+/// `zstd` feature.
+#[cfg(feature = "frequency")]
+pub fn compact_snapshot(bytes: &[u8], mut w: impl io::Write) -> Result<usize> {
+    #[cfg(feature = "zstd")]
+    let bytes = &*decompress_snapshot(bytes)?;
+    let mut strs: Vec<&str> = snapshot_strings(bytes)?.collect::<Result<_>>()?;
+    let counts: FxHashMap<&str, u64> = crate::frequency::COUNTS
+        .lock()
+        .values()
+        .map(|(t, n)| (t.as_static(), *n))
+        .collect();
+    strs.sort_by_cached_key(|s| Reverse(counts.get(s).copied().unwrap_or(0)));
+    write_header(&mut w, 0, strs.len())?;
+    write_strings(&mut w, &strs)?;
+    Ok(strs.len())
+}
+
+/// Intern every string in a snapshot embedded in the binary, for
+/// example with `include_bytes!`, under a single lock, returning the
+/// number of strings in it. Strings long enough are referenced in
+/// place as with `Str::from_static` instead of being copied.
+///
+/// A compressed snapshot has to be decompressed first, so its strings
+/// are copied.
+///
+/// If the snapshot is malformed the error is returned and the strings
+/// before the problem remain in the pool.
+pub fn preload_embedded(bytes: &'static [u8]) -> Result<usize> {
+    #[cfg(feature = "zstd")]
+    if let Cow::Owned(bytes) = decompress_snapshot(bytes)? {
+        return intern_all(&bytes);
+    }
+    let mut root = ROOT.lock();
+    let mut n = 0;
+    for s in snapshot_strings(bytes)? {
+        root.intern_static(s?)?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Map the snapshot file at `path` into memory read only and intern
+/// its strings from the mapping, as `preload_embedded` does, returning
+/// the number of strings in it. Strings long enough are referenced in
+/// place, so starting up copies nothing but the short strings, and
+/// every process that preloads the same file shares its pages through
+/// the page cache. The mapping is kept until the process exits.
+///
+/// Without unix the file is read into a buffer that lives as long as
+/// the process instead. With `forbid-unsafe` it is read the same way
+/// everywhere, and this function is safe.
+///
+/// # Safety
+///
+/// The strings in the mapping are handed out as `&'static str`, so
+/// neither this process nor any other may modify or truncate the file
+/// for as long as this process runs. A file that changes can make
+/// interned strings change or stop being UTF-8, and one that shrinks
+/// makes reading them raise `SIGBUS`. Replacing the file by renaming
+/// a new one over it is fine, since the mapping keeps the old one.
+#[cfg(all(feature = "mmap", not(feature = "forbid-unsafe")))]
+pub unsafe fn preload_mapped(path: impl AsRef<Path>) -> Result<usize> {
+    let file = File::open(path)?;
+    // SAFETY: the caller keeps the file unchanged while we run
+    #[cfg(unix)]
+    let bytes = unsafe { map(&file)? };
+    #[cfg(not(unix))]
+    let bytes = read_leaked(&file)?;
+    preload_embedded(bytes)
+}
+
+/// Read the snapshot file at `path` into a buffer that lives as long
+/// as the process and intern its strings from it, as
+/// `preload_embedded` does, returning the number of strings in it.
+/// `forbid-unsafe` rules out mapping the file, so unlike without it
+/// this is safe, and the file can change afterwards.
+#[cfg(all(feature = "mmap", feature = "forbid-unsafe"))]
+pub fn preload_mapped(path: impl AsRef<Path>) -> Result<usize> {
+    preload_embedded(read_leaked(&File::open(path)?)?)
+}
+
+/// Map `file` read only for the rest of the process.
+///
+/// # Safety
+///
+/// The file must not be modified or truncated while the process runs.
+#[cfg(all(feature = "mmap", unix, not(feature = "forbid-unsafe")))]
+unsafe fn map(file: &File) -> Result<&'static [u8]> {
+    use std::{os::fd::AsRawFd, ptr, slice};
+    let len = usize::try_from(file.metadata()?.len()).map_err(|_| Error::AllocFailed)?;
+    if len == 0 {
+        return Ok(&[]);
+    }
+    // SAFETY: a fresh read only mapping of the file, which is never
+    // unmapped, so it lives as long as the process, and which the
+    // caller keeps unchanged
+    unsafe {
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(slice::from_raw_parts(ptr as *const u8, len))
+    }
+}
+
+#[cfg(all(feature = "mmap", any(not(unix), feature = "forbid-unsafe")))]
+fn read_leaked(mut file: &File) -> Result<&'static [u8]> {
+    let mut bytes = Vec::new();
+    io::Read::read_to_end(&mut file, &mut bytes)?;
+    Ok(Vec::leak(bytes))
+}
+
+/// Intern every string in the snapshot read from `r` under a single
+/// lock, returning the number of strings in it. The strings are
+/// copied into the pool. Compressed snapshots are read with the
+/// `zstd` feature.
+///
+/// If the snapshot is malformed the error is returned and the strings
+/// before the problem remain in the pool.
+pub fn load_snapshot(mut r: impl io::Read) -> Result<usize> {
+    let mut bytes = Vec::new();
+    r.read_to_end(&mut bytes)?;
+    #[cfg(feature = "zstd")]
+    let bytes = decompress_snapshot(&bytes)?;
+    intern_all(&bytes)
+}
+
+/// Intern every string in a log of snapshots and deltas read from `r`,
+/// written one after another by `write_snapshot` and `save_delta`,
+/// under a single lock, returning the number of strings in them. Each
+/// snapshot is checked as `load_snapshot` checks it, but they must all
+/// be uncompressed and at least version 3, since older ones don't say
+/// where they end.
+///
+/// If the log is malformed the error is returned and the strings
+/// before the problem remain in the pool. A log whose last delta was
+/// only partly written, because the process died while appending it,
+/// fails this way.
+pub fn load_delta(mut r: impl io::Read) -> Result<usize> {
+    let mut bytes = Vec::new();
+    r.read_to_end(&mut bytes)?;
+    let mut bytes = &bytes[..];
+    let mut root = ROOT.lock();
+    let mut n = 0;
+    while !bytes.is_empty() {
+        let (header, rest) = header(bytes)?;
+        if header.version < 3 || header.flags & COMPRESSED != 0 {
+            return Err(Error::Decode(
+                "only uncompressed version 3 snapshots can be logged",
+            ));
+        }
+        let mut strs = Strings {
+            section: &[],
+            rest,
+            count: header.count,
+            more: true,
+        };
+        for s in strs.by_ref() {
+            root.intern(s?)?;
+            n += 1;
+        }
+        bytes = strs.rest;
+    }
+    Ok(n)
+}
+
+fn intern_all(bytes: &[u8]) -> Result<usize> {
+    let mut root = ROOT.lock();
+    let mut n = 0;
+    for s in snapshot_strings(bytes)? {
+        root.intern(s?)?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Return the uncompressed form of a snapshot, which is `bytes` itself
+/// if it isn't compressed
+#[cfg(feature = "zstd")]
+pub fn decompress_snapshot(bytes: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let (header, rest) = header(bytes)?;
+    if header.flags & COMPRESSED == 0 {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let mut out = Vec::new();
+    let count = header.count.unwrap_or(0) as usize;
+    // the strings keep the layout of the version they were written in
+    write_versioned_header(&mut out, header.version, header.flags & !COMPRESSED, count)?;
+    zstd::stream::copy_decode(rest, &mut out)?;
+    Ok(Cow::Owned(out))
+}
+
+/// The parsed header of a snapshot
+struct Header {
+    version: u16,
+    flags: u16,
+    /// the number of strings, which version 1 doesn't record
+    count: Option<u64>,
+}
+
+/// Split `n` bytes off the front of `bytes`
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if bytes.len() < n {
+        return Err(Error::Decode("truncated input"));
+    }
+    let (head, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Ok(head)
+}
+
+fn take_u32(bytes: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()))
+}
+
+/// Parse the header of a snapshot, returning it and the rest of the
+/// input
+fn header(bytes: &[u8]) -> Result<(Header, &[u8])> {
+    if let Some(rest) = bytes.strip_prefix(MAGIC_V1) {
+        let header = Header {
+            version: 1,
+            flags: 0,
+            count: None,
+        };
+        return Ok((header, rest));
+    }
+    let mut rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or(Error::Decode("not a snapshot"))?;
+    let version = u16::from_le_bytes(take(&mut rest, 2)?.try_into().unwrap());
+    if version > VERSION {
+        return Err(Error::Decode("unsupported snapshot version"));
+    }
+    let flags = u16::from_le_bytes(take(&mut rest, 2)?.try_into().unwrap());
+    let count = u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap());
+    if version >= 3 && take_u32(&mut rest)? != crc32c(&bytes[..HEADER_LEN]) {
+        return Err(Error::Decode("header checksum mismatch"));
+    }
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(Error::Decode("unsupported snapshot layout"));
+    }
+    let header = Header {
+        version,
+        flags,
+        count: Some(count),
+    };
+    Ok((header, rest))
+}
+
+/// The strings of an uncompressed snapshot
+struct Strings<'a> {
+    /// the unread strings of the current section
+    section: &'a [u8],
+    /// the sections after the current one
+    rest: &'a [u8],
+    /// the number of strings left, if the header says
+    count: Option<u64>,
+    /// whether another snapshot may follow this one, which is then
+    /// left in `rest`
+    more: bool,
+}
+
+impl<'a> Strings<'a> {
+    fn fail(&mut self, e: Error) -> Option<Result<&'a str>> {
+        self.section = &[];
+        self.rest = &[];
+        self.count = Some(0);
+        Some(Err(e))
+    }
+
+    fn next_section(&mut self) -> Result<()> {
+        let len = take_u32(&mut self.rest)? as usize;
+        let section = take(&mut self.rest, len)?;
+        if take_u32(&mut self.rest)? != crc32c(section) {
+            return Err(Error::Decode("checksum mismatch"));
+        }
+        self.section = section;
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Strings<'a> {
+    type Item = Result<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let done = self.count == Some(0) && self.more;
+        if self.section.is_empty() && !self.rest.is_empty() && !done {
+            if let Err(e) = self.next_section() {
+                return self.fail(e);
+            }
+        }
+        match self.count.as_mut() {
+            Some(0) if self.section.is_empty() => return None,
+            Some(0) => return self.fail(Error::Decode("trailing data")),
+            Some(_) if self.section.is_empty() => {
+                return self.fail(Error::Decode("truncated input"))
+            }
+            Some(n) => *n -= 1,
+            None => (),
+        }
+        let (&len, tail) = self.section.split_first()?;
+        if tail.len() < len as usize {
+            return self.fail(Error::Decode("truncated input"));
+        }
+        let (s, tail) = tail.split_at(len as usize);
+        self.section = tail;
+        match str::from_utf8(s) {
+            Ok(s) => Some(Ok(s)),
+            Err(e) => self.fail(e.into()),
+        }
+    }
+}
+
+/// Iterate over the strings in a snapshot without interning them, for
+/// tools that examine snapshots. Fails up front if the header is
+/// missing, damaged or not understood, and yields an error and stops
+/// if the rest is malformed or a checksum doesn't match. Compressed
+/// snapshots must be decompressed with `decompress_snapshot` first.
+pub fn snapshot_strings(bytes: &[u8]) -> Result<impl Iterator<Item = Result<&str>>> {
+    let (header, rest) = header(bytes)?;
+    if header.flags & COMPRESSED != 0 {
+        return Err(Error::Decode("snapshot is compressed"));
+    }
+    // before version 3 the strings are one section without a length
+    // or a checksum
+    let (section, rest) = if header.version >= 3 {
+        (&[][..], rest)
+    } else {
+        (rest, &[][..])
+    };
+    Ok(Strings {
+        section,
+        rest,
+        count: header.count,
+        more: false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let a = Str::try_from("snapshot a").unwrap();
+        let mut buf = Vec::new();
+        let n = write_snapshot(&mut buf).unwrap();
+        assert!(n > 0 && buf.starts_with(MAGIC));
+        let buf: &'static [u8] = buf.leak();
+        assert!(preload_embedded(buf).unwrap() >= n);
+        assert_eq!(Str::try_from("snapshot a").unwrap(), a);
+        assert!(matches!(preload_embedded(b"nope"), Err(Error::Decode(_))));
+        assert!(matches!(
+            preload_embedded(b"hcs1\x05abc"),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            preload_embedded(b"hcs1\x01\xff"),
+            Err(Error::Utf8(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_strings() {
+        let strs: Vec<&str> = snapshot_strings(b"hcs1\x01a\x00\x03abc")
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(strs, ["a", "", "abc"]);
+        let mut it = snapshot_strings(b"hcs1\x01a\x05ab").unwrap();
+        assert_eq!(it.next().unwrap().unwrap(), "a");
+        assert!(it.next().unwrap().is_err());
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_versions() {
+        let snap = |version: u16, flags: u16, count: u64, body: &[u8]| {
+            let mut b = MAGIC.to_vec();
+            b.extend(version.to_le_bytes());
+            b.extend(flags.to_le_bytes());
+            b.extend(count.to_le_bytes());
+            b.extend(body);
+            b
+        };
+        let read = |b: &[u8]| -> Result<Vec<String>> {
+            snapshot_strings(b)?.map(|s| s.map(String::from)).collect()
+        };
+        assert_eq!(read(&snap(2, 0, 2, b"\x01a\x02bc")).unwrap(), ["a", "bc"]);
+        let err = |b: &[u8]| match read(b) {
+            Err(Error::Decode(e)) => e,
+            r => panic!("{r:?}"),
+        };
+        assert_eq!(err(&snap(4, 0, 0, b"")), "unsupported snapshot version");
+        assert_eq!(err(&snap(2, 2, 0, b"")), "unsupported snapshot layout");
+        assert_eq!(err(&snap(2, COMPRESSED, 0, b"")), "snapshot is compressed");
+        assert_eq!(err(&snap(2, 0, 2, b"\x01a")), "truncated input");
+        assert_eq!(err(&snap(2, 0, 1, b"\x01a\x01b")), "trailing data");
+        assert_eq!(err(&snap(2, 0, 0, b"")[..10]), "truncated input");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed() {
+        let strs: Vec<String> = (0..1000).map(|i| format!("compressed {i}")).collect();
+        for s in &strs {
+            Str::try_from(s.as_str()).unwrap();
+        }
+        let (mut plain, mut packed) = (Vec::new(), Vec::new());
+        write_snapshot(&mut plain).unwrap();
+        let n = write_snapshot_compressed(&mut packed, 0).unwrap();
+        assert!(packed.len() < plain.len() / 2);
+        let unpacked = decompress_snapshot(&packed).unwrap();
+        let got: Vec<&str> = snapshot_strings(&unpacked)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(got.len(), n);
+        assert!(strs.iter().all(|s| got.contains(&s.as_str())));
+        assert_eq!(load_snapshot(&packed[..]).unwrap(), n);
+        assert!(preload_embedded(packed.leak()).unwrap() == n);
+        assert!(matches!(decompress_snapshot(&plain), Ok(Cow::Borrowed(_))));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed_v2() {
+        let mut v2 = MAGIC.to_vec();
+        v2.extend(2u16.to_le_bytes());
+        v2.extend(COMPRESSED.to_le_bytes());
+        v2.extend(2u64.to_le_bytes());
+        v2.extend(zstd::encode_all(&b"\x01a\x0bcompressed2"[..], 0).unwrap());
+        let unpacked = decompress_snapshot(&v2).unwrap();
+        let got: Vec<&str> = snapshot_strings(&unpacked)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(got, ["a", "compressed2"]);
+        assert_eq!(load_snapshot(&v2[..]).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_delta() {
+        let mut log = Vec::new();
+        let (n, g) = save_delta(Generation::default(), &mut log).unwrap();
+        assert!(n > 0);
+        Str::try_from("delta a").unwrap();
+        Str::try_from("delta b").unwrap();
+        let (m, g) = save_delta(g, &mut log).unwrap();
+        // other tests may be interning at the same time
+        assert!(m >= 2);
+        let (_, g) = save_delta(g, &mut log).unwrap();
+        let mut delta = Vec::new();
+        assert_eq!(save_delta(g, &mut delta).unwrap().0, delta_len(&delta));
+        log.extend(&delta);
+        let total = load_delta(&log[..]).unwrap();
+        assert!(total >= n + m);
+        // a delta alone is a snapshot
+        let mut one = Vec::new();
+        let (k, _) = save_delta(g, &mut one).unwrap();
+        assert_eq!(load_snapshot(&one[..]).unwrap(), k);
+        assert_eq!(load_delta(&[][..]).unwrap(), 0);
+        // a delta cut off while it was appended
+        let mut cut = log.clone();
+        write_snapshot(&mut cut).unwrap();
+        cut.pop();
+        assert!(matches!(load_delta(&cut[..]), Err(Error::Decode(_))));
+        // full snapshots don't accept what follows them
+        assert!(matches!(load_snapshot(&log[..]), Err(Error::Decode(_))));
+        assert!(matches!(
+            load_delta(&b"hcs1\x01a"[..]),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[cfg(feature = "frequency")]
+    #[test]
+    fn test_compact() {
+        let snap = |strs: &[&str]| {
+            let mut b = Vec::new();
+            write_header(&mut b, 0, strs.len()).unwrap();
+            write_strings(&mut b, strs).unwrap();
+            b
+        };
+        let input = snap(&[
+            "compact never",
+            "compact hot",
+            "compact cold",
+            "compact again",
+        ]);
+        for _ in 0..3 {
+            Str::try_from("compact hot").unwrap();
+        }
+        Str::try_from("compact cold").unwrap();
+        Str::try_from("compact again").unwrap();
+        let mut out = Vec::new();
+        assert_eq!(compact_snapshot(&input, &mut out).unwrap(), 4);
+        let got: Vec<&str> = snapshot_strings(&out)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        // ties, and strings never interned, keep their order
+        assert_eq!(
+            got,
+            [
+                "compact hot",
+                "compact cold",
+                "compact again",
+                "compact never"
+            ]
+        );
+        assert!(compact_snapshot(b"nope", &mut out).is_err());
+    }
+
+    fn delta_len(b: &[u8]) -> usize {
+        snapshot_strings(b).unwrap().count()
+    }
+
+    #[test]
+    fn test_checksums() {
+        for i in 0..SECTION + 1 {
+            Str::try_from(format!("checksum {i}").as_str()).unwrap();
+        }
+        let mut buf = Vec::new();
+        let n = write_snapshot(&mut buf).unwrap();
+        let count = |b: &[u8]| -> Result<usize> {
+            snapshot_strings(b)?.try_fold(0, |n, s| s.map(|_| n + 1))
+        };
+        assert_eq!(count(&buf).unwrap(), n);
+        let err = |b: &[u8]| match count(b) {
+            Err(Error::Decode(e)) => e,
+            r => panic!("{r:?}"),
+        };
+        let mut bad = buf.clone();
+        bad[HEADER_LEN + 4 + 10] ^= 1;
+        assert_eq!(err(&bad), "checksum mismatch");
+        let mut bad = buf.clone();
+        bad[MAGIC.len() + 5] ^= 1;
+        assert_eq!(err(&bad), "header checksum mismatch");
+        assert_eq!(err(&buf[..buf.len() - 1]), "truncated input");
+    }
+}
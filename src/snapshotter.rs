@@ -0,0 +1,97 @@
+//! Persisting new strings in the background, behind the `tokio`
+//! feature
+
+use crate::{generation, save_delta, Generation, Result};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    panic,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    task::{self, JoinHandle},
+    time::{self, MissedTickBehavior},
+};
+
+/// Append the strings interned since `since` to the log at `path`,
+/// returning the generation to continue from
+fn append(path: &Path, since: Generation) -> Result<Generation> {
+    let mut buf = Vec::new();
+    let (_, generation) = save_delta(since, &mut buf)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&buf)?;
+    file.sync_data()?;
+    Ok(generation)
+}
+
+/// Spawn a task that appends the strings interned since its last run
+/// to the log at `path` every `interval`, with `save_delta`, so a
+/// restarted process can warm start from a recent copy of the pool by
+/// reading the log with `load_delta`. Gathering the new strings takes
+/// the pool lock only briefly, and the file is written and synced on
+/// tokio's blocking thread pool, so neither interning threads nor the
+/// runtime wait on the disk. Nothing is written when no string has
+/// been interned since the last run.
+///
+/// If `path` doesn't exist the first delta holds the whole pool. If it
+/// does it is taken to hold the strings already in the pool, as it
+/// will if it was just loaded, and only strings interned from now on
+/// are appended. The task runs until it is aborted, or until writing
+/// fails, when it finishes with the error. It must be spawned from
+/// within a tokio runtime.
+pub fn spawn_snapshotter(path: impl Into<PathBuf>, interval: Duration) -> JoinHandle<Result<()>> {
+    let path = path.into();
+    let mut since = if path.exists() {
+        generation()
+    } else {
+        Generation::default()
+    };
+    tokio::spawn(async move {
+        let mut ticks = time::interval(interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            if generation() == since {
+                continue;
+            }
+            let path = path.clone();
+            since = match task::spawn_blocking(move || append(&path, since)).await {
+                Ok(res) => res?,
+                Err(e) => panic::resume_unwind(e.into_panic()),
+            };
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{load_delta, Str};
+    use std::{env, fs, process};
+
+    #[test]
+    fn test_snapshotter() {
+        let path = env::temp_dir().join(format!("hcstatic-str-snapshotter-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let log = rt.block_on(async {
+            let task = spawn_snapshotter(&path, Duration::from_millis(5));
+            Str::try_from("snapshotter first").unwrap();
+            time::sleep(Duration::from_millis(50)).await;
+            Str::try_from("snapshotter second").unwrap();
+            time::sleep(Duration::from_millis(50)).await;
+            task.abort();
+            fs::read(&path).unwrap()
+        });
+        fs::remove_file(&path).unwrap();
+        assert!(load_delta(&log[..]).unwrap() > 0);
+        for s in ["snapshotter first", "snapshotter second"] {
+            let n = log.windows(s.len()).filter(|w| *w == s.as_bytes()).count();
+            assert_eq!(n, 1, "{s}");
+        }
+    }
+}
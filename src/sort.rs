@@ -0,0 +1,117 @@
+//! Sorting large slices of strings by contents
+
+use crate::Str;
+use fxhash::FxHashMap;
+use std::collections::HashMap;
+
+/// The number of string bytes in a key
+const STRIDE: usize = 7;
+
+/// Slices with at most this many strings are checked for duplicates
+/// to decide whether to sort the distinct strings instead
+const SAMPLE: usize = 1 << 16;
+
+/// A key ordering strings that agree on their first `depth` bytes by
+/// their next `STRIDE` bytes, and then by how many of those they have,
+/// with `STRIDE + 1` meaning that they go on past them. Strings with
+/// equal keys that go on must be compared again further along.
+fn key(t: Str, depth: usize) -> u64 {
+    let rest = &t.as_bytes()[depth..];
+    let n = rest.len().min(STRIDE);
+    let mut k = [0; 8];
+    k[..n].copy_from_slice(&rest[..n]);
+    k[STRIDE] = rest.len().min(STRIDE + 1) as u8;
+    u64::from_be_bytes(k)
+}
+
+/// Sort strings paired with their keys at depth 0
+fn sort_keyed(items: &mut [(u64, Str)]) {
+    // slices known to agree on their first depth bytes, kept on the
+    // heap rather than recursing, since the depth can reach MAX_LEN
+    let mut work = vec![(0, items.len(), 0)];
+    while let Some((lo, hi, depth)) = work.pop() {
+        let part = &mut items[lo..hi];
+        if depth > 0 {
+            for (k, t) in part.iter_mut() {
+                *k = key(*t, depth);
+            }
+        }
+        part.sort_unstable_by_key(|(k, _)| *k);
+        let mut start = 0;
+        for end in 1..=part.len() {
+            if end == part.len() || part[end].0 != part[start].0 {
+                let goes_on = part[start].0 as u8 as usize > STRIDE;
+                if end - start > 1 && goes_on {
+                    work.push((lo + start, lo + end, depth + STRIDE));
+                }
+                start = end;
+            }
+        }
+    }
+}
+
+/// Sort `strs` by contents, giving the same order as `sort_unstable`,
+/// but faster on large slices, often several times over. The strings are sorted on
+/// integer keys holding a few of their bytes at a time, read from the
+/// pool once per key, in the manner of a most significant digit first
+/// radix sort, instead of being compared byte by byte straight from
+/// the pool on every comparison. Equal strings are the same `Str`, so
+/// if the slice is mostly duplicates only the distinct strings are
+/// sorted, and the slice is then filled in with as many copies of each
+/// as it held. This uses 16 bytes of memory for each string sorted.
+pub fn sort_interned(strs: &mut [Str]) {
+    let mut counts: FxHashMap<Str, usize> = HashMap::default();
+    for t in &strs[..strs.len().min(SAMPLE)] {
+        *counts.entry(*t).or_insert(0) += 1;
+    }
+    if counts.len() * 2 > strs.len().min(SAMPLE) {
+        let mut items: Vec<(u64, Str)> = strs.iter().map(|t| (key(*t, 0), *t)).collect();
+        sort_keyed(&mut items);
+        for (t, (_, u)) in strs.iter_mut().zip(items) {
+            *t = u;
+        }
+        return;
+    }
+    for t in &strs[SAMPLE.min(strs.len())..] {
+        *counts.entry(*t).or_insert(0) += 1;
+    }
+    let mut items: Vec<(u64, Str)> = counts.keys().map(|t| (key(*t, 0), *t)).collect();
+    sort_keyed(&mut items);
+    let mut i = 0;
+    for (_, t) in items {
+        let n = counts[&t];
+        strs[i..i + n].fill(t);
+        i += n;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_sort_interned() {
+        let mut rng = StdRng::seed_from_u64(194);
+        let words: Vec<Str> = (0..3000)
+            .map(|_| {
+                let len = rng.gen_range(0..20);
+                let s: String = (0..len).map(|_| rng.gen_range('\0'..='c')).collect();
+                Str::try_from(format!("sort {s}").as_str()).unwrap()
+            })
+            .chain(
+                ["", "sort", "sort é", &"z".repeat(Str::MAX_LEN)]
+                    .map(|s| Str::try_from(s).unwrap()),
+            )
+            .collect();
+        // mostly distinct, then mostly duplicates
+        for n in [words.len(), 100_000] {
+            let mut strs: Vec<Str> = (0..n).map(|i| words[i * 7 % words.len()]).collect();
+            let mut expected = strs.clone();
+            expected.sort_unstable();
+            sort_interned(&mut strs);
+            assert_eq!(strs, expected);
+        }
+        sort_interned(&mut []);
+    }
+}
@@ -0,0 +1,55 @@
+//! A fixed size buffer for building a string on the stack before
+//! interning it, so helpers that transform their input don't need to
+//! allocate a `String` first.
+
+use crate::{Error, Result, Str, MAX_LEN};
+use std::{fmt, str};
+
+pub(crate) struct StackBuf {
+    buf: [u8; MAX_LEN],
+    /// the length of everything pushed so far, which may be more
+    /// than fits in buf
+    len: usize,
+}
+
+impl StackBuf {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: [0; MAX_LEN],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn push_str(&mut self, s: &str) {
+        let end = self.len + s.len();
+        if end <= MAX_LEN {
+            self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        }
+        self.len = end;
+    }
+
+    pub(crate) fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// The string built so far, or `Error::TooLong` if it didn't fit
+    pub(crate) fn as_str(&self) -> Result<&str> {
+        if self.len > MAX_LEN {
+            Err(Error::TooLong { len: self.len })
+        } else {
+            // only whole strs are ever pushed, so this can't fail
+            Ok(str::from_utf8(&self.buf[..self.len]).unwrap())
+        }
+    }
+
+    pub(crate) fn intern(&self) -> Result<Str> {
+        Str::try_from(self.as_str()?)
+    }
+}
+
+impl fmt::Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+//! Interning strings before `main`, with the `ctor` feature.
+//!
+//! The pool sets itself up the first time it is used, whenever that
+//! is, so interning from a constructor that runs before `main` is safe
+//! and doesn't depend on the order constructors run in. What changes
+//! is that settings fixed when the pool is first used, the hash seed
+//! and the shard count of the `dashmap` index, are fixed by then, so
+//! `Config::apply` can't change them from `main`; call
+//! `configure_from_env` from a constructor of your own if they are
+//! needed. With `single-threaded` the pool belongs to the thread that
+//! runs the constructors, which is the main thread unless the crate
+//! is in a library loaded later.
+
+/// Declare `LazyStr` statics that are interned before `main`, with
+/// the `ctor` feature, so a plugin registry built by constructors can
+/// hold their handles.
+///
+/// ```
+/// hcstatic_str::intern_at_startup! {
+///     pub static GREETING = "hello";
+///     static FAREWELL = "goodbye";
+/// }
+///
+/// assert!(GREETING.is_interned());
+/// assert_eq!(&**FAREWELL, "goodbye");
+/// ```
+#[macro_export]
+macro_rules! intern_at_startup {
+    ($($(#[$meta:meta])* $vis:vis static $name:ident = $lit:literal;)*) => {
+        $(
+            $(#[$meta])*
+            $vis static $name: $crate::LazyStr = $crate::LazyStr::new($lit);
+        )*
+
+        const _: () = {
+            #[$crate::__private::ctor::ctor(crate_path = $crate::__private::ctor)]
+            unsafe fn intern_at_startup() {
+                $($name.get();)*
+            }
+        };
+    };
+}
@@ -0,0 +1,545 @@
+//! Reporting on the memory used by the pool
+
+#[cfg(feature = "frequency")]
+use crate::frequency;
+#[cfg(feature = "counters")]
+use crate::sync::{Lazy, Mutex};
+use crate::{Str, ROOT};
+use std::cmp::Reverse;
+#[cfg(feature = "counters")]
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+// targets without 64 bit atomics count with word sized ones, which
+// wrap sooner
+#[cfg(all(
+    any(feature = "counters", feature = "lock-contention"),
+    target_has_atomic = "64"
+))]
+pub(crate) type Counter = std::sync::atomic::AtomicU64;
+#[cfg(all(
+    any(feature = "counters", feature = "lock-contention"),
+    not(target_has_atomic = "64")
+))]
+pub(crate) type Counter = std::sync::atomic::AtomicUsize;
+#[cfg(all(feature = "counters", target_has_atomic = "64"))]
+const COUNTER_MAX: u64 = u64::MAX;
+#[cfg(all(feature = "counters", not(target_has_atomic = "64")))]
+const COUNTER_MAX: u64 = usize::MAX as u64;
+
+#[cfg(feature = "counters")]
+static HITS: Counter = Counter::new(0);
+#[cfg(feature = "counters")]
+static MISSES: Counter = Counter::new(0);
+
+#[cfg(any(feature = "counters", feature = "lock-contention"))]
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn load(c: &Counter) -> u64 {
+    c.load(std::sync::atomic::Ordering::Relaxed) as u64
+}
+
+/// Count an interning call that found its string, `t`, already
+/// interned
+#[inline]
+pub(crate) fn hit(_t: Str) {
+    #[cfg(feature = "counters")]
+    HITS.fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "frequency")]
+    frequency::count(_t);
+}
+
+/// Count an interning call that inserted a new string, `t`
+#[inline]
+pub(crate) fn miss(_t: Str) {
+    #[cfg(feature = "counters")]
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "counters")]
+    if WATCHING.load(Ordering::Relaxed) {
+        watch();
+    }
+    #[cfg(feature = "frequency")]
+    frequency::count(_t);
+}
+
+/// How the memory of one chunk of the pool is being used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkInfo {
+    /// The size of the chunk's allocation
+    pub capacity: usize,
+    /// The number of bytes of string data stored in the chunk
+    pub used: usize,
+    /// The number of bytes that don't hold string data and never
+    /// will. This is the entry headers and their padding, static
+    /// string records and name hashes, plus the unwritten tail of
+    /// chunks that have been retired because the next string didn't
+    /// fit, unless `set_recycle_slack` fills it.
+    pub wasted: usize,
+}
+
+/// Report how the memory of each chunk in the pool is being used, in
+/// allocation order. The last chunk is the one currently being
+/// filled, its free space is neither used nor wasted.
+pub fn chunk_report() -> Vec<ChunkInfo> {
+    let root = ROOT.lock();
+    let last = root.chunks.len().saturating_sub(1);
+    root.chunks
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let written = c.capacity - c.free();
+            let tail = if i == last { 0 } else { c.free() };
+            ChunkInfo {
+                capacity: c.capacity,
+                used: c.payload,
+                wasted: written - c.payload + tail,
+            }
+        })
+        .collect()
+}
+
+/// Statistics about the whole pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// The number of strings in the pool, not counting the built in
+    /// common strings
+    pub strings: usize,
+    /// The number of chunks allocated
+    pub chunks: usize,
+    /// The total size of the chunks
+    pub allocated: usize,
+    /// The number of bytes of string data stored in chunks
+    pub used: usize,
+    /// The number of bytes taken by entry headers, static string
+    /// records, and the name hashes stored with the `name-hash` feature
+    pub headers: usize,
+    /// The unwritten tails of retired chunks, which only tiny strings
+    /// stored by `set_recycle_slack` will ever use
+    pub slack: usize,
+    /// The space left in the chunk currently being filled
+    pub free: usize,
+    /// The bytes allocated by the index, including its empty slots.
+    /// This is not part of `allocated`.
+    pub index: usize,
+    /// The number of interning calls that found their string already
+    /// interned. Only counted with the `counters` feature, otherwise
+    /// 0.
+    pub hits: u64,
+    /// The number of interning calls that inserted a new string. Only
+    /// counted with the `counters` feature, otherwise 0.
+    pub misses: u64,
+}
+
+impl Stats {
+    /// The bytes the pool uses for anything other than string data:
+    /// entry headers, the slack of retired chunks, and the index
+    pub fn overhead(&self) -> usize {
+        self.headers + self.slack + self.index
+    }
+
+    /// Everything the pool has allocated, the chunks and the index
+    pub fn footprint(&self) -> usize {
+        self.allocated + self.index
+    }
+}
+
+/// The strings that account for the most of the pool, from `stats_top`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TopStrings {
+    /// The longest strings, longest first
+    pub longest: Vec<Str>,
+    /// The strings that contribute the most bytes, most first, with
+    /// how many. With the `frequency` feature a string contributes its
+    /// length for every time it was interned, otherwise just its
+    /// length once.
+    pub largest: Vec<(Str, u64)>,
+}
+
+/// Report the `n` longest strings in the pool, and the `n` strings
+/// that contribute the most bytes. Ties are broken by the string. The
+/// pool is only locked while its strings are gathered.
+pub fn stats_top(n: usize) -> TopStrings {
+    let mut strs: Vec<Str> = ROOT.lock().strings().collect();
+    #[cfg(feature = "frequency")]
+    let bytes = |t: Str| t.len() as u64 * frequency::request_count(t);
+    #[cfg(not(feature = "frequency"))]
+    let bytes = |t: Str| t.len() as u64;
+    let mut largest: Vec<(Str, u64)> = strs.iter().map(|t| (*t, bytes(*t))).collect();
+    largest.sort_unstable_by(|(s0, b0), (s1, b1)| (Reverse(b0), s0).cmp(&(Reverse(b1), s1)));
+    largest.truncate(n);
+    strs.sort_unstable_by(|s0, s1| (Reverse(s0.len()), s0).cmp(&(Reverse(s1.len()), s1)));
+    strs.truncate(n);
+    TopStrings {
+        longest: strs,
+        largest,
+    }
+}
+
+/// Gather statistics about the pool. `used + headers + slack + free`
+/// always equals `allocated`.
+pub fn stats() -> Stats {
+    let root = ROOT.lock();
+    let mut st = Stats {
+        strings: root.all.len(),
+        chunks: root.chunks.len(),
+        allocated: root.chunks.iter().map(|c| c.capacity).sum(),
+        used: 0,
+        headers: 0,
+        slack: 0,
+        free: 0,
+        index: root.all.allocation_size(),
+        #[cfg(feature = "counters")]
+        hits: load(&HITS),
+        #[cfg(not(feature = "counters"))]
+        hits: 0,
+        #[cfg(feature = "counters")]
+        misses: load(&MISSES),
+        #[cfg(not(feature = "counters"))]
+        misses: 0,
+    };
+    let last = root.chunks.len().saturating_sub(1);
+    for (i, c) in root.chunks.iter().enumerate() {
+        st.used += c.payload;
+        st.headers += c.capacity - c.free() - c.payload;
+        if i == last {
+            st.free += c.free();
+        } else {
+            st.slack += c.free();
+        }
+    }
+    st
+}
+
+/// The counters at one moment, kept to work out rates from
+#[cfg(feature = "counters")]
+#[derive(Clone, Copy)]
+pub(crate) struct Sample {
+    at: Instant,
+    hits: u64,
+    misses: u64,
+}
+
+/// Samples taken by `interning_rates`, at most one a second, oldest
+/// first, going back a little over the longest window
+#[cfg(feature = "counters")]
+pub(crate) static SAMPLES: Lazy<Mutex<VecDeque<Sample>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[cfg(feature = "counters")]
+const SAMPLE_EVERY: Duration = Duration::from_secs(1);
+#[cfg(feature = "counters")]
+const LONGEST: Duration = Duration::from_secs(15 * 60);
+
+/// How much interning went on over a window, from `interning_rates`
+#[cfg(feature = "counters")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    /// The time the counts cover. This is shorter than the window
+    /// until `interning_rates` has been called for that long.
+    pub span: Duration,
+    /// The number of strings inserted
+    pub new_strings: u64,
+    /// The number of interning calls that found their string
+    pub hits: u64,
+}
+
+#[cfg(feature = "counters")]
+impl Rate {
+    /// New strings per second, or 0 over an empty span
+    pub fn new_per_sec(&self) -> f64 {
+        per_sec(self.new_strings, self.span)
+    }
+
+    /// Hits per second, or 0 over an empty span
+    pub fn hits_per_sec(&self) -> f64 {
+        per_sec(self.hits, self.span)
+    }
+}
+
+#[cfg(feature = "counters")]
+fn per_sec(n: u64, span: Duration) -> f64 {
+    if span.is_zero() {
+        0.
+    } else {
+        n as f64 / span.as_secs_f64()
+    }
+}
+
+/// Interning throughput over the last 1, 5 and 15 minutes
+#[cfg(feature = "counters")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rates {
+    /// The last minute
+    pub one_minute: Rate,
+    /// The last 5 minutes
+    pub five_minutes: Rate,
+    /// The last 15 minutes
+    pub fifteen_minutes: Rate,
+}
+
+/// Report how many strings were inserted and how many hits there were
+/// over the last 1, 5 and 15 minutes, so a dashboard can show when a
+/// deployment starts interning unbounded unique values, which the
+/// pool keeps forever. Rates are worked out from samples of the
+/// counters that this takes each time it is called, at most one a
+/// second, so it costs nothing on the interning path; call it
+/// regularly, as a metrics scrape does. Until it has been called for
+/// as long as a window, that window's rate covers only the time since
+/// the first call.
+#[cfg(feature = "counters")]
+pub fn interning_rates() -> Rates {
+    let now = Sample {
+        at: Instant::now(),
+        hits: load(&HITS),
+        misses: load(&MISSES),
+    };
+    let mut samples = SAMPLES.lock();
+    record(&mut samples, now);
+    let over = |minutes: u64| rate(&samples, now, Duration::from_secs(minutes * 60));
+    Rates {
+        one_minute: over(1),
+        five_minutes: over(5),
+        fifteen_minutes: over(15),
+    }
+}
+
+/// Keep `now` if the last sample is old enough, and drop samples no
+/// window reaches back to
+#[cfg(feature = "counters")]
+fn record(samples: &mut VecDeque<Sample>, now: Sample) {
+    if samples
+        .back()
+        .is_none_or(|s| now.at.duration_since(s.at) >= SAMPLE_EVERY)
+    {
+        samples.push_back(now);
+    }
+    // keep the newest sample at least LONGEST old, it starts the
+    // longest window
+    while samples
+        .get(1)
+        .is_some_and(|s| now.at.duration_since(s.at) >= LONGEST)
+    {
+        samples.pop_front();
+    }
+}
+
+/// The count from `start` to `now` of a counter, which may have
+/// wrapped in between
+#[cfg(feature = "counters")]
+fn since(now: u64, start: u64) -> u64 {
+    now.wrapping_sub(start) & COUNTER_MAX
+}
+
+/// What happened between the start of the window ending at `now`, or
+/// the oldest sample if none is that old, and `now`
+#[cfg(feature = "counters")]
+fn rate(samples: &VecDeque<Sample>, now: Sample, window: Duration) -> Rate {
+    let start = samples
+        .iter()
+        .rev()
+        .find(|s| now.at.duration_since(s.at) >= window)
+        .or(samples.front())
+        .copied()
+        .unwrap_or(now);
+    Rate {
+        span: now.at.duration_since(start.at),
+        new_strings: since(now.misses, start.misses),
+        hits: since(now.hits, start.hits),
+    }
+}
+
+/// What `warn_on_low_hit_ratio` saw when it warned
+#[cfg(feature = "counters")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowHitRatio {
+    /// The number of interning calls in the window
+    pub calls: u64,
+    /// How many of them found their string already interned
+    pub hits: u64,
+}
+
+#[cfg(feature = "counters")]
+impl LowHitRatio {
+    /// The fraction of calls that were hits
+    pub fn ratio(&self) -> f64 {
+        self.hits as f64 / self.calls as f64
+    }
+}
+
+#[cfg(feature = "counters")]
+pub(crate) struct Watch {
+    window: u64,
+    min_ratio: f64,
+    hits: u64,
+    misses: u64,
+    warn: Box<dyn FnOnce(LowHitRatio) + Send>,
+}
+
+/// Set while a watch is armed, so misses only lock WATCH then
+#[cfg(feature = "counters")]
+static WATCHING: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "counters")]
+pub(crate) static WATCH: Lazy<Mutex<Option<Watch>>> = Lazy::new(|| Mutex::new(None));
+
+/// Watch the hit ratio over each run of `window` interning calls, and
+/// call `warn` once, the first time fewer than `min_ratio` of the
+/// calls in a run found their string already interned. A pool that
+/// keeps missing is being fed unique values, such as ids or
+/// timestamps, which it keeps forever, so this is worth logging
+/// loudly. Calling it again replaces the previous watch.
+///
+/// The watch is checked when a string is inserted, with the pool
+/// locked, so `warn` must not intern. Misses take an extra lock while
+/// a watch is armed, and hits cost nothing extra.
+#[cfg(feature = "counters")]
+pub fn warn_on_low_hit_ratio(
+    window: u64,
+    min_ratio: f64,
+    warn: impl FnOnce(LowHitRatio) + Send + 'static,
+) {
+    *WATCH.lock() = Some(Watch {
+        window: window.max(1),
+        min_ratio,
+        hits: load(&HITS),
+        misses: load(&MISSES),
+        warn: Box::new(warn),
+    });
+    WATCHING.store(true, Ordering::Relaxed);
+}
+
+#[cfg(feature = "counters")]
+fn watch() {
+    let mut watch = WATCH.lock();
+    let Some(w) = watch.as_mut() else { return };
+    let (hits, misses) = (load(&HITS), load(&MISSES));
+    let seen = LowHitRatio {
+        calls: since(hits, w.hits) + since(misses, w.misses),
+        hits: since(hits, w.hits),
+    };
+    if seen.calls < w.window {
+        return;
+    }
+    if (seen.hits as f64) < w.min_ratio * seen.calls as f64 {
+        WATCHING.store(false, Ordering::Relaxed);
+        let w = watch.take().unwrap();
+        drop(watch);
+        (w.warn)(seen);
+    } else {
+        (w.hits, w.misses) = (hits, misses);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Str;
+
+    #[test]
+    fn test_chunk_report() {
+        Str::try_from("chunk report").unwrap();
+        let report = chunk_report();
+        assert!(!report.is_empty());
+        for c in &report[..report.len() - 1] {
+            assert_eq!(c.used + c.wasted, c.capacity);
+        }
+        let last = report.last().unwrap();
+        assert!(last.used >= "chunk report".len());
+        assert!(last.used + last.wasted <= last.capacity);
+    }
+
+    #[cfg(feature = "counters")]
+    #[test]
+    fn test_counters() {
+        let st0 = stats();
+        Str::try_from("counted string").unwrap();
+        Str::try_from("counted string").unwrap();
+        let st1 = stats();
+        // other tests may be interning at the same time
+        assert!(st1.misses > st0.misses);
+        assert!(st1.hits > st0.hits);
+    }
+
+    #[cfg(feature = "counters")]
+    #[test]
+    fn test_low_hit_ratio() {
+        use std::sync::mpsc;
+        let (tx, rx) = mpsc::channel();
+        let watch = move |min_ratio: f64| {
+            let tx = tx.clone();
+            warn_on_low_hit_ratio(100, min_ratio, move |seen| tx.send(seen).unwrap());
+        };
+        // nothing is below a ratio of 0
+        watch(0.);
+        for i in 0..300 {
+            Str::try_from(format!("hit ratio {i}").as_str()).unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+        watch(0.99);
+        for i in 0..300 {
+            Str::try_from(format!("hit ratio unique {i}").as_str()).unwrap();
+        }
+        let seen = rx.try_recv().unwrap();
+        assert!(seen.calls >= 100 && seen.ratio() < 0.99);
+        // it only warns once
+        for i in 0..300 {
+            Str::try_from(format!("hit ratio unique again {i}").as_str()).unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(feature = "counters")]
+    #[test]
+    fn test_rates() {
+        let t0 = Instant::now();
+        let at = |secs, hits, misses| Sample {
+            at: t0 + Duration::from_secs(secs),
+            hits,
+            misses,
+        };
+        let mut samples = VecDeque::new();
+        for (secs, hits, misses) in [(0, 0, 0), (30, 300, 30), (60, 600, 60), (61, 610, 61)] {
+            record(&mut samples, at(secs, hits, misses));
+        }
+        assert_eq!(samples.len(), 4);
+        let now = at(90, 900, 150);
+        let r = rate(&samples, now, Duration::from_secs(60));
+        assert_eq!(
+            (r.span, r.hits, r.new_strings),
+            (Duration::from_secs(60), 600, 120)
+        );
+        assert_eq!(r.new_per_sec(), 2.);
+        // the 5 minute window only reaches back to the first sample
+        let r = rate(&samples, now, Duration::from_secs(300));
+        assert_eq!((r.span, r.hits), (Duration::from_secs(90), 900));
+        assert_eq!(r.hits_per_sec(), 10.);
+        // samples closer than a second apart aren't kept
+        record(&mut samples, at(61, 611, 61));
+        assert_eq!(samples.len(), 4);
+        // and those no window reaches are dropped
+        record(&mut samples, at(1000, 1000, 100));
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].at, t0 + Duration::from_secs(61));
+        let r = rate(&samples, at(1000, 1000, 100), Duration::from_secs(60));
+        assert_eq!(r.span, Duration::from_secs(939));
+        let empty = rate(&VecDeque::new(), now, Duration::from_secs(60));
+        assert_eq!((empty.span, empty.new_per_sec()), (Duration::ZERO, 0.));
+        let rates = interning_rates();
+        assert!(rates.one_minute.span <= rates.fifteen_minutes.span);
+    }
+
+    #[test]
+    fn test_stats_top() {
+        Str::try_from("x".repeat(Str::MAX_LEN).as_str()).unwrap();
+        let top = stats_top(3);
+        assert_eq!(top.longest.len(), 3);
+        assert_eq!(top.longest[0].len(), Str::MAX_LEN);
+        assert!(top.longest.windows(2).all(|w| w[0].len() >= w[1].len()));
+        assert!(top.largest.windows(2).all(|w| w[0].1 >= w[1].1));
+        #[cfg(not(feature = "frequency"))]
+        assert_eq!(top.largest[0].1, Str::MAX_LEN as u64);
+    }
+}
@@ -0,0 +1,89 @@
+//! Interned strings with a tighter length bound
+
+use crate::{Error, Result, Str, MAX_LEN};
+use std::{fmt, ops::Deref};
+
+/// A `Str` that is guaranteed to be at most `N` bytes long. The
+/// bound is checked once, when the string is interned, and can then be
+/// relied on anywhere the type appears. `N` must be at most 255.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StrMax<const N: usize>(Str);
+
+impl<const N: usize> StrMax<N> {
+    /// Check that `s` is at most `N` bytes long
+    pub fn new(s: Str) -> Result<Self> {
+        const { assert!(N <= MAX_LEN) };
+        if s.len() > N {
+            Err(Error::TooLong { len: s.len() })
+        } else {
+            Ok(StrMax(s))
+        }
+    }
+
+    /// The interned string
+    pub fn get(self) -> Str {
+        self.0
+    }
+
+    /// Convert to a looser bound. This can't fail, and using an `M`
+    /// smaller than `N` is a compile error.
+    pub fn widen<const M: usize>(self) -> StrMax<M> {
+        const { assert!(N <= M && M <= MAX_LEN) };
+        StrMax(self.0)
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for StrMax<N> {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        if s.len() > N {
+            Err(Error::TooLong { len: s.len() })
+        } else {
+            StrMax::new(Str::try_from(s)?)
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<Str> for StrMax<N> {
+    type Error = Error;
+
+    fn try_from(s: Str) -> Result<Self> {
+        StrMax::new(s)
+    }
+}
+
+impl<const N: usize> From<StrMax<N>> for Str {
+    fn from(s: StrMax<N>) -> Str {
+        s.0
+    }
+}
+
+impl<const N: usize> Deref for StrMax<N> {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.0.as_static()
+    }
+}
+
+impl<const N: usize> fmt::Debug for StrMax<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strmax() {
+        let s = StrMax::<8>::try_from("ident").unwrap();
+        assert!(StrMax::<4>::try_from("ident").is_err());
+        let w: StrMax<64> = s.widen();
+        assert_eq!(w.get(), s.get());
+        assert_eq!(&*w, "ident");
+        assert!(StrMax::<4>::new(Str::from(w)).is_err());
+    }
+}
@@ -0,0 +1,36 @@
+//! Watching strings as they are interned
+
+use crate::{Str, ROOT};
+use std::sync::mpsc;
+
+/// Return a channel that receives every string interned from now on,
+/// in the order they were interned. Strings are sent while the pool
+/// lock is held, so nothing is missed or reordered, and the channel is
+/// unbounded so interning never waits on a slow listener. Drop the
+/// receiver to unsubscribe; the sender is removed on the next insert.
+pub fn subscribe() -> mpsc::Receiver<Str> {
+    let (tx, rx) = mpsc::channel();
+    ROOT.lock().subscribers.push(tx);
+    rx
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_subscribe() {
+        let rx = subscribe();
+        let a = Str::try_from("subscribed a").unwrap();
+        Str::try_from("subscribed a").unwrap();
+        let b = Str::try_from("subscribed b").unwrap();
+        // other tests may be interning at the same time
+        let got: Vec<Str> = rx
+            .try_iter()
+            .filter(|t| t.starts_with("subscribed"))
+            .collect();
+        assert_eq!(got, [a, b]);
+        drop(rx);
+        Str::try_from("subscribed c").unwrap();
+    }
+}
@@ -0,0 +1,462 @@
+//! Dense ids for interned strings, and tables keyed by them, behind
+//! the `ids` feature.
+//!
+//! Every string gets the next id the first time it is interned, after
+//! the built in common strings, which take the ids up to 1120. Ids are
+//! therefore small, dense, and handed out in the order strings are
+//! first interned, so a program that interns the same strings in the
+//! same order gets the same ids. With the feature the id is stored in
+//! the trailer after each entry, for 4 bytes per string, and reading
+//! it is a load. With `forbid-unsafe` it is looked up in a table
+//! instead.
+
+#[cfg(not(feature = "forbid-unsafe"))]
+use crate::HASH_LEN;
+use crate::{common, Str, ROOT};
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, Index, Sub},
+};
+
+impl Str {
+    /// The dense id of the string
+    pub fn id(self) -> u32 {
+        #[cfg(not(feature = "forbid-unsafe"))]
+        return common::id(self).unwrap_or_else(|| {
+            // SAFETY: the trailer of every entry in a chunk ends with
+            // the id of its string
+            let id = unsafe { (self.trailer_ptr().add(HASH_LEN) as *const [u8; 4]).read() };
+            u32::from_le_bytes(id)
+        });
+        #[cfg(feature = "forbid-unsafe")]
+        return common::id(self).unwrap_or_else(|| crate::leaked::id(self));
+    }
+
+    /// The string with id `id`, if that id has been given out. This
+    /// takes the pool lock, unless `id` is the id of a common string.
+    /// With `shared-pool`, it only finds strings interned through the
+    /// copy of the crate that shared its pool.
+    pub fn from_id(id: u32) -> Option<Str> {
+        common::by_id(id).or_else(|| resolve([id]).pop())
+    }
+}
+
+/// The strings with `ids`, which have all been given out, under one
+/// lock acquisition
+fn resolve(ids: impl IntoIterator<Item = u32>) -> Vec<Str> {
+    let root = ROOT.lock();
+    ids.into_iter()
+        .filter_map(|id| match common::by_id(id) {
+            Some(t) => Some(t),
+            None => root.by_id.get((id - common::COUNT) as usize).copied(),
+        })
+        .collect()
+}
+
+/// A map from interned strings to values, stored in a vector indexed
+/// by the id of the string, so lookups are a load and a bounds check,
+/// with no hashing. This is the side table a compiler keeps for the
+/// type, documentation, or use count of each symbol. The vector grows
+/// to the largest id inserted, so the map suits tables covering a good
+/// part of the pool, and wastes space on a few strings with large ids.
+/// Each slot also holds its key, so the map can be iterated, in id
+/// order.
+#[derive(Clone)]
+pub struct SymbolMap<V> {
+    slots: Vec<Option<(Str, V)>>,
+    len: usize,
+}
+
+impl<V> Default for SymbolMap<V> {
+    fn default() -> Self {
+        SymbolMap::new()
+    }
+}
+
+impl<V> SymbolMap<V> {
+    pub const fn new() -> Self {
+        SymbolMap {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// A map with room for every id below `ids` without growing
+    pub fn with_capacity(ids: usize) -> Self {
+        SymbolMap {
+            slots: Vec::with_capacity(ids),
+            len: 0,
+        }
+    }
+
+    /// The number of strings in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn slot(&self, key: Str) -> Option<&(Str, V)> {
+        self.slots.get(key.id() as usize)?.as_ref()
+    }
+
+    fn slot_mut(&mut self, key: Str) -> &mut Option<(Str, V)> {
+        let id = key.id() as usize;
+        if id >= self.slots.len() {
+            self.slots.resize_with(id + 1, || None);
+        }
+        &mut self.slots[id]
+    }
+
+    pub fn get(&self, key: Str) -> Option<&V> {
+        self.slot(key).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: Str) -> Option<&mut V> {
+        self.slots
+            .get_mut(key.id() as usize)?
+            .as_mut()
+            .map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: Str) -> bool {
+        self.slot(key).is_some()
+    }
+
+    /// Insert `value` for `key`, returning the value it replaced
+    pub fn insert(&mut self, key: Str, value: V) -> Option<V> {
+        let old = self.slot_mut(key).replace((key, value));
+        if old.is_none() {
+            self.len += 1;
+        }
+        old.map(|(_, v)| v)
+    }
+
+    /// The value for `key`, inserting the result of `f` first if there
+    /// isn't one
+    pub fn get_or_insert_with(&mut self, key: Str, f: impl FnOnce() -> V) -> &mut V {
+        if !self.contains_key(key) {
+            self.insert(key, f());
+        }
+        self.get_mut(key).unwrap()
+    }
+
+    pub fn remove(&mut self, key: Str) -> Option<V> {
+        let (_, v) = self.slots.get_mut(key.id() as usize)?.take()?;
+        self.len -= 1;
+        Some(v)
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    /// The strings in the map and their values, in id order
+    pub fn iter(&self) -> impl Iterator<Item = (Str, &V)> + '_ {
+        self.slots.iter().flatten().map(|(k, v)| (*k, v))
+    }
+
+    /// The values in the map, in id order of their strings
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+}
+
+impl<V> Index<Str> for SymbolMap<V> {
+    type Output = V;
+
+    /// Panics if `key` isn't in the map
+    fn index(&self, key: Str) -> &V {
+        self.get(key).expect("string not in the SymbolMap")
+    }
+}
+
+impl<V> Extend<(Str, V)> for SymbolMap<V> {
+    fn extend<I: IntoIterator<Item = (Str, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<V> FromIterator<(Str, V)> for SymbolMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Str, V)>>(iter: I) -> Self {
+        let mut map = SymbolMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for SymbolMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// A set of interned strings, stored as a bitset indexed by the id of
+/// the string, so membership is a load and a mask, and union,
+/// intersection and difference work a word, 64 strings, at a time. A
+/// set covering most of the pool takes about a bit per string, against
+/// the 9 bytes or more per string of a `HashSet<Str>`. Like
+/// `SymbolMap`, it grows to the largest id inserted.
+#[derive(Clone, Default)]
+pub struct SymbolSet {
+    words: Vec<u64>,
+}
+
+impl SymbolSet {
+    pub const fn new() -> Self {
+        SymbolSet { words: Vec::new() }
+    }
+
+    /// A set with room for every id below `ids` without growing
+    pub fn with_capacity(ids: usize) -> Self {
+        SymbolSet {
+            words: Vec::with_capacity(ids.div_ceil(64)),
+        }
+    }
+
+    fn position(key: Str) -> (usize, u64) {
+        let id = key.id() as usize;
+        (id / 64, 1 << (id % 64))
+    }
+
+    /// Add `key`, returning false if it was already in the set
+    pub fn insert(&mut self, key: Str) -> bool {
+        let (word, bit) = SymbolSet::position(key);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let new = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        new
+    }
+
+    /// Remove `key`, returning false if it wasn't in the set
+    pub fn remove(&mut self, key: Str) -> bool {
+        let (word, bit) = SymbolSet::position(key);
+        match self.words.get_mut(word) {
+            Some(w) if *w & bit != 0 => {
+                *w &= !bit;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn contains(&self, key: Str) -> bool {
+        let (word, bit) = SymbolSet::position(key);
+        self.words.get(word).is_some_and(|w| w & bit != 0)
+    }
+
+    /// The number of strings in the set, which counts the bits of every
+    /// word
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear()
+    }
+
+    /// Add every string in `other` to this set
+    pub fn union_with(&mut self, other: &SymbolSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w |= o;
+        }
+    }
+
+    /// Remove every string that isn't in `other` from this set
+    pub fn intersect_with(&mut self, other: &SymbolSet) {
+        self.words.truncate(other.words.len());
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w &= o;
+        }
+    }
+
+    /// Remove every string in `other` from this set
+    pub fn difference_with(&mut self, other: &SymbolSet) {
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w &= !o;
+        }
+    }
+
+    /// Whether every string in this set is in `other`
+    pub fn is_subset(&self, other: &SymbolSet) -> bool {
+        self.words
+            .iter()
+            .enumerate()
+            .all(|(i, w)| w & !other.words.get(i).copied().unwrap_or(0) == 0)
+    }
+
+    /// The ids of the strings in the set, in order
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, w)| {
+            let mut w = *w;
+            std::iter::from_fn(move || {
+                (w != 0).then(|| {
+                    let bit = w.trailing_zeros();
+                    w &= w - 1;
+                    (i * 64) as u32 + bit
+                })
+            })
+        })
+    }
+
+    /// The strings in the set, in id order. The bitset only holds ids,
+    /// so this looks the strings up under one acquisition of the pool
+    /// lock.
+    pub fn to_vec(&self) -> Vec<Str> {
+        resolve(self.ids())
+    }
+}
+
+impl PartialEq for SymbolSet {
+    /// Sets are equal if they hold the same strings, however far each
+    /// has grown
+    fn eq(&self, other: &Self) -> bool {
+        let (short, long) = if self.words.len() <= other.words.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        short.words == long.words[..short.words.len()]
+            && long.words[short.words.len()..].iter().all(|w| *w == 0)
+    }
+}
+
+impl Eq for SymbolSet {}
+
+impl BitOr for &SymbolSet {
+    type Output = SymbolSet;
+
+    fn bitor(self, other: &SymbolSet) -> SymbolSet {
+        let mut set = self.clone();
+        set.union_with(other);
+        set
+    }
+}
+
+impl BitAnd for &SymbolSet {
+    type Output = SymbolSet;
+
+    fn bitand(self, other: &SymbolSet) -> SymbolSet {
+        let mut set = self.clone();
+        set.intersect_with(other);
+        set
+    }
+}
+
+impl Sub for &SymbolSet {
+    type Output = SymbolSet;
+
+    fn sub(self, other: &SymbolSet) -> SymbolSet {
+        let mut set = self.clone();
+        set.difference_with(other);
+        set
+    }
+}
+
+impl Extend<Str> for SymbolSet {
+    fn extend<I: IntoIterator<Item = Str>>(&mut self, iter: I) {
+        for t in iter {
+            self.insert(t);
+        }
+    }
+}
+
+impl FromIterator<Str> for SymbolSet {
+    fn from_iter<I: IntoIterator<Item = Str>>(iter: I) -> Self {
+        let mut set = SymbolSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl fmt::Debug for SymbolSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.to_vec()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(Str::try_from("").unwrap().id(), 0);
+        assert_eq!(Str::try_from("a").unwrap().id(), 1 + b'a' as u32);
+        assert_eq!(Str::try_from("false").unwrap().id(), 130);
+        assert_eq!(Str::try_from("10").unwrap().id(), 131);
+        assert_eq!(Str::try_from("999").unwrap().id(), common::COUNT - 1);
+        let a = Str::try_from("symbol ids a").unwrap();
+        let b = Str::try_from("symbol ids b").unwrap();
+        let c = Str::from_static("symbol ids from static memory").unwrap();
+        let long = Str::try_from(&*"i".repeat(Str::MAX_LEN)).unwrap();
+        // other tests intern concurrently, so ids are only increasing
+        assert!(common::COUNT <= a.id() && a.id() < b.id() && b.id() < c.id());
+        assert!(c.id() < long.id());
+        assert_eq!(a.id(), Str::try_from("symbol ids a").unwrap().id());
+        for t in [a, b, c, long, Str::try_from("7").unwrap()] {
+            assert_eq!(Str::from_id(t.id()), Some(t));
+        }
+        assert_eq!(Str::from_id(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_symbol_set() {
+        let strs = ["set a", "set b", "set c", "x"].map(|s| Str::try_from(s).unwrap());
+        let [a, b, c, x] = strs;
+        let ab: SymbolSet = [a, b].into_iter().collect();
+        let mut bcx = SymbolSet::new();
+        assert!(bcx.insert(x) && bcx.insert(c) && bcx.insert(b));
+        assert!(!bcx.insert(b));
+        assert_eq!((ab.len(), bcx.len()), (2, 3));
+        assert!(ab.contains(a) && !ab.contains(c));
+        assert_eq!((&ab | &bcx).to_vec(), [x, a, b, c]);
+        assert_eq!((&ab & &bcx).to_vec(), [b]);
+        assert_eq!((&bcx - &ab).to_vec(), [x, c]);
+        assert!((&ab & &bcx).is_subset(&ab) && !ab.is_subset(&bcx));
+        // x has a smaller id than the others, so its set is shorter
+        let mut only_x: SymbolSet = [x].into_iter().collect();
+        only_x.union_with(&ab);
+        only_x.difference_with(&ab);
+        assert_eq!(only_x, [x].into_iter().collect());
+        assert!(only_x.remove(x) && !only_x.remove(x));
+        assert!(only_x.is_empty());
+        assert_eq!(only_x, SymbolSet::new());
+        assert_eq!(format!("{:?}", &ab & &bcx), r#"{"set b"}"#);
+    }
+
+    #[test]
+    fn test_symbol_map() {
+        let [a, b, c] = ["symbol map a", "symbol map b", "7"].map(|s| Str::try_from(s).unwrap());
+        let mut map = SymbolMap::new();
+        assert_eq!(map.insert(b, 2), None);
+        assert_eq!(map.insert(c, 3), None);
+        assert_eq!(map.insert(b, 20), Some(2));
+        assert_eq!(map.len(), 2);
+        assert_eq!((map.get(a), map[b]), (None, 20));
+        *map.get_or_insert_with(a, || 0) += 1;
+        *map.get_or_insert_with(a, || 0) += 1;
+        assert_eq!(map[a], 2);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(c, &3), (a, &2), (b, &20)]);
+        assert_eq!(map.remove(c), Some(3));
+        assert_eq!(map.remove(c), None);
+        assert!(!map.contains_key(c));
+        assert_eq!(map.len(), 2);
+        let copy: SymbolMap<i32> = map.iter().map(|(k, v)| (k, *v)).collect();
+        assert_eq!(format!("{copy:?}"), format!("{map:?}"));
+    }
+}
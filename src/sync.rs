@@ -0,0 +1,172 @@
+//! The synchronization primitives used by the pool.
+//!
+//! The mutex is `std::sync::Mutex` unless the `parking_lot` feature is
+//! enabled, which uses parking_lot's smaller and faster mutex instead.
+//! Either way the pool is never left in an inconsistent state by a
+//! panic, so poisoning is ignored.
+//!
+//! The `single-threaded` feature, which overrides `parking_lot`,
+//! replaces the mutex with unsynchronized interior mutability for
+//! programs that only ever intern from one thread, such as CLIs and
+//! wasm. The first thread to use the pool owns it, and interning from
+//! any other thread panics. Handles can still be sent anywhere, since
+//! reading an interned string never touches the pool. It needs unsafe
+//! code, so `forbid-unsafe` overrides it.
+//!
+//! Lazily initialized statics use once_cell with the default
+//! `once_cell` feature, which supports older toolchains. Without it
+//! they use `std::sync::LazyLock`, and once_cell isn't a dependency.
+
+#[cfg(feature = "once_cell")]
+pub use once_cell::sync::Lazy;
+#[cfg(not(feature = "once_cell"))]
+pub use std::sync::LazyLock as Lazy;
+
+#[cfg(all(
+    feature = "parking_lot",
+    not(all(feature = "single-threaded", not(feature = "forbid-unsafe")))
+))]
+pub(crate) use parking_lot::{Mutex, MutexGuard};
+
+#[cfg(not(any(
+    feature = "parking_lot",
+    all(feature = "single-threaded", not(feature = "forbid-unsafe"))
+)))]
+mod imp {
+    pub(crate) use std::sync::MutexGuard;
+    use std::sync::{self, PoisonError, TryLockError};
+
+    pub(crate) struct Mutex<T>(sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) const fn new(v: T) -> Self {
+            Mutex(sync::Mutex::new(v))
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            match self.0.try_lock() {
+                Ok(g) => Some(g),
+                Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+                Err(TryLockError::WouldBlock) => None,
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "single-threaded", not(feature = "forbid-unsafe")))]
+mod imp {
+    use std::{
+        cell::{Cell, UnsafeCell},
+        ops::{Deref, DerefMut},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    thread_local! {
+        static MARK: u8 = const { 0 };
+    }
+
+    /// An address unique to the calling thread
+    fn thread() -> usize {
+        MARK.with(|m| m as *const u8 as usize)
+    }
+
+    pub(crate) struct Mutex<T> {
+        /// the thread that owns the mutex, 0 until it is first locked
+        owner: AtomicUsize,
+        locked: Cell<bool>,
+        v: UnsafeCell<T>,
+    }
+
+    // Only the owning thread ever gets past check, so locked and v are
+    // never touched by more than one thread.
+    unsafe impl<T: Send> Sync for Mutex<T> {}
+
+    impl<T> Mutex<T> {
+        pub(crate) const fn new(v: T) -> Self {
+            Mutex {
+                owner: AtomicUsize::new(0),
+                locked: Cell::new(false),
+                v: UnsafeCell::new(v),
+            }
+        }
+
+        fn check(&self) {
+            let me = thread();
+            if let Err(owner) =
+                self.owner
+                    .compare_exchange(0, me, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                assert!(
+                    owner == me,
+                    "the pool was used from a second thread with the single-threaded feature"
+                );
+            }
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.try_lock().expect("the pool lock is not reentrant")
+        }
+
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            self.check();
+            if self.locked.replace(true) {
+                None
+            } else {
+                Some(MutexGuard(self))
+            }
+        }
+    }
+
+    pub(crate) struct MutexGuard<'a, T>(&'a Mutex<T>);
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.0.locked.set(false)
+        }
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: the guard is the only way to reach v, and only
+            // one exists at a time
+            unsafe { &*self.0.v.get() }
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // SAFETY: as above
+            unsafe { &mut *self.0.v.get() }
+        }
+    }
+}
+
+#[cfg(any(
+    not(feature = "parking_lot"),
+    all(feature = "single-threaded", not(feature = "forbid-unsafe"))
+))]
+pub(crate) use imp::{Mutex, MutexGuard};
+
+#[cfg(test)]
+mod test {
+    // run with --test-threads=1, so every test uses the pool from the
+    // same thread
+    #[cfg(all(feature = "single-threaded", not(feature = "forbid-unsafe")))]
+    #[test]
+    fn test_single_threaded() {
+        use crate::Str;
+        let t = Str::try_from("single threaded").unwrap();
+        let res = std::thread::spawn(move || {
+            assert_eq!(&*t, "single threaded");
+            Str::try_from("other thread")
+        })
+        .join();
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! A stable, read only string table for consumers outside the
+//! process, such as C programs or firmware, that need to turn ids
+//! back into strings.
+//!
+//! A table is laid out as follows, with every integer little endian:
+//!
+//! | offset       | size      | contents                                |
+//! |--------------|-----------|-----------------------------------------|
+//! | 0            | 4         | the magic bytes `hcst`                  |
+//! | 4            | 2         | the format version, 1                   |
+//! | 6            | 2         | flags, which must be 0                  |
+//! | 8            | 4         | `n`, the number of strings              |
+//! | 12           | 4 `n`     | the end of each string in the data, u32 |
+//! | 12 + 4 `n`   | `d`       | the data, the bytes of every string     |
+//! | 12 + 4 `n` + `d` | 4     | the CRC-32C of everything before it     |
+//!
+//! String `i` is the bytes of the data from the end of string `i - 1`,
+//! or 0 for the first, up to its own end, and is UTF-8. The ends never
+//! decrease and the last one is `d`. `export_table` writes the common
+//! strings first and then every string in the pool, in the order it was
+//! interned with the `ids` feature, so the position of a string in the
+//! table is its id. The format will only ever change under a new
+//! version number.
+//!
+//! `TableReader` reads a table in place, using nothing but `core` and
+//! allocating nothing, so it can be copied into a `#![no_std]` build.
+//! With the `ffi` feature the same reader is exported to C as
+//! `hcstatic_str_table_open` and `hcstatic_str_table_get`.
+
+use crate::{common, snapshot::crc32c, Result, ROOT};
+use core::{fmt, str};
+use std::io;
+
+const MAGIC: &[u8; 4] = b"hcst";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 12;
+
+/// Write a table of every string in the pool, the common strings
+/// first, to `w`, returning the number of strings. The pool is only
+/// locked while the strings are gathered. A pool holding more than
+/// 4 GiB of strings can't be written, and fails with an `Error::Io`.
+pub fn export_table(mut w: impl io::Write) -> Result<usize> {
+    let strs: Vec<&'static str> = {
+        let root = ROOT.lock();
+        // strings written to the tails of retired chunks are out of
+        // order in the chunks
+        #[cfg(feature = "ids")]
+        let pool = root.by_id.iter().copied();
+        #[cfg(not(feature = "ids"))]
+        let pool = root.strings();
+        common::strs().chain(pool.map(|t| t.as_static())).collect()
+    };
+    let mut buf = Vec::with_capacity(HEADER_LEN + strs.len() * 5 + 4);
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&(strs.len() as u32).to_le_bytes());
+    let mut end = 0u32;
+    for s in &strs {
+        end = end
+            .checked_add(s.len() as u32)
+            .ok_or_else(|| io::Error::other("the pool is too large for a string table"))?;
+        buf.extend_from_slice(&end.to_le_bytes());
+    }
+    for s in &strs {
+        buf.extend_from_slice(s.as_bytes());
+    }
+    let crc = crc32c(&buf);
+    buf.extend_from_slice(&crc.to_le_bytes());
+    w.write_all(&buf)?;
+    Ok(strs.len())
+}
+
+/// Why a table couldn't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableError(&'static str);
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid string table: {}", self.0)
+    }
+}
+
+impl std::error::Error for TableError {}
+
+fn u32_at(bytes: &[u8], pos: usize) -> Option<u32> {
+    let b = bytes.get(pos..pos.checked_add(4)?)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// A table written by `export_table`, read in place
+#[derive(Debug, Clone, Copy)]
+pub struct TableReader<'a> {
+    ends: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> TableReader<'a> {
+    /// Check the header, checksum and string bounds of the table in
+    /// `bytes`. This reads the whole table once; lookups after it are
+    /// constant time.
+    pub fn new(bytes: &'a [u8]) -> core::result::Result<Self, TableError> {
+        if bytes.len() < HEADER_LEN + 4 || &bytes[..4] != MAGIC {
+            return Err(TableError("not a string table"));
+        }
+        if bytes[4..6] != VERSION.to_le_bytes() || bytes[6..8] != [0, 0] {
+            return Err(TableError("unsupported version or flags"));
+        }
+        let (body, crc) = bytes.split_at(bytes.len() - 4);
+        if u32_at(crc, 0) != Some(crc32c(body)) {
+            return Err(TableError("checksum mismatch"));
+        }
+        let n = u32_at(body, 8).unwrap() as usize;
+        let ends = n
+            .checked_mul(4)
+            .and_then(|len| body.get(HEADER_LEN..HEADER_LEN.checked_add(len)?))
+            .ok_or(TableError("truncated"))?;
+        let data = &body[HEADER_LEN + ends.len()..];
+        let mut prev = 0;
+        for i in 0..n {
+            let end = u32_at(ends, i * 4).unwrap();
+            if end < prev {
+                return Err(TableError("string ends decrease"));
+            }
+            prev = end;
+        }
+        if prev as usize != data.len() {
+            return Err(TableError("data length doesn't match"));
+        }
+        Ok(TableReader { ends, data })
+    }
+
+    /// The number of strings in the table
+    pub fn len(&self) -> usize {
+        self.ends.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// The string with id `id`, or None if there is no such string or
+    /// it isn't UTF-8
+    pub fn get(&self, id: u32) -> Option<&'a str> {
+        let id = id as usize;
+        let start = match id {
+            0 => 0,
+            _ => u32_at(self.ends, (id - 1).checked_mul(4)?)?,
+        };
+        let end = u32_at(self.ends, id.checked_mul(4)?)?;
+        str::from_utf8(self.data.get(start as usize..end as usize)?).ok()
+    }
+
+    /// Every string in the table, in id order
+    pub fn iter(&self) -> impl Iterator<Item = &'a str> + '_ {
+        (0..self.len() as u32).filter_map(|id| self.get(id))
+    }
+}
+
+/// A table opened by `hcstatic_str_table_open`. It borrows the bytes
+/// it was opened from, which must outlive it and not change. In C it
+/// is declared as below, and its fields should be treated as opaque.
+///
+/// ```c
+/// struct hcstatic_str_table {
+///     const uint8_t *ends;
+///     size_t count;
+///     const uint8_t *data;
+///     size_t data_len;
+/// };
+/// ```
+#[cfg(all(feature = "ffi", not(feature = "forbid-unsafe")))]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct HcstaticStrTable {
+    ends: *const u8,
+    count: usize,
+    data: *const u8,
+    data_len: usize,
+}
+
+/// Check the table in the `len` bytes at `bytes` and fill in `out`,
+/// returning 0, or -1 if it isn't a valid table.
+///
+/// ```c
+/// int hcstatic_str_table_open(const uint8_t *bytes, size_t len,
+///                             struct hcstatic_str_table *out);
+/// ```
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes and `out` to writable
+/// space for a table.
+#[cfg(all(feature = "ffi", not(feature = "forbid-unsafe")))]
+#[no_mangle]
+pub unsafe extern "C" fn hcstatic_str_table_open(
+    bytes: *const u8,
+    len: usize,
+    out: *mut HcstaticStrTable,
+) -> i32 {
+    // SAFETY: the caller passes len readable bytes
+    let bytes = unsafe { std::slice::from_raw_parts(bytes, len) };
+    match TableReader::new(bytes) {
+        Err(_) => -1,
+        Ok(t) => {
+            // SAFETY: the caller passes a writable out pointer
+            unsafe {
+                out.write(HcstaticStrTable {
+                    ends: t.ends.as_ptr(),
+                    count: t.len(),
+                    data: t.data.as_ptr(),
+                    data_len: t.data.len(),
+                })
+            };
+            0
+        }
+    }
+}
+
+/// Store the address and length of the string with id `id` in `ptr`
+/// and `len`, returning 0, or -1 if there is no such string. The
+/// string is not nul terminated.
+///
+/// ```c
+/// int hcstatic_str_table_get(const struct hcstatic_str_table *table,
+///                            uint32_t id, const uint8_t **ptr, size_t *len);
+/// ```
+///
+/// # Safety
+///
+/// `table` must have been filled in by `hcstatic_str_table_open`, and
+/// the bytes it was opened from must still be valid. `ptr` and `len`
+/// must be writable.
+#[cfg(all(feature = "ffi", not(feature = "forbid-unsafe")))]
+#[no_mangle]
+pub unsafe extern "C" fn hcstatic_str_table_get(
+    table: *const HcstaticStrTable,
+    id: u32,
+    ptr: *mut *const u8,
+    len: *mut usize,
+) -> i32 {
+    // SAFETY: the table describes bytes that were checked by
+    // TableReader::new and are still valid
+    let t = unsafe {
+        let t = &*table;
+        TableReader {
+            ends: std::slice::from_raw_parts(t.ends, t.count * 4),
+            data: std::slice::from_raw_parts(t.data, t.data_len),
+        }
+    };
+    match t.get(id) {
+        None => -1,
+        Some(s) => {
+            // SAFETY: the caller passes writable out pointers
+            unsafe {
+                ptr.write(s.as_ptr());
+                len.write(s.len());
+            }
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Str;
+
+    #[test]
+    fn test_table() {
+        let strs = ["table a", "table βeta", "", "table a"].map(|s| Str::try_from(s).unwrap());
+        let mut buf = Vec::new();
+        let n = export_table(&mut buf).unwrap();
+        let t = TableReader::new(&buf).unwrap();
+        assert_eq!(t.len(), n);
+        assert_eq!(t.get(0), Some(""));
+        assert_eq!(t.get(n as u32), None);
+        assert_eq!(t.get(u32::MAX), None);
+        for s in strs {
+            assert!(t.iter().any(|u| u == &*s));
+            #[cfg(feature = "ids")]
+            assert_eq!(t.get(s.id()), Some(&*s));
+        }
+        let mut bad = buf.clone();
+        bad[HEADER_LEN + 1] ^= 1;
+        assert_eq!(TableReader::new(&bad).unwrap_err().0, "checksum mismatch");
+        assert!(TableReader::new(&buf[..buf.len() - 1]).is_err());
+        assert!(TableReader::new(b"hcsv").is_err());
+        // a table with an end past the data, with a valid checksum
+        let mut forged = b"hcst\x01\x00\x00\x00\x01\x00\x00\x00\x05\x00\x00\x00abc".to_vec();
+        let crc = crc32c(&forged);
+        forged.extend(crc.to_le_bytes());
+        assert!(TableReader::new(&forged).is_err());
+    }
+
+    #[cfg(all(feature = "ffi", not(feature = "forbid-unsafe")))]
+    #[test]
+    fn test_ffi() {
+        let t = Str::try_from("table ffi").unwrap();
+        let mut buf = Vec::new();
+        export_table(&mut buf).unwrap();
+        let reader = TableReader::new(&buf).unwrap();
+        let id = reader.iter().position(|s| s == &*t).unwrap() as u32;
+        let mut table = std::mem::MaybeUninit::uninit();
+        let (mut ptr, mut len) = (std::ptr::null(), 0);
+        unsafe {
+            assert_eq!(
+                hcstatic_str_table_open(buf.as_ptr(), 3, table.as_mut_ptr()),
+                -1
+            );
+            assert_eq!(
+                hcstatic_str_table_open(buf.as_ptr(), buf.len(), table.as_mut_ptr()),
+                0
+            );
+            let table = table.assume_init();
+            assert_eq!(hcstatic_str_table_get(&table, id, &mut ptr, &mut len), 0);
+            assert_eq!(std::slice::from_raw_parts(ptr, len), t.as_bytes());
+            assert_eq!(
+                hcstatic_str_table_get(&table, u32::MAX, &mut ptr, &mut len),
+                -1
+            );
+        }
+    }
+}
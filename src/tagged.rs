@@ -0,0 +1,181 @@
+//! Interned strings carrying a user tag byte
+
+#[cfg(not(feature = "forbid-unsafe"))]
+use crate::TAGGED;
+#[cfg(feature = "forbid-unsafe")]
+use crate::TAGGED_RECORD_LEN;
+use crate::{Chunk, Error, Result, Rollback, Root, Str, ROOT};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+#[cfg(not(feature = "forbid-unsafe"))]
+use std::{mem, num::NonZeroUsize, ptr::NonNull};
+
+/// An interned string together with a byte of user metadata, such as
+/// the token kind a lexer assigned to an identifier. The tag is stored
+/// in the chunk next to the handle of the string, so reading either is
+/// one load. Like `Str`, tagged strings are hashconsed: the same string
+/// and tag always give the same handle, and equality is a pointer
+/// comparison. The same string with different tags gives different
+/// `TaggedStr`s, which share the one interned `Str`.
+#[derive(Clone, Copy)]
+#[cfg(not(feature = "forbid-unsafe"))]
+pub struct TaggedStr(NonNull<u8>);
+
+/// With `forbid-unsafe` the string and tag are leaked together
+#[derive(Clone, Copy)]
+#[cfg(feature = "forbid-unsafe")]
+pub struct TaggedStr(&'static (Str, u8));
+
+// The record a TaggedStr points to is immutable and lives forever.
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl Send for TaggedStr {}
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl Sync for TaggedStr {}
+
+#[cfg(feature = "forbid-unsafe")]
+impl TaggedStr {
+    /// The interned string
+    pub fn get(self) -> Str {
+        self.0 .0
+    }
+
+    /// The tag the string was interned with
+    pub fn tag(self) -> u8 {
+        self.0 .1
+    }
+
+    fn addr(self) -> usize {
+        self.0 as *const (Str, u8) as usize
+    }
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl Chunk {
+    /// Account for a record holding t and tag, returning None if it
+    /// doesn't fit
+    fn insert_tagged(&mut self, t: Str, tag: u8) -> Option<TaggedStr> {
+        self.claim(TAGGED_RECORD_LEN)?;
+        Some(TaggedStr(Box::leak(Box::new((t, tag)))))
+    }
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+impl TaggedStr {
+    /// The interned string
+    pub fn get(self) -> Str {
+        // SAFETY: the record was written by insert_tagged and is
+        // immutable, the handle it holds came from a valid Str
+        unsafe {
+            let raw = (self.0.as_ptr().add(2) as *const usize).read_unaligned();
+            Str::from_raw_unchecked(NonZeroUsize::new_unchecked(raw))
+        }
+    }
+
+    /// The tag the string was interned with
+    pub fn tag(self) -> u8 {
+        // SAFETY: as in get
+        unsafe { *self.0.as_ptr().add(2 + mem::size_of::<usize>()) }
+    }
+
+    fn addr(self) -> usize {
+        self.0.as_ptr() as usize
+    }
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+impl Chunk {
+    /// Write a record holding t and tag, returning None if it doesn't
+    /// fit
+    fn insert_tagged(&mut self, t: Str, tag: u8) -> Option<TaggedStr> {
+        let raw = t.as_raw().get().to_ne_bytes();
+        let rec = self.write(0, false, &[&[u8::MAX, TAGGED], &raw, &[tag]])?;
+        Some(TaggedStr(NonNull::new(rec.entry() as *mut u8).unwrap()))
+    }
+}
+
+impl Root {
+    fn intern_tagged(&mut self, s: &str, tag: u8) -> Result<TaggedStr> {
+        let t = self.intern(s)?;
+        if let Some(r) = self.tagged.get(&(t.as_raw(), tag)) {
+            return Ok(*r);
+        }
+        if self.frozen {
+            return Err(Error::Frozen);
+        }
+        self.tagged.try_reserve(1)?;
+        let mark = self.mark();
+        let r = self.write_with(s, |chunk| chunk.insert_tagged(t, tag))?;
+        let guard = Rollback::new(self, mark);
+        guard.root.tagged.insert((t.as_raw(), tag), r);
+        guard.commit();
+        Ok(r)
+    }
+}
+
+impl Str {
+    /// Intern `s` along with `tag`, which can be read back from the
+    /// result with `TaggedStr::tag` without any further lookup.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_with_tag(s: &str, tag: u8) -> Result<TaggedStr> {
+        ROOT.lock().intern_tagged(s, tag)
+    }
+}
+
+impl Deref for TaggedStr {
+    type Target = str;
+
+    fn deref(&self) -> &'static str {
+        self.get().as_static()
+    }
+}
+
+impl fmt::Debug for TaggedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TaggedStr")
+            .field(&&**self)
+            .field(&self.tag())
+            .finish()
+    }
+}
+
+impl PartialEq for TaggedStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for TaggedStr {}
+
+impl Hash for TaggedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tagged() {
+        let a = Str::intern_with_tag("tagged ident", 7).unwrap();
+        assert_eq!((&*a, a.tag()), ("tagged ident", 7));
+        assert_eq!(a, Str::intern_with_tag("tagged ident", 7).unwrap());
+        let b = Str::intern_with_tag("tagged ident", 8).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(b.tag(), 8);
+        let t = Str::try_from("tagged ident").unwrap();
+        assert_eq!(a.get().as_raw(), t.as_raw());
+        assert_eq!(b.get().as_raw(), t.as_raw());
+        let c = Str::intern_with_tag("a", u8::MAX).unwrap();
+        assert_eq!((&*c, c.tag()), ("a", u8::MAX));
+        // records are skipped when walking the strings in the pool
+        let root = ROOT.lock();
+        assert!(root
+            .strings()
+            .all(|s| root.all.get(crate::index::hash(&s), &s) == Some(s)));
+    }
+}
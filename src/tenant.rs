@@ -0,0 +1,171 @@
+//! Per tenant quotas on the global pool
+
+use crate::{budget::intern_charged, Error, Result, Str, ROOT};
+use fxhash::FxHashMap;
+use std::{fmt, num::NonZeroUsize};
+
+/// The accounting of one tenant
+struct Tenant {
+    name: Str,
+    quota: usize,
+    used: usize,
+    strings: usize,
+    rejected: u64,
+}
+
+/// Every tenant, kept in the pool so it is only touched under the pool
+/// lock
+#[derive(Default)]
+pub(crate) struct Tenants {
+    by_name: FxHashMap<NonZeroUsize, usize>,
+    all: Vec<Tenant>,
+}
+
+/// A named share of the global pool with a quota of its own, for
+/// processes that intern strings on behalf of many customers, so one
+/// of them can't fill the pool for everyone. A tenant is charged for
+/// the entry of every string it adds to the pool, and once its quota
+/// is used up, interning strings that aren't in the pool yet fails
+/// with `Error::QuotaExceeded`. Strings that are already interned, by
+/// any tenant or by the program itself through `Str::try_from`, cost
+/// nothing, and are the same `Str` for everyone, so strings are still
+/// stored once and compared by handle across tenants.
+///
+/// Tenants live as long as the pool, as the strings they were charged
+/// for do. With `shared-pool`, strings interned while another copy's
+/// pool is shared aren't charged, since that copy keeps its own
+/// tenants.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TenantPool(usize);
+
+/// What a tenant has used of its quota, returned by
+/// `TenantPool::stats`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TenantStats {
+    /// The bytes the tenant may be charged for
+    pub quota: usize,
+    /// The bytes the tenant has been charged for
+    pub used: usize,
+    /// The number of strings the tenant added to the pool
+    pub strings: usize,
+    /// The number of strings that were refused because they would
+    /// have gone over the quota
+    pub rejected: u64,
+}
+
+impl TenantPool {
+    /// The tenant named `name`, which is created without a quota the
+    /// first time it is asked for. The name is interned in the global
+    /// pool.
+    pub fn new(name: &str) -> Result<Self> {
+        let mut root = ROOT.lock();
+        let name = root.intern(name)?;
+        let tenants = &mut root.tenants;
+        if let Some(i) = tenants.by_name.get(&name.as_raw()) {
+            return Ok(TenantPool(*i));
+        }
+        tenants.all.try_reserve(1)?;
+        tenants.by_name.try_reserve(1)?;
+        tenants.by_name.insert(name.as_raw(), tenants.all.len());
+        tenants.all.push(Tenant {
+            name,
+            quota: usize::MAX,
+            used: 0,
+            strings: 0,
+            rejected: 0,
+        });
+        Ok(TenantPool(tenants.all.len() - 1))
+    }
+
+    /// The name of the tenant
+    pub fn name(self) -> Str {
+        ROOT.lock().tenants.all[self.0].name
+    }
+
+    /// Let the tenant be charged for at most `bytes` of entries. A
+    /// quota below what it has already used stops it adding strings,
+    /// but doesn't take any away.
+    pub fn set_quota(self, bytes: usize) {
+        ROOT.lock().tenants.all[self.0].quota = bytes;
+    }
+
+    pub fn stats(self) -> TenantStats {
+        let root = ROOT.lock();
+        let t = &root.tenants.all[self.0];
+        TenantStats {
+            quota: t.quota,
+            used: t.used,
+            strings: t.strings,
+            rejected: t.rejected,
+        }
+    }
+
+    /// Intern `s` on behalf of the tenant, charging it for the entry
+    /// if `s` isn't interned yet
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern(self, s: &str) -> Result<Str> {
+        intern_charged(
+            s,
+            |root, cost| {
+                let tenant = &mut root.tenants.all[self.0];
+                if tenant.used.saturating_add(cost) > tenant.quota {
+                    tenant.rejected += 1;
+                    return Err(Error::QuotaExceeded);
+                }
+                Ok(())
+            },
+            |root, cost| {
+                let tenant = &mut root.tenants.all[self.0];
+                tenant.used += cost;
+                tenant.strings += 1;
+            },
+        )
+    }
+}
+
+impl fmt::Debug for TenantPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TenantPool").field(&self.name()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::budget::entry_len;
+
+    #[test]
+    fn test_tenants() {
+        let a = TenantPool::new("tenant a").unwrap();
+        let b = TenantPool::new("tenant b").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a, TenantPool::new("tenant a").unwrap());
+        assert_eq!(&*a.name(), "tenant a");
+        let quota = 10 * entry_len("tenant a 0".len());
+        a.set_quota(quota);
+        let strs: Vec<Str> = (0..10)
+            .map(|i| a.intern(&format!("tenant a {i}")).unwrap())
+            .collect();
+        assert!(matches!(a.intern("tenant a 10"), Err(Error::QuotaExceeded)));
+        // strings that are already interned are free, and shared
+        assert_eq!(a.intern("tenant a 3").unwrap(), strs[3]);
+        assert_eq!(a.intern("tenant b").unwrap(), b.name());
+        let t = b.intern("tenant a 10").unwrap();
+        assert_eq!(a.intern("tenant a 10").unwrap().as_raw(), t.as_raw());
+        assert_eq!(
+            Str::try_from("tenant a 9").unwrap().as_raw(),
+            strs[9].as_raw()
+        );
+        assert_eq!(
+            a.stats(),
+            TenantStats {
+                quota,
+                used: quota,
+                strings: 10,
+                rejected: 1,
+            }
+        );
+        assert_eq!(b.stats().strings, 1);
+        assert_eq!(format!("{a:?}"), "TenantPool(\"tenant a\")");
+    }
+}
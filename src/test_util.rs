@@ -0,0 +1,200 @@
+//! Helpers for testing code that interns, behind the `test-util`
+//! feature.
+//!
+//! The pool is global and strings are never removed, so every test in
+//! a process shares it, and a test can't start from an empty pool.
+//! What tests can do deterministically is check whether a string is
+//! in the pool without interning it, with `assert_interned!` and
+//! `assert_not_interned!`, seed fixtures with `seed`, and run inside
+//! `with_isolated_pool`, which keeps other isolated tests from
+//! interning at the same time, reports exactly the strings the test
+//! added, and puts back whatever settings it changed.
+
+#[cfg(feature = "audit")]
+use crate::AuditSink;
+#[cfg(feature = "numa")]
+use crate::NumaPolicy;
+use crate::{
+    generation, new_since, Generation, Global, Guard, Interner, LimitPolicy, Result, Str, ROOT,
+};
+use std::sync::{Mutex, PoisonError};
+
+/// Whether `s` is in the pool, without interning it
+pub fn is_interned(s: &str) -> bool {
+    Global.get(s).is_some()
+}
+
+/// Intern every fixture under a single lock, returning their handles
+/// in the same order
+pub fn seed<S: AsRef<str>>(fixtures: &[S]) -> Result<Vec<Str>> {
+    let mut root = ROOT.lock();
+    root.all.try_reserve(fixtures.len())?;
+    fixtures.iter().map(|s| root.intern(s.as_ref())).collect()
+}
+
+/// Assert that a string is in the pool, without interning it
+#[macro_export]
+macro_rules! assert_interned {
+    ($s:expr $(,)?) => {{
+        let s: &str = ::std::convert::AsRef::as_ref(&$s);
+        assert!($crate::test_util::is_interned(s), "{:?} is not interned", s);
+    }};
+    ($s:expr, $($arg:tt)+) => {
+        assert!($crate::test_util::is_interned(::std::convert::AsRef::as_ref(&$s)), $($arg)+)
+    };
+}
+
+/// Assert that a string is not in the pool, without interning it
+#[macro_export]
+macro_rules! assert_not_interned {
+    ($s:expr $(,)?) => {{
+        let s: &str = ::std::convert::AsRef::as_ref(&$s);
+        assert!(!$crate::test_util::is_interned(s), "{:?} is interned", s);
+    }};
+    ($s:expr, $($arg:tt)+) => {
+        assert!(!$crate::test_util::is_interned(::std::convert::AsRef::as_ref(&$s)), $($arg)+)
+    };
+}
+
+/// Held by `with_isolated_pool` so isolated tests run one at a time
+pub(crate) static ISOLATED: Mutex<()> = Mutex::new(());
+
+/// The pool settings a test might change
+struct Settings {
+    limit: usize,
+    max_chunk: usize,
+    policy: LimitPolicy,
+    align: usize,
+    guard: Option<Guard>,
+    #[cfg(feature = "audit")]
+    audit: Option<Box<dyn AuditSink>>,
+    #[cfg(feature = "mlock")]
+    lock_chunks: bool,
+    #[cfg(feature = "numa")]
+    numa: NumaPolicy,
+}
+
+impl Settings {
+    /// Take the current settings, leaving the defaults in their place
+    /// where they aren't plain values
+    fn take() -> Self {
+        let mut root = ROOT.lock();
+        Settings {
+            limit: root.limit,
+            max_chunk: root.max_chunk,
+            policy: root.policy,
+            align: root.align,
+            guard: root.guard.take(),
+            #[cfg(feature = "audit")]
+            audit: root.audit.take(),
+            #[cfg(feature = "mlock")]
+            lock_chunks: root.lock_chunks,
+            #[cfg(feature = "numa")]
+            numa: root.numa,
+        }
+    }
+}
+
+impl Drop for Settings {
+    fn drop(&mut self) {
+        let mut root = ROOT.lock();
+        root.limit = self.limit;
+        root.max_chunk = self.max_chunk;
+        root.policy = self.policy;
+        root.align = self.align;
+        root.guard = self.guard.take();
+        #[cfg(feature = "audit")]
+        {
+            root.audit = self.audit.take();
+        }
+        #[cfg(feature = "mlock")]
+        {
+            root.lock_chunks = self.lock_chunks;
+        }
+        #[cfg(feature = "numa")]
+        {
+            root.numa = self.numa;
+        }
+    }
+}
+
+/// What happened to the pool during `with_isolated_pool`
+#[derive(Debug)]
+pub struct PoolScope {
+    start: Generation,
+}
+
+impl PoolScope {
+    /// The strings added to the pool since the scope started, in the
+    /// order they were interned. Code interning outside isolated
+    /// tests, on other threads, can still add strings too.
+    pub fn new_strings(&self) -> Vec<Str> {
+        new_since(self.start).0
+    }
+}
+
+/// Run `f`, waiting for any other `with_isolated_pool` call to finish
+/// first, and pass it a `PoolScope` that reports the strings it adds.
+/// The intern guard and, with `audit`, the audit sink are cleared while
+/// it runs, and the memory limit, maximum chunk size, limit policy,
+/// entry alignment and the rest of the pool's settings are put back
+/// afterwards, even if `f` panics. Strings it interns stay in the
+/// pool, and a `freeze` can't be undone.
+pub fn with_isolated_pool<R>(f: impl FnOnce(&PoolScope) -> R) -> R {
+    let _isolated = ISOLATED.lock().unwrap_or_else(PoisonError::into_inner);
+    let _settings = Settings::take();
+    f(&PoolScope {
+        start: generation(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{set_intern_guard, set_memory_limit, Verdict};
+    use std::panic;
+
+    // other tests intern at the same time, so only reject ours
+    fn reject(s: &str) -> Verdict {
+        if s.starts_with("test util") {
+            Verdict::Reject
+        } else {
+            Verdict::Allow
+        }
+    }
+
+    #[test]
+    fn test_util() {
+        assert_not_interned!("test util never interned");
+        assert_not_interned!(String::from("test util never interned"), "{}", "custom");
+        assert_interned!("7");
+        let fixtures = seed(&["test util a", "test util b"]).unwrap();
+        assert_eq!(&*fixtures[1], "test util b");
+        assert_interned!("test util a");
+        assert_interned!(fixtures[0]);
+        let new = with_isolated_pool(|scope| {
+            set_memory_limit(usize::MAX / 2);
+            set_intern_guard(Some(Box::new(reject)));
+            Str::try_from("test util a").unwrap();
+            let res = Str::try_from("test util c");
+            set_intern_guard(None);
+            assert!(res.is_err());
+            Str::try_from("test util c").unwrap();
+            scope.new_strings()
+        });
+        assert!(new.iter().any(|t| &**t == "test util c"));
+        assert!(!new.iter().any(|t| &**t == "test util a"));
+        assert_eq!(ROOT.lock().limit, usize::MAX);
+        // settings are put back when the test panics
+        let res = panic::catch_unwind(|| {
+            with_isolated_pool(|_| {
+                set_intern_guard(Some(Box::new(reject)));
+                panic!("isolated test failed")
+            })
+        });
+        assert!(res.is_err());
+        assert!(ROOT.lock().guard.is_none());
+        let res = panic::catch_unwind(|| assert_interned!("test util never interned"));
+        assert!(res.is_err());
+    }
+}
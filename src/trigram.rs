@@ -0,0 +1,110 @@
+//! A substring index over the pool, behind the `substring-index`
+//! feature.
+//!
+//! The index maps every three byte sequence to the strings containing
+//! it. It is built the first time `strings_containing` is called, and
+//! brought up to date with the strings interned since on every later
+//! call, so interning itself pays nothing for it. A query for a needle
+//! of three or more bytes only checks the strings that contain its
+//! rarest trigram, instead of scanning the whole pool. Each string
+//! costs four bytes per distinct trigram it contains.
+
+use crate::{
+    generation::{new_since, Generation},
+    sync::{Lazy, Mutex},
+    Str,
+};
+use fxhash::FxHashMap;
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub(crate) struct Trigrams {
+    /// the generation the index is up to date with
+    seen: Generation,
+    /// every indexed string, in the order it was interned
+    strs: Vec<Str>,
+    /// the positions in strs of the strings containing each trigram
+    postings: FxHashMap<[u8; 3], Vec<u32>>,
+}
+
+pub(crate) static INDEX: Lazy<Mutex<Trigrams>> = Lazy::new(|| {
+    Mutex::new(Trigrams {
+        postings: HashMap::default(),
+        ..Trigrams::default()
+    })
+});
+
+fn trigrams(s: &[u8]) -> Vec<[u8; 3]> {
+    let mut grams: Vec<[u8; 3]> = s.windows(3).map(|w| [w[0], w[1], w[2]]).collect();
+    grams.sort_unstable();
+    grams.dedup();
+    grams
+}
+
+impl Trigrams {
+    fn update(&mut self) {
+        let (strs, generation) = new_since(self.seen);
+        for t in strs {
+            let id = u32::try_from(self.strs.len()).expect("too many strings to index");
+            for g in trigrams(t.as_bytes()) {
+                self.postings.entry(g).or_default().push(id);
+            }
+            self.strs.push(t);
+        }
+        self.seen = generation;
+    }
+}
+
+/// Every interned string containing `needle`, in the order they were
+/// interned. The built in common strings aren't searched. Needles
+/// shorter than three bytes can't use the index, so they scan every
+/// string.
+pub fn strings_containing(needle: &str) -> Vec<Str> {
+    let mut index = INDEX.lock();
+    index.update();
+    if needle.len() < 3 {
+        return index
+            .strs
+            .iter()
+            .filter(|t| t.contains(needle))
+            .copied()
+            .collect();
+    }
+    let mut rarest: &[u32] = &[];
+    for g in trigrams(needle.as_bytes()) {
+        match index.postings.get(&g) {
+            None => return Vec::new(),
+            Some(ids) if rarest.is_empty() || ids.len() < rarest.len() => rarest = ids,
+            Some(_) => (),
+        }
+    }
+    rarest
+        .iter()
+        .map(|id| index.strs[*id as usize])
+        .filter(|t| t.contains(needle))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strings_containing() {
+        let strs = [
+            "substring::alpha",
+            "substring::beta",
+            "alpha::substring",
+            "sub",
+        ];
+        let strs = strs.map(|s| Str::try_from(s).unwrap());
+        assert_eq!(strings_containing("substring::"), strs[..2]);
+        assert_eq!(strings_containing("alpha::substr"), strs[2..3]);
+        // interned after the index was built
+        let late = Str::try_from("late substring::gamma").unwrap();
+        assert_eq!(strings_containing("::gamma"), [late]);
+        assert!(strings_containing("substring::").contains(&late));
+        assert!(strings_containing("::").contains(&strs[0]));
+        assert!(strings_containing("no such substring anywhere").is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Display length and grapheme safe truncation, behind the `unicode`
+//! feature.
+//!
+//! `MAX_LEN` bounds strings in bytes, but labels shown to people are
+//! measured in chars or grapheme clusters, and cutting one at a char
+//! boundary can still split an emoji sequence or strip a combining
+//! accent from its letter. The counts are computed once per string and
+//! cached by handle, since the strings never change; ASCII strings
+//! skip the cache, since their counts are their length.
+
+use crate::{
+    sync::{Lazy, Mutex},
+    Result, Str, MAX_LEN,
+};
+use fxhash::FxHashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The chars and grapheme clusters in a non ASCII string. Strings are
+/// at most `MAX_LEN` bytes, so both fit in a byte.
+#[derive(Clone, Copy)]
+pub(crate) struct Counts {
+    chars: u8,
+    graphemes: u8,
+}
+
+pub(crate) static COUNTS: Lazy<Mutex<FxHashMap<usize, Counts>>> =
+    Lazy::new(|| Mutex::new(Default::default()));
+
+fn counts(t: Str) -> Counts {
+    let key = t.as_raw().get();
+    if let Some(c) = COUNTS.lock().get(&key) {
+        return *c;
+    }
+    let c = Counts {
+        chars: t.chars().count() as u8,
+        graphemes: t.graphemes(true).count() as u8,
+    };
+    COUNTS.lock().insert(key, c);
+    c
+}
+
+/// The longest prefix of `s` that is at most `max` bytes and ends on a
+/// grapheme cluster boundary
+pub fn grapheme_prefix(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let end = s
+        .grapheme_indices(true)
+        .map(|(i, g)| i + g.len())
+        .take_while(|end| *end <= max)
+        .last()
+        .unwrap_or(0);
+    &s[..end]
+}
+
+impl Str {
+    /// The number of chars in the string, cached after the first call
+    pub fn char_count(self) -> usize {
+        if self.is_ascii() {
+            self.len()
+        } else {
+            counts(self).chars as usize
+        }
+    }
+
+    /// The number of extended grapheme clusters in the string, what a
+    /// reader would count as characters, cached after the first call
+    pub fn grapheme_count(self) -> usize {
+        if self.is_ascii() {
+            // \r\n is the only ASCII cluster of more than one char
+            self.len() - self.as_bytes().windows(2).filter(|w| w == b"\r\n").count()
+        } else {
+            counts(self).graphemes as usize
+        }
+    }
+
+    /// Intern the first `n` grapheme clusters of the string, or return
+    /// it unchanged if it has no more than that
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn truncate_graphemes(self, n: usize) -> Result<Str> {
+        match self.grapheme_indices(true).nth(n) {
+            None => Ok(self),
+            Some((end, _)) => Str::try_from(&self[..end]),
+        }
+    }
+
+    /// Like `intern_truncated`, but cut at the last grapheme cluster
+    /// boundary at or before `MAX_LEN` bytes, so no cluster is split
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn intern_truncated_graphemes(s: &str) -> Result<Str> {
+        Str::try_from(grapheme_prefix(s, MAX_LEN))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unicode() {
+        let t = Str::try_from("cafe\u{301} 👍🏽!").unwrap();
+        assert_eq!((t.char_count(), t.grapheme_count()), (9, 7));
+        assert_eq!((t.char_count(), t.grapheme_count()), (9, 7));
+        let t = Str::try_from("a\r\nb").unwrap();
+        assert_eq!((t.char_count(), t.grapheme_count()), (4, 3));
+        let t = Str::try_from("🇫🇷🇩🇪 flags").unwrap();
+        assert_eq!(&*t.truncate_graphemes(1).unwrap(), "🇫🇷");
+        assert_eq!(t.truncate_graphemes(8).unwrap(), t);
+        assert_eq!(&*t.truncate_graphemes(0).unwrap(), "");
+        // a combining accent pushed past the limit takes its letter
+        // with it
+        let long = format!("{}e\u{301}", "x".repeat(MAX_LEN - 2));
+        let t = Str::intern_truncated_graphemes(&long).unwrap();
+        assert_eq!(t.len(), MAX_LEN - 2);
+        assert_eq!(
+            &*Str::intern_truncated(&long).unwrap(),
+            &long[..MAX_LEN - 1]
+        );
+        assert_eq!(grapheme_prefix("üb", 1), "");
+        assert_eq!(grapheme_prefix("üb", 10), "üb");
+    }
+}
@@ -0,0 +1,56 @@
+//! OpenAPI schemas, behind the `utoipa` feature.
+//!
+//! A `Str` is documented as a string of at most `Str::MAX_LEN` bytes,
+//! so DTOs can hold interned identifiers directly and still generate
+//! correct OpenAPI documents. Being a named schema, it is referenced
+//! as `Str` by the types that contain it.
+
+use crate::Str;
+use ::utoipa::{
+    openapi::{schema::SchemaType, ObjectBuilder, RefOr, Schema, Type},
+    PartialSchema, ToSchema,
+};
+
+impl PartialSchema for Str {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::Type(Type::String))
+            .max_length(Some(Str::MAX_LEN))
+            .description(Some("an interned string"))
+            .into()
+    }
+}
+
+impl ToSchema for Str {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(ToSchema)]
+    #[allow(dead_code)]
+    struct Dto {
+        id: Str,
+        tags: Vec<Str>,
+    }
+
+    #[test]
+    fn test_schema() {
+        let json = |s: RefOr<Schema>| serde_json::to_value(s).unwrap();
+        let s = json(Str::schema());
+        assert_eq!(s["type"], "string");
+        assert_eq!(s["maxLength"], 255);
+        assert_eq!(Str::name(), "Str");
+        let dto = json(Dto::schema());
+        assert_eq!(dto["properties"]["id"]["$ref"], "#/components/schemas/Str");
+        assert_eq!(
+            dto["properties"]["tags"]["items"]["$ref"],
+            "#/components/schemas/Str"
+        );
+        let mut schemas = Vec::new();
+        Dto::schemas(&mut schemas);
+        assert!(schemas
+            .iter()
+            .any(|(name, s)| name == "Str" && json(s.clone()) == json(Str::schema())));
+    }
+}
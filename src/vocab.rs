@@ -0,0 +1,89 @@
+//! Building a vocabulary without filling the pool with rare strings
+
+use crate::{Result, Str, ROOT};
+use fxhash::FxHashMap;
+use std::{cmp::Reverse, collections::HashMap};
+
+/// Counts candidate strings in its own table, then interns only the
+/// ones seen often enough. The strings that fall below the cutoff are
+/// freed with the builder instead of staying in the pool forever.
+#[derive(Debug, Default, Clone)]
+pub struct VocabBuilder {
+    counts: FxHashMap<Box<str>, u64>,
+}
+
+impl VocabBuilder {
+    pub fn new() -> Self {
+        VocabBuilder {
+            counts: HashMap::default(),
+        }
+    }
+
+    /// Count one occurrence of `s`. Strings too long to intern are
+    /// ignored.
+    pub fn add(&mut self, s: &str) {
+        if !Str::is_internable(s) {
+            return;
+        }
+        match self.counts.get_mut(s) {
+            Some(n) => *n += 1,
+            None => {
+                self.counts.insert(s.into(), 1);
+            }
+        }
+    }
+
+    /// The number of times `s` has been counted
+    pub fn count(&self, s: &str) -> u64 {
+        self.counts.get(s).copied().unwrap_or(0)
+    }
+
+    /// The number of distinct strings counted
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Intern every string counted at least `min` times, under a
+    /// single lock, returning them with their counts, most frequent
+    /// first, with ties broken by the string.
+    pub fn build(self, min: u64) -> Result<Vec<(Str, u64)>> {
+        let mut keep: Vec<(Box<str>, u64)> =
+            self.counts.into_iter().filter(|(_, n)| *n >= min).collect();
+        keep.sort_unstable_by(|(s0, n0), (s1, n1)| (Reverse(n0), s0).cmp(&(Reverse(n1), s1)));
+        let mut root = ROOT.lock();
+        root.all.try_reserve(keep.len())?;
+        keep.iter()
+            .map(|(s, n)| Ok((root.intern(s)?, *n)))
+            .collect()
+    }
+}
+
+impl<'a> Extend<&'a str> for VocabBuilder {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.add(s)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Global, Interner};
+
+    #[test]
+    fn test_vocab() {
+        let mut vb = VocabBuilder::new();
+        vb.extend("vocab b vocab a vocab b rare".split(' '));
+        vb.add(&"x".repeat(Str::MAX_LEN + 1));
+        assert_eq!((vb.len(), vb.count("vocab")), (4, 3));
+        let words = vb.build(2).unwrap();
+        let words: Vec<(&str, u64)> = words.iter().map(|(t, n)| (t.as_static(), *n)).collect();
+        assert_eq!(words, [("vocab", 3), ("b", 2)]);
+        assert!(Global.get("rare").is_none());
+    }
+}
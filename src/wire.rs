@@ -0,0 +1,392 @@
+//! A simple protocol for sending interned strings between processes.
+//!
+//! The `Encoder` assigns each distinct string it sends a sequential
+//! u32 id. The first time a string is sent its bytes are transmitted
+//! along with it, after that only the id is. The `Decoder` on the
+//! other end interns the strings into its own pool as they arrive and
+//! remembers them by id.
+//!
+//! Each encoded string is either a definition, the byte `1` followed
+//! by the length as a byte and then the bytes of the string, or a
+//! reference, the byte `0` followed by the id as a little endian u32.
+//! Ids are implicit, the nth definition has id n, so once a definition
+//! fails to decode the ids of the rest are unknown, and the decoder
+//! fails every call after it.
+//!
+//! `encode_slice` packs a whole slice of handles into one blob with
+//! its own dictionary, for storing collections such as per document
+//! token lists. A blob is the number of distinct strings as a LEB128
+//! varint, then each of them in order of first appearance as a length
+//! byte and the bytes of the string, then the number of handles as a
+//! varint, then the position of each handle's string in the
+//! dictionary as a varint.
+
+use crate::{Error, Result, Str, ROOT};
+use fxhash::FxHashMap;
+use std::{collections::HashMap, num::NonZeroUsize, str};
+
+const REF: u8 = 0;
+const DEF: u8 = 1;
+
+/// The sending side of the protocol
+#[derive(Debug, Default)]
+pub struct Encoder {
+    ids: FxHashMap<NonZeroUsize, u32>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self {
+            ids: HashMap::default(),
+        }
+    }
+
+    /// The id assigned to `s`, if it has been sent
+    pub fn id(&self, s: Str) -> Option<u32> {
+        self.ids.get(&s.as_raw()).copied()
+    }
+
+    /// The number of distinct strings sent so far
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Append the encoding of `s` to `buf`
+    pub fn encode(&mut self, s: Str, buf: &mut Vec<u8>) {
+        let next = u32::try_from(self.ids.len()).expect("too many strings");
+        match self.ids.get(&s.as_raw()) {
+            Some(id) => {
+                buf.push(REF);
+                buf.extend_from_slice(&id.to_le_bytes());
+            }
+            None => {
+                self.ids.insert(s.as_raw(), next);
+                buf.push(DEF);
+                buf.push(s.len() as u8);
+                buf.extend_from_slice(s.as_bytes());
+            }
+        }
+    }
+}
+
+/// The receiving side of the protocol
+#[derive(Debug, Default)]
+pub struct Decoder {
+    strs: Vec<Str>,
+    /// set when a definition fails to decode, after which the ids of
+    /// the strings that follow are unknown
+    failed: bool,
+}
+
+/// The length of the first encoded string in `buf`, or None if `buf`
+/// doesn't hold all of it yet. Invalid tags are reported as a length
+/// of 1, so that decoding them fails.
+pub fn frame_len(buf: &[u8]) -> Option<usize> {
+    let len = match *buf.first()? {
+        REF => 5,
+        DEF => 2 + *buf.get(1)? as usize,
+        _ => 1,
+    };
+    (buf.len() >= len).then_some(len)
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if buf.len() < n {
+        return Err(Error::Decode("truncated input"));
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Ok(head)
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            strs: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// The string with id `id`, if it has been received
+    pub fn get(&self, id: u32) -> Option<Str> {
+        self.strs.get(id as usize).copied()
+    }
+
+    /// The number of distinct strings received so far
+    pub fn len(&self) -> usize {
+        self.strs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strs.is_empty()
+    }
+
+    /// Whether a definition failed to decode, after which every call
+    /// to `decode` fails
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Decode one string from the front of `buf`, advancing it past
+    /// the consumed bytes. If decoding fails `buf` is left in an
+    /// unspecified position, and if it was a definition that failed,
+    /// every later call fails too, since the ids the encoder assigned
+    /// after it can no longer be matched up.
+    pub fn decode(&mut self, buf: &mut &[u8]) -> Result<Str> {
+        if self.failed {
+            return Err(Error::Decode("an earlier definition failed to decode"));
+        }
+        match take(buf, 1)?[0] {
+            REF => {
+                let id = u32::from_le_bytes(take(buf, 4)?.try_into().unwrap());
+                self.get(id).ok_or(Error::Decode("unknown string id"))
+            }
+            DEF => {
+                let s = Self::definition(buf);
+                match s {
+                    Ok(s) => self.strs.push(s),
+                    Err(_) => self.failed = true,
+                }
+                s
+            }
+            _ => Err(Error::Decode("invalid tag")),
+        }
+    }
+
+    fn definition(buf: &mut &[u8]) -> Result<Str> {
+        let len = take(buf, 1)?[0] as usize;
+        Str::try_from(str::from_utf8(take(buf, len)?)?)
+    }
+}
+
+/// A tokio-util codec framing strings with the wire protocol, so
+/// both ends of a stream share a dictionary that grows as new strings
+/// are sent.
+#[cfg(feature = "tokio-util")]
+#[derive(Debug, Default)]
+pub struct Codec {
+    pub encoder: Encoder,
+    pub decoder: Decoder,
+}
+
+#[cfg(feature = "tokio-util")]
+impl Codec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl tokio_util::codec::Encoder<Str> for Codec {
+    type Error = Error;
+
+    fn encode(&mut self, s: Str, dst: &mut bytes::BytesMut) -> Result<()> {
+        let mut buf = Vec::with_capacity(2 + s.len());
+        self.encoder.encode(s, &mut buf);
+        dst.extend_from_slice(&buf);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl tokio_util::codec::Decoder for Codec {
+    type Item = Str;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Str>> {
+        use bytes::Buf;
+        if self.decoder.failed {
+            return Err(Error::Decode("an earlier definition failed to decode"));
+        }
+        match frame_len(src) {
+            None => Ok(None),
+            Some(n) => {
+                let s = self.decoder.decode(&mut &src[..n])?;
+                src.advance(n);
+                Ok(Some(s))
+            }
+        }
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        buf.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn take_varint(buf: &mut &[u8]) -> Result<usize> {
+    let mut n = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let b = take(buf, 1)?[0];
+        n |= ((b & 0x7f) as usize)
+            .checked_shl(shift)
+            .filter(|v| v >> shift == (b & 0x7f) as usize)
+            .ok_or(Error::Decode("varint overflows"))?;
+        if b < 0x80 {
+            return Ok(n);
+        }
+    }
+    Err(Error::Decode("varint overflows"))
+}
+
+/// Encode `strs` into a blob that stores each distinct string once
+/// and every handle as a small index, usually a single byte, however
+/// long its string is
+pub fn encode_slice(strs: &[Str]) -> Vec<u8> {
+    let mut ids: FxHashMap<NonZeroUsize, usize> = HashMap::default();
+    let mut dict = Vec::new();
+    let mut indexes = Vec::with_capacity(strs.len());
+    for t in strs {
+        let next = ids.len();
+        let id = *ids.entry(t.as_raw()).or_insert_with(|| {
+            dict.push(*t);
+            next
+        });
+        indexes.push(id);
+    }
+    let mut buf = Vec::new();
+    put_varint(&mut buf, dict.len());
+    for t in &dict {
+        buf.push(t.len() as u8);
+        buf.extend_from_slice(t.as_bytes());
+    }
+    put_varint(&mut buf, indexes.len());
+    for id in indexes {
+        put_varint(&mut buf, id);
+    }
+    buf
+}
+
+/// Decode a blob made by `encode_slice`, interning each distinct
+/// string once, straight out of `buf`, under a single lock. Malformed
+/// blobs, including those with bytes after the handles, fail with
+/// `Error::Decode`, and strings interned before the problem was found
+/// remain in the pool.
+pub fn decode_slice(mut buf: &[u8]) -> Result<Vec<Str>> {
+    let buf = &mut buf;
+    let n = take_varint(buf)?;
+    // every string takes at least a byte, so a bad count can't make
+    // us allocate more than the input
+    let mut dict = Vec::with_capacity(n.min(buf.len()));
+    {
+        let mut root = ROOT.lock();
+        for _ in 0..n {
+            let len = take(buf, 1)?[0] as usize;
+            dict.push(root.intern(str::from_utf8(take(buf, len)?)?)?);
+        }
+    }
+    let n = take_varint(buf)?;
+    let mut strs = Vec::with_capacity(n.min(buf.len()));
+    for _ in 0..n {
+        let id = take_varint(buf)?;
+        strs.push(*dict.get(id).ok_or(Error::Decode("unknown string id"))?);
+    }
+    if !buf.is_empty() {
+        return Err(Error::Decode("trailing bytes"));
+    }
+    Ok(strs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wire() {
+        let strs: Vec<Str> = ["wire a", "wire b", "wire a", "wire a", "wire c"]
+            .into_iter()
+            .map(|s| Str::try_from(s).unwrap())
+            .collect();
+        let mut enc = Encoder::new();
+        let mut buf = Vec::new();
+        for s in &strs {
+            enc.encode(*s, &mut buf);
+        }
+        assert_eq!(enc.len(), 3);
+        assert_eq!(enc.id(strs[4]), Some(2));
+        let mut dec = Decoder::new();
+        let mut rd = &buf[..];
+        for s in &strs {
+            assert_eq!(dec.decode(&mut rd).unwrap().as_raw(), s.as_raw());
+        }
+        assert!(rd.is_empty());
+        assert!(matches!(dec.decode(&mut rd), Err(Error::Decode(_))));
+        assert!(matches!(
+            dec.decode(&mut &[0, 9, 0, 0, 0][..]),
+            Err(Error::Decode(_))
+        ));
+        assert_eq!(frame_len(&buf), Some(8));
+        assert_eq!(frame_len(&buf[..7]), None);
+        // a definition that fails leaves the ids after it unknown
+        let mut dec = Decoder::new();
+        let bad = [DEF, 1, b'a', DEF, 1, 0xff, REF, 0, 0, 0, 0];
+        let mut rd = &bad[..];
+        assert_eq!(&*dec.decode(&mut rd).unwrap(), "a");
+        assert!(dec.decode(&mut rd).is_err());
+        assert!(dec.is_failed());
+        assert_eq!(rd, [REF, 0, 0, 0, 0]);
+        // id 0 is known, but the decoder can't trust it any more
+        assert!(matches!(dec.decode(&mut rd), Err(Error::Decode(_))));
+        assert_eq!(dec.len(), 1);
+    }
+
+    #[test]
+    fn test_slice() {
+        let words: Vec<Str> = (0..1000)
+            .map(|i| Str::try_from(format!("slice word {}", i % 200).as_str()).unwrap())
+            .collect();
+        let buf = encode_slice(&words);
+        // 200 definitions of 12 to 14 bytes, and a byte or two per handle
+        assert!(buf.len() < 200 * 14 + 2 * 1000 + 4);
+        let got = decode_slice(&buf).unwrap();
+        assert_eq!(got.len(), words.len());
+        assert!(got
+            .iter()
+            .zip(&words)
+            .all(|(a, b)| a.as_raw() == b.as_raw()));
+        assert_eq!(decode_slice(&encode_slice(&[])).unwrap(), []);
+        assert_eq!(encode_slice(&[]), [0, 0]);
+        let mut long = vec![0; 3];
+        put_varint(&mut long, 300);
+        assert_eq!(long[3..], [0xac, 0x02]);
+        assert_eq!(take_varint(&mut &long[3..]).unwrap(), 300);
+        for bad in [
+            &buf[..buf.len() - 1],
+            &[1, 1, b'a', 1, 1][..],
+            &[0, 0, 0],
+            &[1, 1, 0xff, 0],
+            &[0xff; 12],
+        ] {
+            assert!(decode_slice(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[cfg(feature = "tokio-util")]
+    #[test]
+    fn test_codec() {
+        use tokio_util::codec::{Decoder as _, Encoder as _};
+        let a = Str::try_from("codec a").unwrap();
+        let mut codec = Codec::new();
+        let mut buf = bytes::BytesMut::new();
+        codec.encode(a, &mut buf).unwrap();
+        codec.encode(a, &mut buf).unwrap();
+        let mut rx = Codec::new();
+        let mut partial = buf.split_to(3);
+        assert!(rx.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(buf);
+        assert_eq!(rx.decode(&mut partial).unwrap(), Some(a));
+        assert_eq!(rx.decode(&mut partial).unwrap(), Some(a));
+        assert!(partial.is_empty());
+        let mut bad = bytes::BytesMut::from(&[DEF, 1, 0xff, REF, 0, 0, 0, 0][..]);
+        assert!(rx.decode(&mut bad).is_err());
+        assert!(rx.decode(&mut bad).is_err());
+        assert!(rx.decode(&mut bytes::BytesMut::new()).is_err());
+    }
+}
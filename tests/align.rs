@@ -0,0 +1,49 @@
+//! Checks that entries are padded as `set_entry_alignment` asks. This
+//! runs in its own process, so the padding doesn't affect any other
+//! test.
+
+use hcstatic_str::{chunk_report, generation, new_since, set_entry_alignment, Str};
+use std::panic;
+
+#[test]
+fn entry_alignment() {
+    let before = generation();
+    let packed: Vec<Str> = (0..100)
+        .map(|i| Str::try_from(format!("packed {i}").as_str()).unwrap())
+        .collect();
+    let wasted: usize = chunk_report().iter().map(|c| c.wasted).sum();
+    for align in [16, 64] {
+        set_entry_alignment(align);
+        let strs: Vec<Str> = (0..1000)
+            .map(|i| Str::try_from(format!("aligned {align} {i}").as_str()).unwrap())
+            .collect();
+        if !cfg!(feature = "forbid-unsafe") {
+            assert!(strs
+                .iter()
+                .all(|t| (t.as_ptr() as usize).is_multiple_of(align)));
+        }
+        let long = format!("{align:a>255}");
+        let t = Str::try_from(long.as_str()).unwrap();
+        if !cfg!(feature = "forbid-unsafe") {
+            assert_eq!(t.as_ptr() as usize % align, 0);
+        }
+        assert_eq!(Str::try_from_raw(strs[1].as_raw()), Some(strs[1]));
+    }
+    set_entry_alignment(1);
+    Str::try_from("packed again").unwrap();
+    // walking the chunks skips the padding
+    let (all, _) = new_since(before);
+    assert_eq!(&all[..100], &packed[..]);
+    assert_eq!(all.len(), 100 + 2 * 1001 + 1);
+    assert!(all
+        .iter()
+        .all(|t| Str::try_from(t.as_static()).unwrap() == *t));
+    let after: usize = chunk_report().iter().map(|c| c.wasted).sum();
+    assert!(after > wasted + 1000 * 8);
+    for c in chunk_report() {
+        assert!(c.used + c.wasted <= c.capacity);
+    }
+    for align in [0, 3, 128] {
+        assert!(panic::catch_unwind(|| set_entry_alignment(align)).is_err());
+    }
+}
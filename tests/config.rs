@@ -0,0 +1,26 @@
+//! Checks configuring the pool from the environment. Integration tests
+//! run in their own process, so the pool here hasn't been used yet.
+
+use hcstatic_str::{configure_from_env, stats, Error, Str};
+use std::env;
+
+#[test]
+fn configure() {
+    env::set_var("HCSTATIC_STR_MEMORY_LIMIT", "64K");
+    env::set_var("HCSTATIC_STR_CHUNK_SIZE", "8k");
+    env::set_var("HCSTATIC_STR_HASH_SEED", "random");
+    env::set_var("HCSTATIC_STR_SHARDS", "8");
+    configure_from_env().unwrap();
+    let err = (0..)
+        .map(|i| Str::try_from(format!("config {i:0>240}").as_str()))
+        .find_map(|r| r.err())
+        .unwrap();
+    assert!(matches!(err, Error::CapacityExceeded));
+    let st = stats();
+    assert!(st.allocated <= 64 << 10);
+    assert!(st.chunks > 8);
+    // the seed can't change once the pool is in use
+    assert!(matches!(configure_from_env(), Err(Error::Config(_))));
+    env::set_var("HCSTATIC_STR_ALIGN", "many");
+    assert!(matches!(configure_from_env(), Err(Error::Config(_))));
+}
@@ -0,0 +1,34 @@
+//! Checks that `intern_at_startup!` interns its strings before `main`.
+//! This runs in its own process, so nothing else has used the pool.
+#![cfg(feature = "ctor")]
+
+use hcstatic_str::{intern_at_startup, new_since, Generation, LazyStr, Str};
+
+intern_at_startup! {
+    /// registered by a plugin
+    pub static PLUGIN = "startup plugin name";
+    static SHORT = "s1";
+}
+
+// a second use in the same module
+intern_at_startup! {
+    static OTHER = "startup other";
+}
+
+static LATER: LazyStr = LazyStr::new("startup later");
+
+#[test]
+fn intern_at_startup() {
+    assert!(PLUGIN.is_interned() && SHORT.is_interned() && OTHER.is_interned());
+    assert!(!LATER.is_interned());
+    let (before, _) = new_since(Generation::default());
+    assert!(before.iter().any(|t| &**t == "startup plugin name"));
+    assert_eq!(*PLUGIN, Str::try_from("startup plugin name").unwrap());
+    assert_eq!(SHORT.get(), Str::try_from("s1").unwrap());
+    assert_eq!(&**LATER, "startup later");
+    assert!(LATER.is_interned());
+    assert_eq!(
+        format!("{LATER} {LATER:?}"),
+        "startup later \"startup later\""
+    );
+}
@@ -0,0 +1,25 @@
+//! Freezing affects the whole pool, so it is tested in its own
+//! process.
+
+use hcstatic_str::{freeze, is_frozen, Error, Str};
+
+#[test]
+fn frozen() {
+    let strs: Vec<Str> = (0..50_000)
+        .map(|i| Str::try_from(format!("frozen {i}").as_str()).unwrap())
+        .collect();
+    assert!(!is_frozen());
+    freeze();
+    assert!(is_frozen());
+    for (i, s) in strs.iter().enumerate() {
+        assert_eq!(Str::try_from(format!("frozen {i}").as_str()).unwrap(), *s);
+    }
+    assert_eq!(&*Str::try_from("7").unwrap(), "7");
+    assert!(matches!(Str::try_from("not frozen"), Err(Error::Frozen)));
+    assert!(matches!(
+        Str::from_static("not frozen either, and long"),
+        Err(Error::Frozen)
+    ));
+    assert!(Str::try_intern_nonblocking("not frozen").is_none());
+    freeze();
+}
@@ -0,0 +1,54 @@
+//! Checks each memory limit policy. Integration tests run in their own
+//! process, so the limit set here doesn't affect any other test.
+
+use hcstatic_str::{set_limit_policy, set_memory_limit, Error, LimitPolicy, MaybeInterned, Str};
+use std::{
+    panic,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static ALERTS: AtomicUsize = AtomicUsize::new(0);
+
+fn alert(s: &str) {
+    assert!(s.starts_with("limit"));
+    ALERTS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn limit_policy() {
+    // only the first chunk may be allocated, fill it
+    set_memory_limit(0);
+    let s = (0..)
+        .map(|i| format!("limit {i:0>240}"))
+        .find(|s| Str::try_from(s.as_str()).is_err())
+        .unwrap();
+    assert!(matches!(
+        Str::try_from(s.as_str()),
+        Err(Error::CapacityExceeded)
+    ));
+    assert_eq!(Str::intern_or_borrow(&s), MaybeInterned::Borrowed(&s));
+    assert_eq!(&*Str::intern_or_borrow(&s), s);
+    // strings already interned are still found
+    let first = format!("limit {:0>240}", 0);
+    let t = Str::intern_or_borrow(&first);
+    assert_eq!(&*t, first);
+    assert!(t.interned().is_some());
+
+    set_limit_policy(LimitPolicy::Callback(alert));
+    assert!(matches!(
+        Str::try_from(s.as_str()),
+        Err(Error::CapacityExceeded)
+    ));
+    assert_eq!(ALERTS.load(Ordering::Relaxed), 1);
+
+    set_limit_policy(LimitPolicy::Panic);
+    assert!(panic::catch_unwind(|| Str::try_from(s.as_str())).is_err());
+    // the pool still works after the panic
+    set_limit_policy(LimitPolicy::Error);
+    assert!(matches!(
+        Str::try_from(s.as_str()),
+        Err(Error::CapacityExceeded)
+    ));
+    set_memory_limit(usize::MAX);
+    assert_eq!(&*Str::try_from(s.as_str()).unwrap(), s);
+}
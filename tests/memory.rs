@@ -0,0 +1,108 @@
+//! Checks that the layout of the pool keeps its promises about
+//! overhead. Integration tests run in their own process, so the pool
+//! here starts empty and the numbers are exact. Everything is in one
+//! test so nothing else touches the pool concurrently.
+
+use hcstatic_str::{chunk_report, set_max_chunk_size, stats, Str};
+
+/// The name hash and id stored after each entry with the name-hash
+/// and ids features
+const TRAILER: usize =
+    if cfg!(feature = "name-hash") { 8 } else { 0 } + if cfg!(feature = "ids") { 4 } else { 0 };
+
+fn intern_n(n: usize, len: usize, tag: &str) -> Vec<Str> {
+    (0..n)
+        .map(|i| {
+            let s = format!("{tag}{i:0>width$}", width = len - tag.len());
+            Str::try_from(s.as_str()).unwrap()
+        })
+        .collect()
+}
+
+/// The largest chunk by default, chunks are smaller on 32 and 16 bit
+/// targets
+const MAX_CHUNK: usize = match usize::BITS {
+    64 => 1 << 20,
+    32 => 64 << 10,
+    _ => 4 << 10,
+};
+
+/// Check that the first chunk is 4 KiB, and each one after it is sized
+/// as `max` allows from how fast the one before it filled, which only
+/// `deterministic` makes exact: each is then as large as all the ones
+/// before it
+fn check_growth(max: usize) {
+    let caps: Vec<usize> = chunk_report().iter().map(|c| c.capacity).collect();
+    assert_eq!(caps[0], 4096);
+    for i in 1..caps.len() {
+        if cfg!(feature = "deterministic") {
+            assert_eq!(caps[i], caps[..i].iter().sum::<usize>().clamp(4096, max));
+        } else {
+            assert!(caps[i] >= caps[i - 1].min(max), "{caps:?}");
+            assert!(caps[i] <= (caps[i - 1] * 4).min(max), "{caps:?}");
+        }
+    }
+}
+
+#[test]
+fn memory_overhead() {
+    // nothing is allocated until the first insert
+    let st = stats();
+    assert_eq!((st.strings, st.chunks, st.allocated), (0, 0, 0));
+    Str::try_from("a").unwrap();
+    assert_eq!(stats().chunks, 0);
+
+    // short strings cost 1 header byte each
+    intern_n(10_000, 16, "short");
+    let st = stats();
+    assert_eq!(st.strings, 10_000);
+    assert_eq!(st.used, 10_000 * 16);
+    assert_eq!(st.headers, 10_000 * (1 + TRAILER));
+    // the index holds a handle and a control byte per slot
+    assert!(st.index >= 10_000 * (std::mem::size_of::<Str>() + 1));
+    assert_eq!(st.overhead(), st.headers + st.slack + st.index);
+    assert_eq!(st.footprint(), st.allocated + st.index);
+    assert!(st.slack < (st.chunks - 1) * (17 + TRAILER));
+    check_growth(MAX_CHUNK);
+
+    // interning again costs nothing
+    intern_n(10_000, 16, "short");
+    let again = stats();
+    assert_eq!(
+        (again.strings, again.used, again.headers),
+        (st.strings, st.used, st.headers)
+    );
+    assert_eq!((again.chunks, again.free), (st.chunks, st.free));
+    #[cfg(feature = "counters")]
+    assert_eq!((again.hits, again.misses), (st.hits + 10_000, st.misses));
+
+    // strings at the maximum length cost 2 header bytes each
+    intern_n(100, 255, "long");
+    let st2 = stats();
+    assert_eq!(st2.used - st.used, 100 * 255);
+    assert_eq!(st2.headers - st.headers, 100 * (2 + TRAILER));
+
+    // filling many chunks only wastes the tail of each retired chunk,
+    // which is less than the largest entry
+    intern_n(20_000, 200, "fill");
+    let st3 = stats();
+    assert!(st3.chunks > 3);
+    assert!(st3.slack < (st3.chunks - 1) * (201 + TRAILER));
+    assert_eq!(st3.used + st3.headers + st3.slack + st3.free, st3.allocated);
+    check_growth(MAX_CHUNK);
+
+    // the maximum applies to the chunks allocated after it is set
+    set_max_chunk_size(8192);
+    intern_n(st3.free / 200 + 200, 200, "small max");
+    let caps: Vec<usize> = chunk_report().iter().map(|c| c.capacity).collect();
+    let before = st3.chunks;
+    assert!(caps.len() > before + 1);
+    assert!(caps[before..].iter().all(|c| *c == 8192));
+
+    // 1 word on the stack
+    assert_eq!(std::mem::size_of::<Str>(), std::mem::size_of::<usize>());
+    assert_eq!(
+        std::mem::size_of::<Option<Str>>(),
+        std::mem::size_of::<usize>()
+    );
+}
@@ -0,0 +1,57 @@
+//! Checks that chunks are locked once `set_lock_chunks` is on. This
+//! runs in its own process, so lowering the lock limit doesn't affect
+//! any other test.
+#![cfg(all(feature = "mlock", unix, not(feature = "forbid-unsafe")))]
+
+use hcstatic_str::{chunk_report, set_lock_chunks, Error, Str};
+
+/// The bytes this process has locked, from /proc on Linux
+fn locked() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmLck:"))?;
+    let kb: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Intern strings until a new chunk is needed, returning the error if
+/// it couldn't be allocated
+fn grow(tag: &str) -> Result<(), Error> {
+    let chunks = chunk_report().len();
+    for i in 0.. {
+        Str::try_from(format!("mlock {tag} {i:0>200}").as_str())?;
+        if chunk_report().len() > chunks {
+            return Ok(());
+        }
+    }
+    unreachable!()
+}
+
+#[test]
+fn lock_chunks() {
+    set_lock_chunks(true);
+    grow("a").unwrap();
+    grow("b").unwrap();
+    let allocated: usize = chunk_report().iter().map(|c| c.capacity).sum();
+    if let Some(locked) = locked() {
+        assert!(locked >= allocated, "{locked} < {allocated}");
+    }
+    // with no room left under the limit the next chunk can't be locked
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: libc::RLIM_INFINITY,
+    };
+    // SAFETY: limit is a valid rlimit
+    if unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit) } == 0 {
+        // mlock ignores the limit for privileged processes
+        if !is_root() {
+            assert!(matches!(grow("c"), Err(Error::AllocFailed)));
+        }
+    }
+    set_lock_chunks(false);
+    grow("d").unwrap();
+}
+
+fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions
+    unsafe { libc::geteuid() == 0 }
+}
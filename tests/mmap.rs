@@ -0,0 +1,73 @@
+//! Checks that `preload_mapped` references strings in the mapped file.
+//! The snapshot is written by a child process, so the strings in it
+//! aren't in this process's pool until they are preloaded.
+#![cfg(feature = "mmap")]
+
+use hcstatic_str::{preload_mapped, write_snapshot, Error, Result, Str};
+use std::{env, fs, path::Path, process::Command};
+
+const LONG: &str = "a mapped string long enough to be referenced in place";
+const VAR: &str = "HCSTATIC_STR_MMAP_SNAPSHOT";
+
+/// The range of addresses `path` is mapped at, from /proc on Linux
+#[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+fn mapped_at(path: &Path) -> Option<(usize, usize)> {
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+    let path = path.to_str()?;
+    let line = maps.lines().find(|l| l.ends_with(path))?;
+    let (start, end) = line.split_whitespace().next()?.split_once('-')?;
+    Some((
+        usize::from_str_radix(start, 16).ok()?,
+        usize::from_str_radix(end, 16).ok()?,
+    ))
+}
+
+fn preload(path: &Path) -> Result<usize> {
+    // SAFETY: every file this test maps is left as it is, and the bad
+    // ones are written to paths of their own
+    #[cfg(not(feature = "forbid-unsafe"))]
+    return unsafe { preload_mapped(path) };
+    #[cfg(feature = "forbid-unsafe")]
+    preload_mapped(path)
+}
+
+#[test]
+fn preload_mapped_snapshot() {
+    if let Ok(path) = env::var(VAR) {
+        Str::try_from(LONG).unwrap();
+        Str::try_from("mapped").unwrap();
+        write_snapshot(fs::File::create(path).unwrap()).unwrap();
+        return;
+    }
+    let path = env::temp_dir().join(format!("hcstatic-str-mmap-{}", std::process::id()));
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "preload_mapped_snapshot"])
+        .env(VAR, &path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(preload(&path).unwrap() >= 2);
+    let t = Str::try_from(LONG).unwrap();
+    assert_eq!(&*t, LONG);
+    assert_eq!(&*Str::try_from("mapped").unwrap(), "mapped");
+    #[cfg(all(target_os = "linux", not(feature = "forbid-unsafe")))]
+    {
+        let (start, end) = mapped_at(&path).unwrap();
+        let ptr = t.as_ptr() as usize;
+        assert!(
+            start <= ptr && ptr < end,
+            "{ptr:x} not in {start:x}-{end:x}"
+        );
+    }
+    // the mapped file must not change, so the bad ones are new files
+    let bad = path.with_extension("bad");
+    fs::write(&bad, b"not a snapshot").unwrap();
+    assert!(matches!(preload(&bad), Err(Error::Decode(_))));
+    let empty = path.with_extension("empty");
+    fs::write(&empty, b"").unwrap();
+    assert!(matches!(preload(&empty), Err(Error::Decode(_))));
+    for p in [&path, &bad, &empty] {
+        fs::remove_file(p).unwrap();
+    }
+    assert!(matches!(preload(&path), Err(Error::Io(_))));
+}
@@ -0,0 +1,48 @@
+//! Checks that new chunks get the NUMA policy that was set. This runs
+//! in its own process, so the policy doesn't affect any other test.
+#![cfg(all(feature = "numa", target_os = "linux", not(feature = "forbid-unsafe")))]
+
+use hcstatic_str::{chunk_report, set_numa_policy, NumaPolicy, Str};
+
+/// Intern strings until a new chunk is needed, returning one stored in
+/// it
+fn grow(tag: &str) -> Str {
+    let chunks = chunk_report().len();
+    (0..)
+        .map(|i| Str::try_from(format!("numa {tag} {i:0>200}").as_str()).unwrap())
+        .find(|_| chunk_report().len() > chunks)
+        .unwrap()
+}
+
+/// The policy mode of the page holding `t`
+fn mode(t: Str) -> libc::c_int {
+    let mut mode = -1;
+    // SAFETY: mode is a valid out pointer, and no node mask is asked for
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_get_mempolicy,
+            &mut mode as *mut libc::c_int,
+            std::ptr::null_mut::<libc::c_ulong>(),
+            0,
+            t.as_ptr(),
+            2, // MPOL_F_ADDR
+        )
+    };
+    assert_eq!(res, 0, "{}", std::io::Error::last_os_error());
+    mode
+}
+
+#[test]
+fn numa_policy() {
+    assert_eq!(mode(grow("a")), libc::MPOL_DEFAULT);
+    set_numa_policy(NumaPolicy::Bind(0)).unwrap();
+    assert_eq!(mode(grow("b")), libc::MPOL_BIND);
+    set_numa_policy(NumaPolicy::Interleave).unwrap();
+    assert_eq!(mode(grow("c")), libc::MPOL_INTERLEAVE);
+    // a node that doesn't exist is rejected, and the policy is kept
+    assert!(set_numa_policy(NumaPolicy::Bind(1000)).is_err());
+    assert!(set_numa_policy(NumaPolicy::Bind(100_000)).is_err());
+    assert_eq!(mode(grow("d")), libc::MPOL_INTERLEAVE);
+    set_numa_policy(NumaPolicy::Default).unwrap();
+    assert_eq!(mode(grow("e")), libc::MPOL_DEFAULT);
+}
@@ -0,0 +1,46 @@
+//! Checks that `set_recycle_slack` fills the tails of retired chunks
+//! with tiny strings. This runs in its own process, so the strings out
+//! of order don't affect any other test.
+
+use hcstatic_str::{chunk_report, new_since, set_recycle_slack, stats, Generation, Str};
+
+#[test]
+fn recycle_slack() {
+    let intern = |s: String| Str::try_from(s.as_str()).unwrap();
+    let mix = |tag: &str| -> Vec<Str> {
+        (0..2000)
+            .map(|i| match i % 4 {
+                0 => intern(format!("{tag} {i:0>200}")),
+                _ => intern(format!("{tag}{i}")),
+            })
+            .collect()
+    };
+    let packed = mix("p");
+    let slack = stats().slack;
+    assert!(slack > 0);
+    set_recycle_slack(true);
+    let (_, g) = new_since(Generation::default());
+    let recycled = mix("r");
+    let st = stats();
+    assert!(st.slack < slack, "{} >= {slack}", st.slack);
+    assert_eq!(st.used + st.headers + st.slack + st.free, st.allocated);
+    assert_eq!(
+        chunk_report().iter().map(|c| c.used).sum::<usize>(),
+        st.used
+    );
+    // every string is found again, and new_since returns each string
+    // written to a tail exactly once
+    for t in packed.iter().chain(&recycled) {
+        assert_eq!(Str::try_from(t.as_static()).unwrap(), *t);
+        assert_eq!(Str::try_from_raw(t.as_raw()), Some(*t));
+    }
+    let (mut new, g1) = new_since(g);
+    new.sort();
+    let mut want = recycled.clone();
+    want.sort();
+    assert_eq!(new, want);
+    assert!(g1 > g);
+    let t = intern("r tiny".into());
+    assert_eq!(new_since(g1).0, [t]);
+    set_recycle_slack(false);
+}
@@ -0,0 +1,56 @@
+//! Checks that deserializing MessagePack interns strings straight from
+//! the buffer. Integration tests run in their own process, so the
+//! allocator here only counts this test.
+#![cfg(feature = "rmp")]
+
+use hcstatic_str::{rmp, Str};
+use serde::{Deserialize, Serialize};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[derive(Serialize)]
+struct Sent<'a> {
+    host: &'a str,
+    service: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Received {
+    host: Str,
+    service: Str,
+}
+
+#[test]
+fn zero_alloc() {
+    let buf = rmp_serde::to_vec(&Sent {
+        host: "ingest-01",
+        service: "telemetry",
+    })
+    .unwrap();
+    let first: Received = rmp::from_slice(&buf).unwrap();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let again: Received = rmp::from_slice(&buf).unwrap();
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), before);
+    assert_eq!((&*again.host, &*again.service), ("ingest-01", "telemetry"));
+    assert_eq!(again.host.as_raw(), first.host.as_raw());
+}
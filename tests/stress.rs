@@ -0,0 +1,69 @@
+//! Interns overlapping sets of strings from many threads at once and
+//! checks that the pool stays consistent. Integration tests run in
+//! their own process, so the pool here holds only these strings.
+#![cfg(not(feature = "single-threaded"))]
+
+use hcstatic_str::{stats, Str};
+use rand::{seq::SliceRandom, thread_rng};
+use std::{collections::HashMap, sync::Barrier, thread};
+
+const THREADS: usize = 8;
+const STRINGS: usize = 20_000;
+
+/// The name hash and id stored after each entry with the name-hash
+/// and ids features
+const TRAILER: usize =
+    if cfg!(feature = "name-hash") { 8 } else { 0 } + if cfg!(feature = "ids") { 4 } else { 0 };
+
+/// String `i`, whose length cycles through every length the pool
+/// stores in entries, so entries straddle chunk boundaries
+fn string(i: usize) -> String {
+    let s = format!("stress {i} ");
+    let len = s.len() + i % (Str::MAX_LEN + 1 - s.len());
+    format!("{s:x<len$}")
+}
+
+#[test]
+fn stress() {
+    let barrier = Barrier::new(THREADS);
+    let results: Vec<Vec<(usize, Str)>> = thread::scope(|sc| {
+        let threads: Vec<_> = (0..THREADS)
+            .map(|n| {
+                let barrier = &barrier;
+                sc.spawn(move || {
+                    // each thread takes an overlapping window of the
+                    // strings, in its own random order
+                    let start = n * STRINGS / (2 * THREADS);
+                    let mut ids: Vec<usize> = (start..start + STRINGS / 2).collect();
+                    ids.shuffle(&mut thread_rng());
+                    barrier.wait();
+                    let mut got = Vec::with_capacity(ids.len());
+                    for i in ids {
+                        got.push((i, Str::try_from(string(i).as_str()).unwrap()));
+                        // re-read earlier strings while others insert
+                        let (j, t) = got[got.len() / 2];
+                        assert_eq!(&*t, string(j));
+                    }
+                    got
+                })
+            })
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    let mut seen: HashMap<usize, Str> = HashMap::new();
+    for (i, t) in results.into_iter().flatten() {
+        assert_eq!(&*t, string(i));
+        assert_eq!(seen.entry(i).or_insert(t).as_raw(), t.as_raw());
+    }
+    let st = stats();
+    let lens = seen.keys().map(|i| string(*i).len());
+    assert_eq!(st.strings, seen.len());
+    assert_eq!(st.used, lens.clone().sum::<usize>());
+    assert_eq!(
+        st.headers,
+        lens.map(|l| TRAILER + if l == Str::MAX_LEN { 2 } else { 1 })
+            .sum::<usize>()
+    );
+    assert!(st.chunks > 1);
+    assert_eq!(st.used + st.headers + st.slack + st.free, st.allocated);
+}